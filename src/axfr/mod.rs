@@ -0,0 +1,247 @@
+use colored::*;
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use trust_dns_resolver::proto::op::{Message, MessageType, OpCode, Query, ResponseCode};
+use trust_dns_resolver::proto::rr::{Name, RData, RecordType};
+use trust_dns_resolver::proto::serialize::binary::BinEncodable;
+use crate::scanner::Scanner;
+
+/// Bound for one whole transfer, connection included.
+pub const DEFAULT_AXFR_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+pub enum AxfrError {
+    ResolverError(String),
+    NetworkError(String),
+    /// The server answered with an error code, usually REFUSED or NOTAUTH
+    Refused(ResponseCode),
+    InvalidResponse(String),
+    Timeout(Duration),
+}
+
+impl std::fmt::Display for AxfrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AxfrError::ResolverError(e) => write!(f, "Resolver error: {}", e),
+            AxfrError::NetworkError(e) => write!(f, "Network error: {}", e),
+            AxfrError::Refused(code) => write!(f, "zone transfer refused ({})", code),
+            AxfrError::InvalidResponse(e) => write!(f, "invalid zone transfer response: {}", e),
+            AxfrError::Timeout(d) => write!(f, "zone transfer timed out after {:?}", d),
+        }
+    }
+}
+
+impl Error for AxfrError {}
+
+pub struct ZoneTransfer<'a> {
+    scanner: &'a Scanner,
+    timeout: Duration,
+}
+
+impl<'a> ZoneTransfer<'a> {
+    /// Find the nameservers through `scanner`, so the NS and address lookups share its
+    /// resolvers, rate limit and timeout. `timeout` bounds each transfer.
+    pub fn new(scanner: &'a Scanner, timeout: Duration) -> Self {
+        ZoneTransfer { scanner, timeout }
+    }
+
+    /// Ask every nameserver in the domain's NS records for a zone transfer and merge
+    /// the names of all that allow one. Refusals, the usual outcome, are only logged.
+    pub async fn fetch_subdomains(&self, domain: &str) -> Result<Vec<String>, AxfrError> {
+        let mut subdomains = BTreeSet::new();
+        for (host, addrs) in self.nameservers(domain).await? {
+            if addrs.is_empty() {
+                self.warn(&format!("{}: no addresses", host));
+            }
+            for addr in addrs {
+                match transfer(SocketAddr::new(addr, 53), domain, self.timeout).await {
+                    Ok(names) => {
+                        self.success(&format!("{} ({}) allowed a zone transfer: {} names", host, addr, names.len()));
+                        subdomains.extend(names);
+                        break;
+                    }
+                    Err(e) => self.warn(&format!("{} ({}): {}", host, addr, e)),
+                }
+            }
+        }
+        Ok(subdomains.into_iter().collect())
+    }
+
+    /// The domain's NS hosts and the addresses each resolves to.
+    async fn nameservers(&self, domain: &str) -> Result<Vec<(String, Vec<IpAddr>)>, AxfrError> {
+        let records = self
+            .scanner
+            .lookup_records(domain, RecordType::NS)
+            .await
+            .map_err(|e| AxfrError::ResolverError(e.to_string()))?;
+
+        let mut nameservers = Vec::new();
+        for record in records {
+            let RData::NS(nameserver) = record else { continue };
+            let host = nameserver.0.to_utf8().trim_end_matches('.').to_lowercase();
+            let addrs = self.scanner.resolve(&host).await;
+            nameservers.push((host, addrs));
+        }
+        Ok(nameservers)
+    }
+
+    fn success(&self, message: &str) {
+        say!("{} {}", "[+]".green(), message);
+    }
+
+    fn warn(&self, message: &str) {
+        say!("{} {}", "[!]".yellow(), message);
+    }
+}
+
+/// Request an AXFR of `domain` from `server` over TCP and return the names under
+/// `domain` it lists, lowercased, deduplicated and sorted. Wildcard owners are skipped.
+pub async fn transfer(server: SocketAddr, domain: &str, timeout: Duration) -> Result<Vec<String>, AxfrError> {
+    tokio::time::timeout(timeout, transfer_records(server, domain))
+        .await
+        .map_err(|_| AxfrError::Timeout(timeout))?
+}
+
+async fn transfer_records(server: SocketAddr, domain: &str) -> Result<Vec<String>, AxfrError> {
+    let zone = Name::from_ascii(domain).map_err(|e| AxfrError::InvalidResponse(e.to_string()))?;
+    let mut query = Message::new();
+    query
+        .set_id(rand::random())
+        .set_message_type(MessageType::Query)
+        .set_op_code(OpCode::Query)
+        .add_query(Query::query(zone, RecordType::AXFR));
+    let query = query.to_bytes().map_err(|e| AxfrError::InvalidResponse(e.to_string()))?;
+
+    let network = |e: std::io::Error| AxfrError::NetworkError(e.to_string());
+    let mut stream = TcpStream::connect(server).await.map_err(network)?;
+    stream.write_all(&(query.len() as u16).to_be_bytes()).await.map_err(network)?;
+    stream.write_all(&query).await.map_err(network)?;
+
+    // The zone is framed by its SOA record, sent first and again last
+    let suffix = format!(".{}", domain.trim_end_matches('.').to_lowercase());
+    let mut names = BTreeSet::new();
+    let mut soa_count = 0;
+    while soa_count < 2 {
+        let mut len = [0u8; 2];
+        stream.read_exact(&mut len).await.map_err(network)?;
+        let mut buf = vec![0u8; u16::from_be_bytes(len) as usize];
+        stream.read_exact(&mut buf).await.map_err(network)?;
+        let message = Message::from_vec(&buf).map_err(|e| AxfrError::InvalidResponse(e.to_string()))?;
+
+        if message.response_code() != ResponseCode::NoError {
+            return Err(AxfrError::Refused(message.response_code()));
+        }
+        if message.answers().is_empty() {
+            return Err(AxfrError::InvalidResponse("message without records".to_string()));
+        }
+        for record in message.answers() {
+            if record.record_type() == RecordType::SOA {
+                soa_count += 1;
+            }
+            let name = record.name().to_utf8().trim_end_matches('.').to_lowercase();
+            if name.ends_with(&suffix) && !name.starts_with('*') {
+                names.insert(name);
+            }
+        }
+    }
+    Ok(names.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+    use trust_dns_resolver::proto::rr::{rdata, RData, Record};
+
+    /// Serve AXFR over TCP: `example.test` is transferred in two messages, anything
+    /// else is refused.
+    async fn axfr_server() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut len = [0u8; 2];
+                stream.read_exact(&mut len).await.unwrap();
+                let mut buf = vec![0u8; u16::from_be_bytes(len) as usize];
+                stream.read_exact(&mut buf).await.unwrap();
+                let query = Message::from_vec(&buf).unwrap();
+                let question = query.queries()[0].clone();
+
+                let name = |name: &str| Name::from_ascii(name).unwrap();
+                let a = |owner: &str| Record::from_rdata(name(owner), 60, RData::A(rdata::A([10, 0, 0, 1].into())));
+                let soa = Record::from_rdata(
+                    name("example.test."),
+                    60,
+                    RData::SOA(rdata::SOA::new(name("ns1.example.test."), name("admin.example.test."), 1, 60, 60, 60, 60)),
+                );
+                let cname = Record::from_rdata(
+                    name("Mail.example.test."),
+                    60,
+                    RData::CNAME(rdata::CNAME(name("mx.example.net."))),
+                );
+                let batches = if question.name() == &name("example.test.") {
+                    vec![vec![soa.clone(), a("www.example.test."), cname], vec![a("*.dev.example.test."), a("api.example.test."), soa]]
+                } else {
+                    vec![Vec::new()]
+                };
+                for records in batches {
+                    let mut response = Message::new();
+                    response.set_id(query.id()).set_message_type(MessageType::Response).add_query(question.clone());
+                    if records.is_empty() {
+                        response.set_response_code(ResponseCode::Refused);
+                    }
+                    response.add_answers(records);
+                    let bytes = response.to_bytes().unwrap();
+                    stream.write_all(&(bytes.len() as u16).to_be_bytes()).await.unwrap();
+                    stream.write_all(&bytes).await.unwrap();
+                }
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_nameservers_through_scan_resolvers() {
+        use crate::scanner::fake_dns::{self, a, code, records};
+
+        let port = fake_dns::serve(|question| match (question.name.as_str(), question.record_type) {
+            ("example.test.", RecordType::NS) => records(
+                ["ns1.example.test.", "ns2.example.test."]
+                    .map(|host| RData::NS(rdata::NS(Name::from_ascii(host).unwrap())))
+                    .to_vec(),
+            ),
+            ("ns1.example.test.", RecordType::A) => records(vec![a([127, 0, 0, 53])]),
+            ("example.test." | "ns1.example.test.", _) => records(Vec::new()),
+            _ => code(ResponseCode::NXDomain),
+        })
+        .await;
+        let resolvers = crate::scanner::parse_resolvers(&[format!("127.0.0.1:{}", port)]).unwrap();
+        let scanner = Scanner::with_resolvers(&resolvers, 10).await.unwrap();
+
+        let nameservers = ZoneTransfer::new(&scanner, DEFAULT_AXFR_TIMEOUT).nameservers("example.test").await.unwrap();
+        assert_eq!(
+            nameservers,
+            vec![
+                ("ns1.example.test".to_string(), vec!["127.0.0.53".parse::<IpAddr>().unwrap()]),
+                ("ns2.example.test".to_string(), Vec::new()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_transfer() {
+        let server = axfr_server().await;
+        assert_eq!(
+            transfer(server, "example.test", DEFAULT_AXFR_TIMEOUT).await.unwrap(),
+            vec!["api.example.test", "mail.example.test", "www.example.test"]
+        );
+        assert!(matches!(
+            transfer(server, "other.test", DEFAULT_AXFR_TIMEOUT).await,
+            Err(AxfrError::Refused(ResponseCode::Refused))
+        ));
+    }
+}
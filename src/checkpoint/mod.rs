@@ -0,0 +1,242 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+const BATCH_HEADER: &str = "# subtahu batch checkpoint";
+const SCAN_HEADER: &str = "# subtahu scan checkpoint";
+
+/// Records buffered before a scan checkpoint is flushed to disk.
+const SCAN_FLUSH_EVERY: usize = 50;
+
+#[derive(Debug)]
+pub enum CheckpointError {
+    IoError(PathBuf, String),
+    /// The scan checkpoint was written for other targets or wordlists
+    InputMismatch(PathBuf),
+}
+
+impl std::fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CheckpointError::IoError(path, e) => write!(f, "Checkpoint {}: {}", path.display(), e),
+            CheckpointError::InputMismatch(path) => write!(
+                f,
+                "Checkpoint {} was written for a different target or wordlist; delete it to start over",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl Error for CheckpointError {}
+
+/// Tracks which domains of a `--domains-file` batch have been fully scanned.
+///
+/// The file starts with a header carrying the hash of the domains file, followed by
+/// one completed domain per line. A checkpoint written for different input is ignored.
+pub struct BatchCheckpoint {
+    path: PathBuf,
+    completed: HashSet<String>,
+}
+
+impl BatchCheckpoint {
+    pub fn open(path: &Path, inputs_hash: u64) -> Result<Self, CheckpointError> {
+        let io_error = |e: std::io::Error| CheckpointError::IoError(path.to_path_buf(), e.to_string());
+        let header = format!("{} {:016x}", BATCH_HEADER, inputs_hash);
+
+        let mut completed = HashSet::new();
+        if path.exists() {
+            let mut lines = BufReader::new(File::open(path).map_err(io_error)?).lines();
+            if lines.next().transpose().map_err(io_error)?.as_deref() == Some(header.as_str()) {
+                for line in lines {
+                    let line = line.map_err(io_error)?;
+                    if !line.trim().is_empty() {
+                        completed.insert(line.trim().to_string());
+                    }
+                }
+            }
+        }
+
+        if completed.is_empty() {
+            std::fs::write(path, format!("{}\n", header)).map_err(io_error)?;
+        }
+
+        Ok(BatchCheckpoint {
+            path: path.to_path_buf(),
+            completed,
+        })
+    }
+
+    pub fn is_completed(&self, domain: &str) -> bool {
+        self.completed.contains(domain)
+    }
+
+    pub fn completed_count(&self) -> usize {
+        self.completed.len()
+    }
+
+    /// Record a finished domain, flushing it to disk immediately.
+    pub fn mark_completed(&mut self, domain: &str) -> Result<(), CheckpointError> {
+        let io_error = |e: std::io::Error| CheckpointError::IoError(self.path.clone(), e.to_string());
+        let mut file = OpenOptions::new().append(true).open(&self.path).map_err(io_error)?;
+        writeln!(file, "{}", domain).map_err(io_error)?;
+        self.completed.insert(domain.to_string());
+        Ok(())
+    }
+
+    /// Remove the checkpoint once the whole batch has been scanned.
+    pub fn finish(self) -> Result<(), CheckpointError> {
+        std::fs::remove_file(&self.path).map_err(|e| CheckpointError::IoError(self.path.clone(), e.to_string()))
+    }
+}
+
+/// One scanned candidate as stored in a scan checkpoint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckpointEntry {
+    pub status: String,
+    /// Whatever the scanner needs to restore the outcome, e.g. the result as JSON.
+    pub detail: Option<String>,
+}
+
+/// Tracks which candidates of a scan have been processed, so an interrupted
+/// `--checkpoint` scan can resume with only the remaining names.
+///
+/// The file starts with a header carrying the hash of the scan inputs, followed by one
+/// `name<TAB>status[<TAB>detail]` line per processed candidate. Writes are buffered and
+/// flushed every few records. Unlike a batch checkpoint, one written for different
+/// input is refused rather than overwritten, since it may hold hours of progress.
+pub struct ScanCheckpoint {
+    path: PathBuf,
+    entries: HashMap<String, CheckpointEntry>,
+    writer: BufWriter<File>,
+    unflushed: usize,
+}
+
+impl ScanCheckpoint {
+    pub fn open(path: &Path, inputs_hash: u64) -> Result<Self, CheckpointError> {
+        let io_error = |e: std::io::Error| CheckpointError::IoError(path.to_path_buf(), e.to_string());
+        let header = format!("{} {:016x}", SCAN_HEADER, inputs_hash);
+
+        let mut entries = HashMap::new();
+        if path.exists() {
+            let mut lines = BufReader::new(File::open(path).map_err(io_error)?).lines();
+            let first = lines.next().transpose().map_err(io_error)?;
+            if first.as_deref().is_some_and(|first| first.starts_with(SCAN_HEADER) && first != header) {
+                return Err(CheckpointError::InputMismatch(path.to_path_buf()));
+            }
+            if first.as_deref() == Some(header.as_str()) {
+                for line in lines {
+                    let line = line.map_err(io_error)?;
+                    let mut fields = line.splitn(3, '\t');
+                    if let (Some(name), Some(status)) = (fields.next(), fields.next()) {
+                        let entry = CheckpointEntry {
+                            status: status.to_string(),
+                            detail: fields.next().map(str::to_string),
+                        };
+                        entries.insert(name.to_string(), entry);
+                    }
+                }
+            }
+        }
+        let file = if entries.is_empty() {
+            let mut file = File::create(path).map_err(io_error)?;
+            writeln!(file, "{}", header).map_err(io_error)?;
+            file
+        } else {
+            OpenOptions::new().append(true).open(path).map_err(io_error)?
+        };
+
+        Ok(ScanCheckpoint {
+            path: path.to_path_buf(),
+            entries,
+            writer: BufWriter::new(file),
+            unflushed: 0,
+        })
+    }
+
+    /// The candidates recorded so far, by name.
+    pub fn entries(&self) -> &HashMap<String, CheckpointEntry> {
+        &self.entries
+    }
+
+    /// Record a processed candidate. Tabs and newlines in `detail` are replaced by spaces.
+    pub fn record(&mut self, name: &str, status: &str, detail: Option<&str>) -> Result<(), CheckpointError> {
+        let detail = detail.map(|detail| detail.replace(['\t', '\n', '\r'], " "));
+        let line = match &detail {
+            Some(detail) => format!("{}\t{}\t{}", name, status, detail),
+            None => format!("{}\t{}", name, status),
+        };
+        writeln!(self.writer, "{}", line).map_err(|e| self.io_error(e))?;
+        self.entries.insert(name.to_string(), CheckpointEntry { status: status.to_string(), detail });
+
+        self.unflushed += 1;
+        if self.unflushed >= SCAN_FLUSH_EVERY {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), CheckpointError> {
+        self.unflushed = 0;
+        self.writer.flush().map_err(|e| self.io_error(e))
+    }
+
+    /// Remove the checkpoint once every target has been scanned.
+    pub fn finish(self) -> Result<(), CheckpointError> {
+        std::fs::remove_file(&self.path).map_err(|e| CheckpointError::IoError(self.path.clone(), e.to_string()))
+    }
+
+    fn io_error(&self, e: std::io::Error) -> CheckpointError {
+        CheckpointError::IoError(self.path.clone(), e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_checkpoint_resume() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("batch.checkpoint");
+
+        let mut checkpoint = BatchCheckpoint::open(&path, 42).unwrap();
+        assert_eq!(checkpoint.completed_count(), 0);
+        checkpoint.mark_completed("example.com").unwrap();
+        checkpoint.mark_completed("example.org").unwrap();
+
+        let resumed = BatchCheckpoint::open(&path, 42).unwrap();
+        assert_eq!(resumed.completed_count(), 2);
+        assert!(resumed.is_completed("example.com"));
+        assert!(!resumed.is_completed("example.net"));
+
+        // A different domains file starts a fresh batch
+        let other = BatchCheckpoint::open(&path, 7).unwrap();
+        assert_eq!(other.completed_count(), 0);
+        other.finish().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_scan_checkpoint_resume() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scan.checkpoint");
+
+        let mut checkpoint = ScanCheckpoint::open(&path, 42).unwrap();
+        checkpoint.record("www.example.com", "valid", Some("{\"a\":\t1}")).unwrap();
+        checkpoint.record("old.example.com", "nxdomain", None).unwrap();
+        checkpoint.flush().unwrap();
+
+        // Progress made for other inputs is kept and the scan refused
+        assert!(matches!(ScanCheckpoint::open(&path, 7), Err(CheckpointError::InputMismatch(_))));
+
+        let resumed = ScanCheckpoint::open(&path, 42).unwrap();
+        assert_eq!(resumed.entries().len(), 2);
+        assert_eq!(resumed.entries()["www.example.com"].detail.as_deref(), Some("{\"a\": 1}"));
+        assert_eq!(resumed.entries()["old.example.com"].status, "nxdomain");
+        resumed.finish().unwrap();
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,71 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Turn the human-facing log lines and progress bars on or off. Off by default, so
+/// library users only get the returned results; the command-line tool turns it on.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Send the log lines to stderr instead of stdout, so stdout carries nothing but
+/// the program's machine-readable output.
+pub fn set_stderr(to_stderr: bool) {
+    TO_STDERR.store(to_stderr, Ordering::Relaxed);
+}
+
+/// Write one log line to stdout, or to stderr after `set_stderr(true)`. Used by `say!`.
+pub fn print(line: std::fmt::Arguments) {
+    let _ = if TO_STDERR.load(Ordering::Relaxed) {
+        writeln!(std::io::stderr().lock(), "{}", line)
+    } else {
+        writeln!(std::io::stdout().lock(), "{}", line)
+    };
+}
+
+/// A progress bar in the style shared by every scan phase, hidden when console
+/// output is disabled.
+pub fn progress_bar(total: u64, message: &'static str) -> ProgressBar {
+    quiet_progress_bar(!enabled(), total, message)
+}
+
+/// `progress_bar` for types that carry their own quiet setting: hidden when `quiet`,
+/// whatever `set_enabled` says.
+pub fn quiet_progress_bar(quiet: bool, total: u64, message: &'static str) -> ProgressBar {
+    if quiet {
+        return ProgressBar::hidden();
+    }
+    let progress = ProgressBar::new(total);
+    progress.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} ({eta}) {msg}")
+            .unwrap()
+            .progress_chars("#>-"),
+    );
+    progress.set_message(message);
+    progress
+}
+
+/// `println!`, but only when console output is enabled, and to stderr after
+/// `set_stderr(true)`. `say!(quiet = self.quiet; ...)`
+/// checks the given quiet setting instead, for types that carry their own.
+#[macro_export]
+macro_rules! say {
+    (quiet = $quiet:expr; $($arg:tt)*) => {
+        if !$quiet {
+            $crate::console::print(format_args!($($arg)*));
+        }
+    };
+    ($($arg:tt)*) => {
+        if $crate::console::enabled() {
+            $crate::console::print(format_args!($($arg)*));
+        }
+    };
+}
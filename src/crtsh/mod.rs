@@ -0,0 +1,148 @@
+use colored::*;
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::error::Error;
+
+#[derive(Debug)]
+pub enum CrtShError {
+    NetworkError(String),
+    HttpError(String),
+    /// crt.sh answered 429 Too Many Requests
+    RateLimited,
+    EmptyResponse,
+    InvalidJson(String),
+}
+
+impl std::fmt::Display for CrtShError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CrtShError::NetworkError(e) => write!(f, "Network error: {}", e),
+            CrtShError::HttpError(e) => write!(f, "HTTP error: {}", e),
+            CrtShError::RateLimited => write!(f, "crt.sh rate limit reached, try again later"),
+            CrtShError::EmptyResponse => write!(f, "crt.sh returned an empty body"),
+            CrtShError::InvalidJson(e) => write!(f, "crt.sh returned invalid JSON: {}", e),
+        }
+    }
+}
+
+impl Error for CrtShError {}
+
+/// One certificate as listed by crt.sh. `name_value` holds every name on the
+/// certificate, one per line.
+#[derive(Debug, Deserialize)]
+struct CertEntry {
+    name_value: String,
+}
+
+pub struct CrtSh {
+    client: reqwest::Client,
+}
+
+impl Default for CrtSh {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CrtSh {
+    pub fn new() -> Self {
+        CrtSh {
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub async fn fetch_subdomains(&self, domain: &str) -> Result<Vec<String>, CrtShError> {
+        self.info(&format!("Querying certificate transparency logs for: {}", domain));
+
+        let url = format!("https://crt.sh/?q=%25.{}&output=json", domain);
+        let response = self.client.get(&url)
+            .send()
+            .await
+            .map_err(|e| CrtShError::NetworkError(e.to_string()))?;
+
+        if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(CrtShError::RateLimited);
+        }
+        if !response.status().is_success() {
+            return Err(CrtShError::HttpError(format!(
+                "HTTP {} {}",
+                response.status().as_str(),
+                response.status().canonical_reason().unwrap_or("Unknown error")
+            )));
+        }
+
+        let body = response.text()
+            .await
+            .map_err(|e| CrtShError::NetworkError(e.to_string()))?;
+        let subdomains = parse_crtsh(&body, domain)?;
+
+        self.success(&format!("Found {} unique subdomains in certificates", subdomains.len()));
+        Ok(subdomains)
+    }
+
+    fn info(&self, message: &str) {
+        say!("{} {}", "[*]".blue(), message);
+    }
+
+    fn success(&self, message: &str) {
+        say!("{} {}", "[+]".green(), message);
+    }
+}
+
+/// Collect the names under `base_domain` from a crt.sh JSON body, with wildcard
+/// prefixes stripped, lowercased, deduplicated and sorted.
+fn parse_crtsh(body: &str, base_domain: &str) -> Result<Vec<String>, CrtShError> {
+    if body.trim().is_empty() {
+        return Err(CrtShError::EmptyResponse);
+    }
+    let entries: Vec<CertEntry> = serde_json::from_str(body).map_err(|e| CrtShError::InvalidJson(e.to_string()))?;
+
+    let suffix = format!(".{}", base_domain.trim_end_matches('.').to_lowercase());
+    let mut subdomains = BTreeSet::new();
+    for entry in &entries {
+        for name in entry.name_value.lines() {
+            let name = name.trim().trim_start_matches("*.").to_lowercase();
+            if name.ends_with(&suffix) {
+                subdomains.insert(name);
+            }
+        }
+    }
+    Ok(subdomains.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_crtsh() {
+        let body = r#"[
+            {"issuer_name": "R3", "name_value": "*.example.com\nexample.com"},
+            {"issuer_name": "R3", "name_value": "API.example.com\nwww.example.com"},
+            {"issuer_name": "R3", "name_value": "*.dev.example.com\nexample.community\nwww.example.com"}
+        ]"#;
+        assert_eq!(
+            parse_crtsh(body, "example.com").unwrap(),
+            vec!["api.example.com", "dev.example.com", "www.example.com"]
+        );
+
+        assert!(parse_crtsh("[]", "example.com").unwrap().is_empty());
+        assert!(matches!(parse_crtsh("", "example.com"), Err(CrtShError::EmptyResponse)));
+        assert!(matches!(parse_crtsh("<html>", "example.com"), Err(CrtShError::InvalidJson(_))));
+    }
+
+    /// Entries as returned by crt.sh, extra fields included.
+    const FIXTURE: &str = r#"[
+        {"issuer_ca_id":183267,"issuer_name":"C=US, O=Let's Encrypt, CN=R3","common_name":"shop.example.com","name_value":"shop.example.com\nwww.shop.example.com","id":9210433721,"entry_timestamp":"2023-04-02T10:11:12.345","not_before":"2023-04-02T09:11:12","not_after":"2023-07-01T09:11:11","serial_number":"04a1b2c3d4e5f6","result_count":3},
+        {"issuer_ca_id":183267,"issuer_name":"C=US, O=Let's Encrypt, CN=R3","common_name":"*.example.com","name_value":"*.example.com\nexample.com","id":9210433722,"entry_timestamp":"2023-04-02T10:11:13.001","not_before":"2023-04-02T09:11:13","not_after":"2023-07-01T09:11:12","serial_number":"04a1b2c3d4e5f7","result_count":2},
+        {"issuer_ca_id":16418,"issuer_name":"C=US, O=DigiCert Inc, CN=DigiCert TLS RSA SHA256 2020 CA1","common_name":"Mail.Example.com","name_value":"Mail.Example.com\nshop.example.com","id":8123456789,"entry_timestamp":"2022-11-20T08:00:00.000","not_before":"2022-11-20T00:00:00","not_after":"2023-11-20T23:59:59","serial_number":"0c9a8b7d6e5f","result_count":2}
+    ]"#;
+
+    #[test]
+    fn test_parse_crtsh_fixture() {
+        assert_eq!(
+            parse_crtsh(FIXTURE, "example.com").unwrap(),
+            vec!["mail.example.com", "shop.example.com", "www.shop.example.com"]
+        );
+    }
+}
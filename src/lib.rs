@@ -0,0 +1,46 @@
+//! Subdomain discovery as a library: brute force from wordlists, passive sources
+//! (Wayback Machine, crt.sh, TXT records, zone transfers), DNS validation and HTTP probing.
+//!
+//! Nothing is printed unless [`console::set_enabled`] is called, or `with_quiet(false)`
+//! on a [`Scanner`] or [`WaybackMachine`]; results and stats are returned as values.
+//! Errors the scanner cannot return, such as a failing checkpoint write, go to stderr.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let scanner = subtahu::Scanner::new(50).await?;
+//! let candidates = vec!["www.example.com".to_string(), "api.example.com".to_string()];
+//! let (results, stats) = scanner.scan_domains("example.com", candidates).await?;
+//! for result in &results {
+//!     println!("{} {:?}", result.subdomain, result.ips);
+//! }
+//! println!("{} of {} valid", stats.valid, stats.total);
+//! # Ok(())
+//! # }
+//! ```
+
+#[macro_use]
+pub mod console;
+pub mod axfr;
+pub mod checkpoint;
+pub mod crtsh;
+pub mod output;
+pub mod permute;
+pub mod probe;
+pub mod scanner;
+pub mod screenshot;
+pub mod sink;
+pub mod takeover;
+pub mod txt;
+pub mod utils;
+pub mod wayback;
+pub mod wordlist;
+
+pub use crtsh::{CrtSh, CrtShError};
+pub use output::{OutputFormat, Report};
+pub use probe::{HttpProber, ProbeResult};
+pub use scanner::{Candidate, ResultStatus, ScanError, ScanResult, ScanStats, Scanner, Source};
+pub use sink::{ResultSink, SinkError};
+pub use takeover::{TakeoverChecker, TakeoverFinding};
+pub use txt::TxtParser;
+pub use wayback::{WaybackError, WaybackMachine};
+pub use wordlist::{WordlistError, WordlistManager};
@@ -1,41 +1,407 @@
-mod scanner;
-mod utils;
-mod wayback;
+use subtahu::{
+    axfr, checkpoint, console, crtsh, output, permute, probe, say, scanner, screenshot, sink, takeover, txt, utils, wayback, wordlist,
+};
 
-use clap::Parser;
+use checkpoint::{BatchCheckpoint, ScanCheckpoint};
+use clap::{Parser, Subcommand};
 use colored::*;
-use scanner::Scanner;
+use output::{CsvField, Manifest, OutputFormat, OutputOptions, Report, DEFAULT_CSV_FIELDS};
+use scanner::{
+    Candidate, Intensity, ResolverHealth, ScanError, ScanResult, ScanStats, Scanner, Source, DEFAULT_TRUSTED_RESOLVERS,
+    HEALTH_CHECK_DOMAIN, WILDCARD_PROBES,
+};
+use serde::Deserialize;
+use sink::{FilteredSink, NdjsonSink, ResultSink, StdoutSink, WebhookSink};
+use std::collections::HashSet;
+use std::net::IpAddr;
 use std::path::PathBuf;
-use std::time::Instant;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use trust_dns_resolver::proto::rr::RecordType;
+use probe::HttpProber;
+use screenshot::Screenshotter;
+use takeover::TakeoverChecker;
+use txt::{EmailPosture, TxtParser};
 use wayback::WaybackMachine;
-use utils::extract_domain;
+use crtsh::CrtSh;
+use axfr::{ZoneTransfer, DEFAULT_AXFR_TIMEOUT};
+use permute::{DEFAULT_MAX_NUMBER, DEFAULT_SMART_CANDIDATES};
+use utils::{cidr_addresses, extract_domain, harvest_hostnames, hash_content, registrable_domain, to_unicode_domain};
+use wordlist::{
+    CharsetLabels, LabelCase, WordlistError, WordlistManager, CRITICAL_LABELS, DEFAULT_MAX_NESTED, DEFAULT_MAX_PERMUTATIONS,
+    DEFAULT_PERMUTATION_AFFIXES, DEFAULT_SKIP_CHARS, MAX_BRUTE_LEN,
+};
 
 #[derive(Parser, Debug)]
 #[command(
     name = "SubTahu",
     about = "A fast subdomain scanner with Wayback Machine integration for discovering historical subdomains",
-    version = "0.1.0"
+    version = "0.1.0",
+    subcommand_negates_reqs = true
 )]
 struct Args {
-    #[arg(short, long, help = "Target domain to scan (e.g., example.com)")]
-    domain: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[arg(short, long, required_unless_present_any = ["domains_file", "json_stdin"], help = "Target domain to scan (e.g., example.com)")]
+    domain: Option<String>,
+
+    #[arg(long, conflicts_with_all = ["domain", "domains_file"], help = "Read the scan request as JSON from stdin and write the report as JSON to stdout")]
+    json_stdin: bool,
+
+    #[arg(long, value_name = "FILE", conflicts_with = "domain", help = "Scan every domain listed in this file (one per line)")]
+    domains_file: Option<PathBuf>,
+
+    #[arg(long, requires = "domains_file", help = "Reduce input domains to their registrable apex and drop duplicates")]
+    collapse_to_apex: bool,
+
+    #[arg(long, value_name = "FILE", requires = "domains_file", help = "Record finished domains here and skip them when the batch is resumed")]
+    batch_checkpoint: Option<PathBuf>,
+
+    #[arg(long, visible_alias = "resume", value_name = "FILE", help = "Record every scanned candidate here and skip them when an interrupted scan is re-run")]
+    checkpoint: Option<PathBuf>,
+
+    #[arg(short, long, help = "Number of concurrent connections for scanning [default: from --intensity]")]
+    concurrency: Option<usize>,
+
+    #[arg(long, value_enum, default_value = "balanced", help = "Preset bundle of concurrency, timeout, jitter and rate settings")]
+    intensity: Intensity,
+
+    #[arg(long, value_name = "MS", help = "Timeout for each DNS query and connection attempt [default: from --intensity]")]
+    timeout_ms: Option<u64>,
+
+    #[arg(long, value_name = "N", help = "Retries after a failed or timed-out DNS query [default: from --intensity]")]
+    retries: Option<usize>,
+
+    #[arg(long = "retry-delay", value_name = "MS", default_value_t = 0, help = "Back off this long before the first retry, doubling each time (0: retry at once)")]
+    retry_delay_ms: u64,
+
+    #[arg(long, value_name = "MS", help = "Maximum random delay before each lookup [default: from --intensity]")]
+    jitter_ms: Option<u64>,
+
+    #[arg(long, value_name = "QPS", value_parser = clap::value_parser!(u32).range(1..), help = "Start at most this many lookups and port connects per second [default: from --intensity]")]
+    rate: Option<u32>,
+
+    #[arg(
+        long,
+        value_name = "TYPES",
+        value_delimiter = ',',
+        default_value = "A,AAAA",
+        value_parser = parse_record_type,
+        help = "Record types that make a subdomain valid when any has records (e.g. A,AAAA,CNAME,MX)"
+    )]
+    record_types: Vec<RecordType>,
+
+    #[arg(long, help = "Keep subdomains that only resolve to the domain's wildcard addresses")]
+    no_wildcard_filter: bool,
+
+    #[arg(long, value_name = "N", default_value_t = WILDCARD_PROBES, help = "Random labels resolved to detect wildcard DNS")]
+    wildcard_probes: usize,
+
+    #[arg(long, help = "List the DNS queries the scan would issue without sending them")]
+    dry_run: bool,
+
+    #[arg(short, long, action = clap::ArgAction::Count, help = "Increase output detail (-vvv explains every invalid result)")]
+    verbose: u8,
+
+    #[arg(long, value_name = "ADDRS|FILE", value_delimiter = ',', help = "Nameservers to use instead of the system configuration, as IP[:PORT] addresses or a file with one per line")]
+    resolvers: Vec<String>,
+
+    #[arg(long, help = "Give each configured nameserver its own disjoint share of candidates")]
+    shard_by_resolver: bool,
+
+    #[arg(long, conflicts_with = "shard_by_resolver", help = "Send each candidate to the next configured nameserver in turn")]
+    round_robin: bool,
+
+    #[arg(long, help = "Send DNS queries over TCP only, for networks where UDP is filtered or unreliable")]
+    tcp_only: bool,
+
+    #[arg(long, help = "Drop resolvers that fail a startup health check and blacklist those that start failing mid-scan")]
+    check_resolvers: bool,
+
+    #[arg(
+        long,
+        value_name = "RESOLVER",
+        num_args = 0..=1,
+        default_missing_value = "1.1.1.1",
+        help = "Confirm every NXDOMAIN with a second resolver [default: 1.1.1.1] and report disagreements"
+    )]
+    verify_nxdomain: Option<IpAddr>,
+
+    #[arg(
+        long,
+        value_name = "RESOLVERS",
+        num_args = 0..=1,
+        value_delimiter = ',',
+        default_missing_value = DEFAULT_TRUSTED_RESOLVERS,
+        help = "Confirm every valid subdomain with trusted resolvers [default: 8.8.8.8,1.1.1.1] and drop those they do not resolve"
+    )]
+    verify_trusted: Option<Vec<String>>,
+
+    #[arg(long, value_name = "ADDR", help = "Probe .onion candidates through this Tor SOCKS5 proxy (e.g. 127.0.0.1:9050) instead of skipping them")]
+    tor: Option<std::net::SocketAddr>,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["wordlist", "wordlist_dir", "wayback", "crtsh", "parse_txt", "try_axfr"],
+        help = "Quick health check: scan only the apex and a few critical names (www, mail, api)"
+    )]
+    apex_only: bool,
+
+    #[arg(short, long, value_name = "FILE", help = "Wordlist of subdomain labels to brute force (repeatable)")]
+    wordlist: Vec<PathBuf>,
+
+    #[arg(long, value_name = "DIR", help = "Load every .txt wordlist in this directory (repeatable)")]
+    wordlist_dir: Vec<PathBuf>,
+
+    #[arg(long, help = "Abort if any wordlist fails to load instead of skipping it")]
+    strict_wordlists: bool,
+
+    #[arg(long, value_name = "CHARS", default_value = DEFAULT_SKIP_CHARS, help = "Skip wordlist lines containing any of these characters (empty to disable)")]
+    skip_chars: String,
+
+    #[arg(long, value_enum, default_value = "lower", help = "Letter case of wordlist labels in candidates")]
+    label_case: LabelCase,
+
+    #[arg(long, conflicts_with = "stream_wordlists", help = "Scan the wordlist again under every subdomain found, see --depth")]
+    recursive: bool,
+
+    #[arg(long, value_name = "N", default_value_t = 1, requires = "recursive", value_parser = clap::value_parser!(u32).range(1..), help = "Levels --recursive descends below the first pass")]
+    depth: u32,
+
+    #[arg(long, conflicts_with = "permutations", help = "Read wordlists while scanning instead of loading them into memory first")]
+    stream_wordlists: bool,
+
+    #[arg(long, help = "Also try permutations of wordlist words (word-dev, dev-word, word.dev, word1)")]
+    permutations: bool,
+
+    #[arg(long, value_name = "N", default_value_t = 1, conflicts_with = "stream_wordlists", value_parser = clap::value_parser!(u32).range(1..=3), help = "Also build candidates of up to N wordlist labels (word1.word2.domain)")]
+    label_depth: u32,
+
+    #[arg(long, help = "Also try every label of up to --max-len characters from a-z, 0-9 and hyphen")]
+    charset_brute: bool,
+
+    #[arg(long, value_name = "N", default_value_t = 4, requires = "charset_brute", value_parser = clap::value_parser!(u32).range(1..=MAX_BRUTE_LEN as i64), help = "Longest label tried by --charset-brute")]
+    max_len: u32,
+
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_NESTED, help = "Stop generating --label-depth names after N labels")]
+    max_nested: usize,
+
+    #[arg(long, help = "Scan mutations of the found subdomains (api-dev, dev-api, api2) in a second pass")]
+    permute: bool,
 
-    #[arg(short, long, default_value = "50", help = "Number of concurrent connections for scanning")]
-    concurrency: usize,
+    #[arg(long, help = "Scan numbered variants of the found subdomains (app1 -> app2, web -> web01, web-01) in a second pass")]
+    mutate_numbers: bool,
+
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_NUMBER, requires = "mutate_numbers", help = "Highest number tried by --mutate-numbers")]
+    max_number: u32,
+
+    #[arg(long, help = "Scan new names proposed by a model trained on the found subdomains in a second pass")]
+    smart_gen: bool,
+
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_SMART_CANDIDATES, requires = "smart_gen", help = "Stop --smart-gen after N candidates")]
+    max_candidates: usize,
+
+    #[arg(long, value_name = "CIDR", value_parser = parse_cidr, help = "Reverse-resolve this address range and scan the in-scope hostnames found (e.g. 203.0.113.0/24); can be repeated")]
+    cidr: Vec<String>,
+
+    #[arg(long, help = "Reverse-resolve the found addresses and scan the in-scope hostnames their PTR records name")]
+    rdns: bool,
+
+    #[arg(long, requires = "rdns", help = "Reverse-resolve the whole /24 around each found IPv4 address")]
+    rdns_expand: bool,
+
+    #[arg(long, value_name = "LIST", value_delimiter = ',', default_value = DEFAULT_PERMUTATION_AFFIXES, help = "Affixes combined with each word by --permutations and --permute")]
+    permutation_affixes: Vec<String>,
+
+    #[arg(long, value_name = "FILE", help = "Add the affixes listed in FILE, one per line, to --permutation-affixes")]
+    affix_file: Option<PathBuf>,
+
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_PERMUTATIONS, help = "Stop generating permutations after N labels")]
+    max_permutations: usize,
 
     #[arg(short = 'b', long, help = "Use Wayback Machine to discover historical subdomains")]
     wayback: bool,
 
+    #[arg(long, value_name = "SECS", help = "Give up on the whole Wayback phase after this many seconds")]
+    wayback_timeout: Option<u64>,
+
+    #[arg(long, value_name = "SECS", help = "Timeout for each Wayback CDX request [default: 30]")]
+    wayback_request_timeout: Option<u64>,
+
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..), help = "Stop fetching Wayback results after N rows")]
+    wayback_limit: Option<u64>,
+
+    #[arg(long, value_name = "TIMESTAMP", value_parser = wayback::parse_timestamp, help = "Only use Wayback captures from this date on (YYYYMMDD[hhmmss])")]
+    wayback_from: Option<String>,
+
+    #[arg(long, value_name = "TIMESTAMP", value_parser = wayback::parse_timestamp, help = "Only use Wayback captures up to this date (YYYYMMDD[hhmmss])")]
+    wayback_to: Option<String>,
+
+    #[arg(long, visible_alias = "ctlogs", help = "Use certificate transparency logs (crt.sh) to discover subdomains")]
+    crtsh: bool,
+
+    #[arg(long, help = "Parse TXT records (SPF includes, verification records) for new subdomains")]
+    parse_txt: bool,
+
+    #[arg(long, help = "Request a zone transfer (AXFR) from each of the domain's nameservers")]
+    try_axfr: bool,
+
+    #[arg(long, visible_alias = "ports", value_name = "PORTS", value_delimiter = ',', help = "TCP-connect to these ports on each resolved IP (e.g. 80,443,22)")]
+    check_ports: Vec<u16>,
+
+    #[arg(long, value_name = "N", conflicts_with = "check_ports", value_parser = clap::value_parser!(u16).range(1..=100), help = "TCP-connect to the N most common ports (up to 100) on each resolved IP")]
+    top_ports: Option<u16>,
+
+    #[arg(long, visible_alias = "probe-http", help = "Request each valid subdomain over HTTP and HTTPS and report status codes, sizes and titles")]
+    probe: bool,
+
+    #[arg(long, value_name = "DIR", requires = "probe", help = "Save a headless Chrome screenshot of every live --probe page in DIR")]
+    screenshots: Option<PathBuf>,
+
+    #[arg(long, value_name = "PATH", requires = "screenshots", help = "Chrome or Chromium executable used by --screenshots")]
+    browser: Option<PathBuf>,
+
+    #[arg(long, help = "Only report subdomains with at least one IP that the apex does not resolve to")]
+    distinct_from_apex: bool,
+
+    #[arg(long, value_name = "SECS", help = "Flag subdomains whose DNS TTL is below this many seconds")]
+    min_ttl: Option<u32>,
+
+    #[arg(long, requires = "min_ttl", help = "Drop subdomains flagged by --min-ttl instead of only flagging them")]
+    drop_low_ttl: bool,
+
+    #[arg(long, help = "Drop subdomains whose addresses are all private or bogon (RFC 1918, loopback, CGNAT, ...)")]
+    exclude_private: bool,
+
+    #[arg(long, conflicts_with = "exclude_private", help = "Only report subdomains whose addresses are all private or bogon")]
+    only_private: bool,
+
+    #[arg(long, value_name = "N", default_value = "2", help = "Flag subdomains resolving to more than N addresses as load-balanced")]
+    min_ips_flag: usize,
+
+    #[arg(long, value_name = "SUFFIX", help = "Flag subdomains whose CNAME target ends with this suffix, e.g. github.io (repeatable)")]
+    match_cname: Vec<String>,
+
+    #[arg(long, value_name = "N", default_value = "10", help = "Stop following a CNAME chain after N hops")]
+    max_cname_depth: usize,
+
+    #[arg(long, help = "Flag names whose CNAME points at an unclaimed resource of a takeover-prone service")]
+    takeover_check: bool,
+
+    #[arg(long, value_name = "FILE", requires = "takeover_check", help = "CNAME suffixes to treat as takeover-prone, one per line, instead of the built-in list")]
+    takeover_fingerprints: Option<PathBuf>,
+
+    #[arg(long, help = "Report the apex's SPF, DMARC and common DKIM selector records")]
+    email_posture: bool,
+
+    #[arg(long, help = "Print a histogram of label lengths among valid findings")]
+    label_stats: bool,
+
     #[arg(short, long, help = "Save results to this output file")]
     output: Option<PathBuf>,
+
+    #[arg(short, long, visible_alias = "output-format", value_enum, default_value = "text", help = "Format used when saving results")]
+    format: OutputFormat,
+
+    #[arg(long, value_enum, value_delimiter = ',', default_value = DEFAULT_CSV_FIELDS, help = "Columns (and their order) for CSV output")]
+    csv_fields: Vec<CsvField>,
+
+    #[arg(long, value_name = "FILE", help = "Stream each finding to this file as newline-delimited JSON as soon as it is found, followed by its enriched version")]
+    stream_ndjson: Option<PathBuf>,
+
+    #[arg(long, conflicts_with = "json_stdin", help = "Print each finding's bare subdomain to stdout as soon as it is found")]
+    stream_stdout: bool,
+
+    #[arg(short, long, conflicts_with = "json_stdin", help = "Print only the valid subdomains, one per line, without banner, logs or progress bars")]
+    quiet: bool,
+
+    #[arg(long, value_name = "URL", help = "POST findings to this webhook in batches as they are found, followed by their enriched versions")]
+    webhook: Option<String>,
+
+    #[arg(long, value_name = "N", default_value = "50", requires = "webhook", help = "Number of events per webhook request")]
+    webhook_batch: usize,
+
+    #[arg(long, help = "Write one output row per (subdomain, IP) pair instead of one per subdomain")]
+    flatten_results: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Extract in-scope subdomains from arbitrary text (logs, HTML, JS) and print them, one per line
+    Harvest {
+        #[arg(short, long, help = "Only keep hostnames under this domain")]
+        domain: String,
+
+        #[arg(value_name = "FILE", help = "Files to read; stdin when none are given or for '-'")]
+        files: Vec<PathBuf>,
+    },
+}
+
+/// A scan request read by `--json-stdin`. Fields mirror the command line options.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonRequest {
+    domain: String,
+    #[serde(default)]
+    wordlists: Vec<PathBuf>,
+    #[serde(default)]
+    wordlist_dirs: Vec<PathBuf>,
+    /// Passive sources to query: `wayback`, `crtsh`, `txt`
+    #[serde(default)]
+    sources: Vec<String>,
+    concurrency: Option<usize>,
+    intensity: Option<Intensity>,
+    #[serde(default)]
+    check_ports: Vec<u16>,
+    #[serde(default)]
+    match_cname: Vec<String>,
+    /// Format written to stdout, `json` (the full report) unless given
+    format: Option<OutputFormat>,
+}
+
+impl JsonRequest {
+    fn apply(self, args: &mut Args) -> Result<(), String> {
+        args.domain = Some(self.domain);
+        args.wordlist = self.wordlists;
+        args.wordlist_dir = self.wordlist_dirs;
+        for source in &self.sources {
+            match source.as_str() {
+                "wayback" => args.wayback = true,
+                "crtsh" | "ctlogs" => args.crtsh = true,
+                "txt" => args.parse_txt = true,
+                "axfr" => args.try_axfr = true,
+                other => return Err(format!("Unknown source in JSON request: {}", other)),
+            }
+        }
+        args.concurrency = self.concurrency.or(args.concurrency);
+        args.intensity = self.intensity.unwrap_or(args.intensity);
+        args.check_ports = self.check_ports;
+        args.match_cname = self.match_cname;
+        args.format = self.format.unwrap_or(OutputFormat::Json);
+        args.output = None;
+        Ok(())
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Args::parse();
+    let mut args = Args::parse();
     let start_time = Instant::now();
+    console::set_enabled(!args.quiet);
 
-    println!("\n{}", r#"
+    if let Some(Command::Harvest { domain, files }) = &args.command {
+        return harvest(domain, files);
+    }
+
+    if args.json_stdin {
+        let request: JsonRequest = serde_json::from_reader(std::io::stdin())?;
+        request.apply(&mut args)?;
+        // Logs go to stderr so stdout carries only the report
+        console::set_stderr(true);
+    }
+
+    say!("\n{}", r#"
   █████████             █████     ███████████           █████                
  ███░░░░░███           ░░███     ░█░░░███░░░█          ░░███                 
 ░███    ░░░  █████ ████ ░███████ ░   ░███  ░   ██████   ░███████   █████ ████
@@ -49,49 +415,1127 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
                             by 0x1Jar
     "#.bright_blue().bold());
-    
-    // Extract and validate domain
-    let domain = extract_domain(&args.domain)
-        .ok_or("Invalid domain format")?;
-    println!("{} {}\n", "Target Domain:".yellow(), domain);
+
+    // Collect and validate target domains
+    let (domains, domains_hash) = match &args.domains_file {
+        Some(path) => {
+            let content = std::fs::read_to_string(path)?;
+            let mut domains = parse_domains(&content);
+            if args.collapse_to_apex {
+                domains = collapse_to_apex(domains);
+            }
+            if domains.is_empty() {
+                return Err(format!("No valid domains in {}", path.display()).into());
+            }
+            say!("{} {} domains from {}\n", "Targets:".yellow(), domains.len(), path.display());
+            (domains, hash_content(content.as_bytes()))
+        }
+        None => {
+            let domain = extract_domain(args.domain.as_deref().unwrap_or_default())
+                .ok_or("Invalid domain format")?;
+            say!("{} {}\n", "Target Domain:".yellow(), domain);
+            (vec![domain], 0)
+        }
+    };
+
+    load_affixes(&mut args)?;
+
+    if let Some(n) = args.top_ports {
+        args.check_ports = scanner::top_ports(n as usize);
+    }
 
     // Initialize scanner
-    let scanner = Scanner::new(args.concurrency).await?;
-    
-    // Fetch historical subdomains if wayback option is enabled
+    let profile = args.intensity.profile();
+    let concurrency = args.concurrency.unwrap_or(profile.concurrency);
+    let jitter = args.jitter_ms.map(Duration::from_millis).unwrap_or(profile.jitter);
+    let timeout = args.timeout_ms.map(Duration::from_millis).unwrap_or(profile.timeout);
+    let resolvers = scanner::parse_resolvers(&args.resolvers)?;
+    let trusted_resolvers = scanner::parse_resolvers(args.verify_trusted.as_deref().unwrap_or_default())?;
+    let takeover_fingerprints = match (&args.takeover_fingerprints, args.takeover_check) {
+        (Some(path), _) => scanner::parse_fingerprints(&std::fs::read_to_string(path)?),
+        (None, true) => takeover::cname_suffixes(),
+        (None, false) => Vec::new(),
+    };
+    let scan_checkpoint = match &args.checkpoint {
+        Some(path) => Some(ScanCheckpoint::open(path, scan_inputs_hash(&args, &domains))?),
+        None => None,
+    };
+    let scanner = if resolvers.is_empty() {
+        Scanner::new(concurrency).await?
+    } else {
+        say!("{} Using {} custom resolvers", "[*]".blue(), resolvers.len());
+        Scanner::with_resolvers(&resolvers, concurrency).await?
+    };
+    // The apex addresses of the current target, for --distinct-from-apex while streaming
+    let stream_apex_ips = Arc::new(RwLock::new(Vec::new()));
+    let mut scanner = scanner
+        .with_quiet(args.quiet)
+        .with_timing(timeout, jitter)
+        .with_tcp_only(args.tcp_only)
+        .with_retries(args.retries.unwrap_or(profile.retries))
+        .with_retry_delay(Duration::from_millis(args.retry_delay_ms))
+        .with_record_types(args.record_types.clone())
+        .with_wildcard_filter(!args.no_wildcard_filter)
+        .with_wildcard_probes(args.wildcard_probes)
+        .with_cname_lookup(!args.match_cname.is_empty() || args.takeover_check)
+        .with_max_cname_depth(args.max_cname_depth)
+        .with_resolver_shards(args.shard_by_resolver)
+        .with_round_robin(args.round_robin)
+        .with_resolver_blacklist(args.check_resolvers)
+        .with_verbosity(args.verbose)
+        .with_tor_proxy(args.tor)
+        .with_rate(args.rate.or(profile.rate))
+        .with_takeover_check(takeover_fingerprints)
+        .with_checkpoint(scan_checkpoint)
+        .with_nxdomain_verifier(args.verify_nxdomain)
+        .with_trusted_verifier(&trusted_resolvers)
+        .with_sinks(build_sinks(&args, stream_apex_ips.clone())?);
+
+    if args.check_resolvers {
+        say!("{} Checking resolver health", "[*]".blue());
+        for (addr, health) in scanner.check_resolvers(HEALTH_CHECK_DOMAIN).await? {
+            match health {
+                ResolverHealth::Healthy => say!("    {} {}", "✓".green(), addr),
+                health => say!("    {} {} {}", "✗".red(), addr, format!("({}, dropped)", health).dimmed()),
+            }
+        }
+    }
+
+    // Load wordlists once; candidates are built per target domain
+    let wordlists = load_wordlists(&args)?;
+
+    let mut checkpoint = match &args.batch_checkpoint {
+        Some(path) => {
+            let checkpoint = BatchCheckpoint::open(path, domains_hash)?;
+            if checkpoint.completed_count() > 0 {
+                say!(
+                    "{} Resuming batch, skipping {} completed domains",
+                    "[*]".blue(),
+                    checkpoint.completed_count()
+                );
+            }
+            Some(checkpoint)
+        }
+        None => None,
+    };
+
+    // Batch results are appended per domain; a resumed batch keeps the previous run's results.
+    // The JSON report is a single document, so it is assembled and written once at the end.
+    let batch = args.domains_file.is_some();
+    let json_report = args.format == OutputFormat::Json || args.json_stdin;
+    let resuming = checkpoint.as_ref().is_some_and(|c| c.completed_count() > 0);
+    if let (Some(output_path), true, false, false) = (&args.output, batch, resuming, json_report) {
+        std::fs::write(output_path, "")?;
+    }
+
+    let output_options = OutputOptions {
+        format: args.format,
+        csv_fields: args.csv_fields.clone(),
+        flatten: args.flatten_results,
+    };
+
+    let mut report_stats = ScanStats::default();
+    let mut report_results = Vec::new();
+    let mut email_postures = Vec::new();
+    // TXT lookups (--parse-txt, --email-posture) and the nameserver lookups of --try-axfr
+    // share the scan's resolvers and rate limit
+    let txt_parser = TxtParser::new(&scanner);
+    let zone_transfer = ZoneTransfer::new(&scanner, DEFAULT_AXFR_TIMEOUT);
+
+    for domain in &domains {
+        if checkpoint.as_ref().is_some_and(|c| c.is_completed(domain)) {
+            continue;
+        }
+        if domains.len() > 1 {
+            say!("\n{} {}", "Target Domain:".yellow(), domain);
+        }
+
+        // Resolved up front so that streamed findings are filtered against them too
+        let apex_ips = if args.distinct_from_apex { scanner.resolve(domain).await } else { Vec::new() };
+        stream_apex_ips.write().unwrap().clone_from(&apex_ips);
+
+        let scan = scan_domain(&args, &scanner, &txt_parser, &zone_transfer, wordlists.as_ref(), domain, &apex_ips);
+        let (results, stats) = match scan.await {
+            Ok(outcome) => outcome,
+            Err(e) if batch => {
+                eprintln!("Error scanning {}: {}", domain, e);
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+
+        if args.email_posture {
+            let posture = txt_parser.email_posture(domain).await;
+            print_email_posture(&posture);
+            email_postures.push(posture);
+        }
+
+        scanner.update_sinks(&results);
+        report_stats.merge(&stats);
+        if json_report {
+            report_results.extend(results);
+        } else if let (Some(output_path), false) = (&args.output, results.is_empty()) {
+            if batch {
+                output::append_results(output_path, &results, &output_options)?;
+            } else {
+                output::write_results(output_path, &results, &output_options)?;
+            }
+        }
+        if let Some(checkpoint) = checkpoint.as_mut() {
+            checkpoint.mark_completed(domain)?;
+        }
+    }
+
+    scanner.finish_sinks(&report_stats).await;
+
+    if args.flatten_results {
+        report_results = output::flatten_results(&report_results);
+    }
+    if args.json_stdin {
+        use std::io::Write;
+        let content = match args.format {
+            OutputFormat::Json => {
+                let manifest = Manifest::new(domains.clone(), enabled_phases(&args));
+                Report::new(manifest, report_stats, report_results)
+                    .with_email_posture(email_postures)
+                    .to_json()?
+            }
+            _ => output::format_results(&report_results, &output_options),
+        };
+        let mut stdout = std::io::stdout().lock();
+        writeln!(stdout, "{}", content)?;
+        stdout.flush()?;
+    } else if let (Some(output_path), true) = (&args.output, json_report) {
+        let manifest = Manifest::new(domains.clone(), enabled_phases(&args));
+        let report = Report::new(manifest, report_stats, report_results).with_email_posture(email_postures);
+        output::write_report(output_path, &report)?;
+    }
+
+    if let Some(checkpoint) = checkpoint {
+        checkpoint.finish()?;
+    }
+    scanner.finish_checkpoint()?;
+
+    let elapsed = start_time.elapsed();
+    say!("\n{}", "Scan Complete!".bright_blue().bold());
+    say!("Time elapsed: {:.2?}", elapsed);
+
+    Ok(())
+}
+
+/// Run the full discovery pipeline for a single target domain. `apex_ips` are the
+/// domain's own addresses, resolved only with `--distinct-from-apex`.
+async fn scan_domain(
+    args: &Args,
+    scanner: &Scanner,
+    txt_parser: &TxtParser<'_>,
+    zone_transfer: &ZoneTransfer<'_>,
+    wordlists: Option<&WordlistManager>,
+    domain: &str,
+    apex_ips: &[IpAddr],
+) -> Result<(Vec<ScanResult>, ScanStats), Box<dyn std::error::Error>> {
+    // Build brute-force candidates from the loaded wordlists
     let mut subdomains = Vec::new();
+    if args.apex_only {
+        subdomains.push(Candidate::from(domain.to_string()));
+        subdomains.extend(CRITICAL_LABELS.iter().map(|label| Candidate::from(format!("{}.{}", label, domain))));
+    }
+    if let Some(wordlists) = wordlists.filter(|_| !args.stream_wordlists) {
+        let build_start = Instant::now();
+        subdomains.extend(wordlists.build_candidates(domain, args.label_case).into_iter().map(Candidate::from));
+        say!(
+            "{} Built {} wordlist candidates in {:.2?}",
+            "[*]".blue(),
+            subdomains.len(),
+            build_start.elapsed()
+        );
+    }
+
+    // Fetch historical subdomains if wayback option is enabled
     if args.wayback {
-        println!("{}", "Fetching historical subdomains from Wayback Machine...".cyan());
-        let wayback = WaybackMachine::new();
-        match wayback.fetch_subdomains(&domain).await {
+        say!("{}", "Fetching historical subdomains from Wayback Machine...".cyan());
+        let wayback = WaybackMachine::new(args.wayback_timeout.map(Duration::from_secs))
+            .with_request_timeout(args.wayback_request_timeout.map(Duration::from_secs).unwrap_or(wayback::DEFAULT_REQUEST_TIMEOUT))
+            .with_row_limit(args.wayback_limit.map(|limit| limit as usize))
+            .with_time_range(args.wayback_from.as_deref(), args.wayback_to.as_deref())?
+            .with_quiet(args.quiet);
+        match wayback.fetch_subdomains(domain).await {
             Ok(historical_subdomains) => {
-                println!("Found {} historical subdomains", historical_subdomains.len());
-                subdomains.extend(historical_subdomains);
+                say!("Found {} historical subdomains", historical_subdomains.len());
+                subdomains.extend(historical_subdomains.into_iter().map(|name| Candidate::new(name, Source::Wayback)));
             }
             Err(e) => eprintln!("Error fetching from Wayback Machine: {}", e),
         }
     }
 
+    // Collect names from certificates issued for the domain
+    if args.crtsh {
+        say!("{}", "Fetching subdomains from certificate transparency logs...".cyan());
+        match CrtSh::new().fetch_subdomains(domain).await {
+            Ok(certificate_subdomains) => {
+                say!("Found {} subdomains in certificates", certificate_subdomains.len());
+                subdomains.extend(certificate_subdomains.into_iter().map(|name| Candidate::new(name, Source::Crtsh)));
+            }
+            Err(e) => eprintln!("Error fetching from crt.sh: {}", e),
+        }
+    }
+
+    // Misconfigured nameservers hand out the whole zone
+    if args.try_axfr {
+        say!("{}", "Attempting zone transfers...".cyan());
+        match zone_transfer.fetch_subdomains(domain).await {
+            Ok(transferred) => {
+                say!("Found {} subdomains in zone transfers", transferred.len());
+                subdomains.extend(transferred.into_iter().map(|name| Candidate::new(name, Source::Axfr)));
+            }
+            Err(e) => eprintln!("Error attempting zone transfers: {}", e),
+        }
+    }
+
+    // PTR records across the given address ranges
+    if !args.cidr.is_empty() {
+        let mut ips = Vec::new();
+        for cidr in &args.cidr {
+            ips.extend(cidr_addresses(cidr)?);
+        }
+        ips.sort();
+        ips.dedup();
+        let hostnames = scanner.reverse_sweep(&ips, domain).await;
+        say!("Found {} hostnames in reverse DNS of {}", hostnames.len(), args.cidr.join(", "));
+        subdomains.extend(hostnames.into_iter().map(|name| Candidate::new(name, Source::Rdns)));
+    }
+
+    // Names already queried, so deeper --recursive levels never repeat one
+    let mut scanned: HashSet<String> = if args.parse_txt || args.recursive || args.permute || args.mutate_numbers || args.smart_gen || args.rdns || args.probe {
+        subdomains.iter().map(|candidate| candidate.name.to_lowercase()).collect()
+    } else {
+        HashSet::new()
+    };
+
+    // Streamed wordlist candidates follow the others and are read as the scan consumes them
+    let mut total = (!args.stream_wordlists).then_some(subdomains.len());
+    let mut candidates: Box<dyn Iterator<Item = Candidate>> = Box::new(subdomains.into_iter());
+    if let Some(wordlists) = wordlists.filter(|_| args.stream_wordlists) {
+        let streamed = wordlists.stream_candidates(domain, args.label_case)?;
+        candidates = Box::new(candidates.chain(streamed.map(Candidate::from)));
+    }
+    // Charset labels are generated as the scan consumes them, after everything else
+    if args.charset_brute {
+        let count = CharsetLabels::count_labels(args.max_len as usize);
+        say!("{} {} charset labels of up to {} characters will be tried", "[*]".blue(), count, args.max_len);
+        let domain = domain.to_string();
+        let labels = CharsetLabels::new(args.max_len as usize);
+        candidates = Box::new(candidates.chain(labels.map(move |label| Candidate::from(format!("{}.{}", label, domain)))));
+        total = total.map(|total| total + count);
+    }
+
+    // Show the queries a scan would issue instead of running it
+    if args.dry_run {
+        let types = scanner.query_types();
+        let mut count = 0;
+        for subdomain in candidates {
+            for record_type in &types {
+                println!("{}\t{}", subdomain.name, record_type);
+            }
+            count += 1;
+        }
+        let names: Vec<String> = types.iter().map(|t| t.to_string()).collect();
+        say!(
+            "\n{} {} candidates × {} record types ({}) = {} queries (at most)",
+            "[*]".blue(),
+            count,
+            types.len(),
+            names.join(", "),
+            count * types.len()
+        );
+        return Ok((Vec::new(), ScanStats::default()));
+    }
+
     // Perform scan
-    let valid_subdomains = scanner.scan_domains(subdomains).await?;
+    let (mut valid_subdomains, mut stats) = match scanner.scan_candidates(domain, candidates, total).await {
+        Ok(outcome) => outcome,
+        Err(ScanError::EmptyInput) if args.domains_file.is_some() => (Vec::new(), ScanStats::default()),
+        Err(e) => return Err(e.into()),
+    };
+    // Mine TXT records of the apex and the names found valid for referenced hostnames
+    if args.parse_txt {
+        say!("\n{}", "Parsing TXT records for referenced subdomains...".cyan());
+        let mut hosts = vec![domain.to_string()];
+        hosts.extend(valid_subdomains.iter().map(|result| result.subdomain.clone()));
+        let mut referenced = Vec::new();
+        for candidate in txt_parser.fetch_candidates(domain, &hosts).await {
+            if !scanned.insert(candidate.subdomain.to_lowercase()) {
+                continue;
+            }
+            say!(
+                "{} {} {}",
+                "[+]".green(),
+                candidate.subdomain,
+                format!("(TXT {}: \"{}\")", candidate.host, candidate.record).dimmed()
+            );
+            referenced.push(Candidate::new(candidate.subdomain, Source::Txt));
+        }
+        match scanner.scan_domains(domain, referenced).await {
+            Ok((results, txt_stats)) => {
+                stats.merge(&txt_stats);
+                valid_subdomains.extend(results);
+            }
+            Err(ScanError::EmptyInput) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    if args.recursive {
+        match wordlists {
+            Some(wordlists) => {
+                scan_recursive(args, scanner, wordlists, &mut valid_subdomains, &mut stats, &mut scanned).await?
+            }
+            None => say!("{} --recursive needs a wordlist, skipping deeper levels", "[!]".yellow()),
+        }
+    }
+    if args.permute && !valid_subdomains.is_empty() {
+        let found: Vec<String> = valid_subdomains.iter().map(|result| result.subdomain.clone()).collect();
+        let permutations: Vec<Candidate> = permute::generate_permutations(&found, domain, &args.permutation_affixes)
+            .into_iter()
+            .filter(|name| scanned.insert(name.clone()))
+            .map(|name| Candidate::new(name, Source::Permutation))
+            .collect();
+        say!("\n{} Scanning {} permutations of the found subdomains", "[*]".blue(), permutations.len());
+        match scanner.scan_domains(domain, permutations).await {
+            Ok((results, permutation_stats)) => {
+                stats.merge(&permutation_stats);
+                valid_subdomains.extend(results);
+            }
+            Err(ScanError::EmptyInput) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    if args.mutate_numbers && !valid_subdomains.is_empty() {
+        let found: Vec<String> = valid_subdomains.iter().map(|result| result.subdomain.clone()).collect();
+        let mutations: Vec<Candidate> = permute::generate_number_mutations(&found, domain, args.max_number)
+            .into_iter()
+            .filter(|name| scanned.insert(name.clone()))
+            .map(|name| Candidate::new(name, Source::Permutation))
+            .collect();
+        say!("\n{} Scanning {} numbered variants of the found subdomains", "[*]".blue(), mutations.len());
+        match scanner.scan_domains(domain, mutations).await {
+            Ok((results, mutation_stats)) => {
+                stats.merge(&mutation_stats);
+                valid_subdomains.extend(results);
+            }
+            Err(ScanError::EmptyInput) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    if args.smart_gen && !valid_subdomains.is_empty() {
+        let found: Vec<String> = valid_subdomains.iter().map(|result| result.subdomain.clone()).collect();
+        let generated: Vec<Candidate> = permute::generate_smart(&found, domain, args.max_candidates)
+            .into_iter()
+            .filter(|name| scanned.insert(name.clone()))
+            .map(|name| Candidate::new(name, Source::Generated))
+            .collect();
+        say!("\n{} Scanning {} names proposed from the found subdomains", "[*]".blue(), generated.len());
+        match scanner.scan_domains(domain, generated).await {
+            Ok((results, generated_stats)) => {
+                stats.merge(&generated_stats);
+                valid_subdomains.extend(results);
+            }
+            Err(ScanError::EmptyInput) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    if args.rdns && !valid_subdomains.is_empty() {
+        let mut ips: Vec<IpAddr> = valid_subdomains.iter().flat_map(|result| result.ips.iter().copied()).collect();
+        ips.sort();
+        ips.dedup();
+        if args.rdns_expand {
+            ips = scanner::expand_to_slash24(&ips);
+        }
+        let siblings: Vec<Candidate> = scanner
+            .reverse_sweep(&ips, domain)
+            .await
+            .into_iter()
+            .filter(|name| scanned.insert(name.clone()))
+            .map(|name| Candidate::new(name, Source::Rdns))
+            .collect();
+        say!("\n{} Scanning {} new hostnames from reverse DNS", "[*]".blue(), siblings.len());
+        match scanner.scan_domains(domain, siblings).await {
+            Ok((results, rdns_stats)) => {
+                stats.merge(&rdns_stats);
+                valid_subdomains.extend(results);
+            }
+            Err(ScanError::EmptyInput) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    // Names found later from TLS certificates go through the same filters and flags
+    apply_filters(args, &mut valid_subdomains, apex_ips);
+
+    // Confirm reachability on the requested ports
+    if !args.check_ports.is_empty() && !valid_subdomains.is_empty() {
+        scanner.check_ports(&mut valid_subdomains, &args.check_ports).await;
+    }
+
+    // See which subdomains actually serve HTTP(S)
+    if args.probe && !valid_subdomains.is_empty() {
+        let prober = HttpProber::new(scanner.concurrency())?;
+        prober.probe_all(&mut valid_subdomains).await;
+
+        // Certificates often name siblings; scan and probe those until no new ones appear
+        scanned.extend(valid_subdomains.iter().map(|result| result.subdomain.to_lowercase()));
+        let mut probed = 0;
+        loop {
+            let names: Vec<&str> = valid_subdomains[probed..]
+                .iter()
+                .filter_map(|result| result.probe.as_ref())
+                .flat_map(|probe| probe.tls_names.iter().map(|name| name.trim_start_matches("*.")))
+                .collect();
+            probed = valid_subdomains.len();
+            let candidates: Vec<Candidate> = harvest_hostnames(&names.join(" "), domain)
+                .into_iter()
+                .filter(|name| scanned.insert(name.clone()))
+                .map(|name| Candidate::new(name, Source::TlsCert))
+                .collect();
+            if candidates.is_empty() {
+                break;
+            }
+            say!("\n{} Scanning {} new names from TLS certificates", "[*]".blue(), candidates.len());
+            match scanner.scan_domains(domain, candidates).await {
+                Ok((mut results, certificate_stats)) => {
+                    stats.merge(&certificate_stats);
+                    apply_filters(args, &mut results, apex_ips);
+                    if !args.check_ports.is_empty() && !results.is_empty() {
+                        scanner.check_ports(&mut results, &args.check_ports).await;
+                    }
+                    if !results.is_empty() {
+                        prober.probe_all(&mut results).await;
+                    }
+                    valid_subdomains.extend(results);
+                }
+                Err(ScanError::EmptyInput) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+    if let Some(dir) = args.screenshots.as_deref().filter(|_| !valid_subdomains.is_empty()) {
+        let screenshotter = Screenshotter::new(dir, args.browser.clone(), scanner.concurrency())?;
+        screenshotter.capture_all(&mut valid_subdomains).await;
+    }
+
+    // Compare CNAME targets and their pages against the takeover fingerprints
+    if args.takeover_check && !valid_subdomains.is_empty() {
+        let checker = TakeoverChecker::new(scanner.concurrency())?;
+        checker.check_all(&mut valid_subdomains).await;
+    }
+
+    // Quiet mode prints only the bare names, unless --stream-stdout already did
+    if args.quiet && !args.stream_stdout {
+        for result in &valid_subdomains {
+            println!("{}", to_unicode_domain(&result.subdomain));
+        }
+    }
 
     // Display and save results
     if !valid_subdomains.is_empty() {
-        println!("\n{}", "Valid Subdomains:".bright_green().bold());
-        for subdomain in &valid_subdomains {
-            println!("✅ {}", subdomain.green());
+        say!("\n{}", "Valid Subdomains:".bright_green().bold());
+        for result in &valid_subdomains {
+            let mut line = if result.dangling {
+                format!("⚠️  {}", to_unicode_domain(&result.subdomain).red())
+            } else {
+                format!("✅ {}", to_unicode_domain(&result.subdomain).green())
+            };
+            if !result.ips.is_empty() {
+                let ips: Vec<String> = result.ips.iter().map(ToString::to_string).collect();
+                line.push_str(&format!(" -> {}", ips.join(", ")));
+            }
+            if args.record_types.iter().any(|t| !matches!(t, RecordType::A | RecordType::AAAA)) {
+                line.push_str(&format!(" {}", format!("[{}]", result.record_types.join(", ")).dimmed()));
+            }
+            if result.load_balanced {
+                line.push_str(&format!(" {}", format!("[load-balanced: {} IPs]", result.ips.len()).magenta()));
+            }
+            if !result.cname_chain.is_empty() {
+                line.push_str(&format!(" {}", format!("(cname: {})", result.cname_chain.join(" -> ")).dimmed()));
+            }
+            if result.private {
+                line.push_str(&format!(" {}", "[private]".yellow()));
+            }
+            if let (true, Some(ttl)) = (result.low_ttl, result.ttl) {
+                line.push_str(&format!(" {}", format!("[ttl: {}s]", ttl).yellow()));
+            }
+            if result.dangling && result.takeover.is_none() {
+                line.push_str(&format!(" {}", "[dangling cname: possible takeover]".red().bold()));
+            }
+            if let Some(finding) = &result.takeover {
+                line.push_str(&format!(" {}", format!("[takeover: {}]", finding).red().bold()));
+            }
+            if let Some(issue) = result.cname_issue {
+                line.push_str(&format!(" {}", format!("[{}]", issue).red()));
+            }
+            if !args.check_ports.is_empty() {
+                let ports: Vec<String> = result.open_ports.iter().map(|p| p.to_string()).collect();
+                line.push_str(&format!(" {}", format!("[open: {}]", ports.join(", ")).cyan()));
+            }
+            if let Some(probe) = &result.probe {
+                line.push_str(&format!(" {}", format!("[{}]", probe).bright_cyan()));
+            }
+            say!("{}", line);
         }
 
-        if let Some(output_path) = args.output {
-            std::fs::write(output_path, valid_subdomains.join("\n"))?;
+        let load_balanced = valid_subdomains.iter().filter(|r| r.load_balanced).count();
+        if load_balanced > 0 {
+            say!(
+                "{} {} subdomains resolve to more than {} addresses",
+                "[*]".blue(),
+                load_balanced,
+                args.min_ips_flag
+            );
+        }
+
+        if args.label_stats {
+            let histogram = output::label_length_histogram(&valid_subdomains);
+            let widest = histogram.values().copied().max().unwrap_or(1);
+            say!("\n{}", "Label Length Distribution:".bright_blue().bold());
+            for (length, count) in histogram {
+                let bar = "█".repeat((count * 40).div_ceil(widest));
+                say!("{:>3} chars | {} {}", length, bar.cyan(), count);
+            }
         }
+
+        if !args.match_cname.is_empty() {
+            let matches: Vec<_> = valid_subdomains
+                .iter()
+                .filter_map(|result| result.cname_match(&args.match_cname).map(|suffix| (result, suffix)))
+                .collect();
+            say!("\n{} ({})", "CNAME Matches:".bright_magenta().bold(), matches.len());
+            for (result, suffix) in matches {
+                say!(
+                    "🔗 {} -> {} {}",
+                    result.subdomain.magenta(),
+                    result.cname.as_deref().unwrap_or_default(),
+                    format!("[{}]", suffix).dimmed()
+                );
+            }
+        }
+
     } else {
-        println!("\n{}", "No valid subdomains found.".yellow());
+        say!("\n{}", "No valid subdomains found.".yellow());
     }
 
-    let elapsed = start_time.elapsed();
-    println!("\n{}", "Scan Complete!".bright_blue().bold());
-    println!("Time elapsed: {:.2?}", elapsed);
+    Ok((valid_subdomains, stats))
+}
+
+/// Print the unique subdomains of `domain` found in `files` (or stdin) to stdout, so
+/// they can be piped into other tools; the summary goes to stderr.
+fn harvest(domain: &str, files: &[PathBuf]) -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::Read;
+
+    let domain = extract_domain(domain).ok_or("Invalid domain format")?;
+    let stdin = [PathBuf::from("-")];
+    let mut seen = HashSet::new();
+
+    for path in if files.is_empty() { &stdin[..] } else { files } {
+        let mut bytes = Vec::new();
+        if path.as_os_str() == "-" {
+            std::io::stdin().read_to_end(&mut bytes)?;
+        } else {
+            bytes = std::fs::read(path)?;
+        }
+        for hostname in harvest_hostnames(&String::from_utf8_lossy(&bytes), &domain) {
+            if seen.insert(hostname.clone()) {
+                println!("{}", hostname);
+            }
+        }
+    }
 
+    eprintln!("{} Harvested {} unique subdomains of {}", "[+]".green(), seen.len(), domain);
     Ok(())
 }
+
+fn parse_record_type(value: &str) -> Result<RecordType, String> {
+    value
+        .trim()
+        .to_uppercase()
+        .parse()
+        .map_err(|_| format!("unknown DNS record type '{}'", value))
+}
+
+/// `--cidr` values are checked up front; the range is expanded again per domain.
+fn parse_cidr(value: &str) -> Result<String, String> {
+    cidr_addresses(value).map(|_| value.trim().to_string())
+}
+
+/// Hash of what decides the candidates of a scan: the targets, each wordlist (path and
+/// size, so huge files are not read twice) and the options that derive names from them.
+fn scan_inputs_hash(args: &Args, domains: &[String]) -> u64 {
+    let mut inputs = domains.join("\n");
+    for path in args.wordlist.iter().chain(&args.wordlist_dir) {
+        let size = std::fs::metadata(path).map(|meta| meta.len()).unwrap_or_default();
+        inputs.push_str(&format!("\n{}:{}", path.display(), size));
+    }
+    inputs.push_str(&format!("\n{:?} {} {:?}", args.label_case, args.permutations, args.permutation_affixes));
+    if args.label_depth > 1 {
+        inputs.push_str(&format!("\nlabel-depth {} {}", args.label_depth, args.max_nested));
+    }
+    if args.charset_brute {
+        inputs.push_str(&format!("\ncharset-brute {}", args.max_len));
+    }
+    if !args.cidr.is_empty() {
+        inputs.push_str(&format!("\n{}", args.cidr.join(",")));
+    }
+    hash_content(inputs.as_bytes())
+}
+
+/// Run the wordlist under each subdomain found by the previous level, `--depth` levels
+/// deep. Each base is its own scan, so wildcard detection and the progress bar cover
+/// exactly its candidates. Names in `scanned` are never queried again.
+async fn scan_recursive(
+    args: &Args,
+    scanner: &Scanner,
+    wordlists: &WordlistManager,
+    results: &mut Vec<ScanResult>,
+    stats: &mut ScanStats,
+    scanned: &mut HashSet<String>,
+) -> Result<(), ScanError> {
+    let mut found: HashSet<String> = results.iter().map(|result| result.subdomain.to_lowercase()).collect();
+    let mut bases: Vec<String> = results.iter().filter(|r| !r.dangling).map(|r| r.subdomain.clone()).collect();
+
+    for level in 1..=args.depth {
+        let mut next_bases = Vec::new();
+        for base in &bases {
+            let candidates: Vec<String> = wordlists
+                .build_candidates(base, args.label_case)
+                .into_iter()
+                .filter(|candidate| scanned.insert(candidate.to_lowercase()))
+                .collect();
+            if candidates.is_empty() {
+                continue;
+            }
+
+            say!("\n{} Recursing into {} (level {} of {})", "[*]".blue(), base, level, args.depth);
+            let (level_results, level_stats) = match scanner.scan_domains(base, candidates).await {
+                Ok(outcome) => outcome,
+                Err(ScanError::EmptyInput) => continue,
+                Err(e) => return Err(e),
+            };
+            stats.merge(&level_stats);
+            for result in level_results {
+                if found.insert(result.subdomain.to_lowercase()) {
+                    if !result.dangling {
+                        next_bases.push(result.subdomain.clone());
+                    }
+                    results.push(result);
+                }
+            }
+        }
+        if next_bases.is_empty() {
+            break;
+        }
+        bases = next_bases;
+    }
+    Ok(())
+}
+
+/// Load every `--wordlist` and `--wordlist-dir`, or `None` when neither was given.
+fn load_wordlists(args: &Args) -> Result<Option<WordlistManager>, WordlistError> {
+    if args.wordlist.is_empty() && args.wordlist_dir.is_empty() {
+        return Ok(None);
+    }
+
+    say!("{}", "Loading wordlists...".cyan());
+    let load_start = Instant::now();
+    let mut manager = WordlistManager::new(&args.skip_chars, args.strict_wordlists);
+    for path in &args.wordlist {
+        manager.add_wordlist(path);
+    }
+    for dir in &args.wordlist_dir {
+        manager.add_directory(dir)?;
+    }
+    if args.stream_wordlists {
+        // Checked up front so --strict-wordlists still fails before any query is sent
+        manager.stream_words()?;
+        say!("{} Wordlists will be streamed while scanning", "[*]".blue());
+        return Ok(Some(manager));
+    }
+    let word_count = manager.load_all()?.len();
+    say!(
+        "{} {} unique words loaded in {:.2?}",
+        "[+]".green(),
+        word_count,
+        load_start.elapsed()
+    );
+    // Built from the words alone, before permutation labels join them
+    let nested = (args.label_depth > 1).then(|| manager.generate_nested(args.label_depth as usize, args.max_nested));
+    if args.permutations {
+        let permutations = manager.generate_permutations(&args.permutation_affixes, args.max_permutations);
+        say!(
+            "{} {} permutation labels generated{}",
+            "[+]".green(),
+            permutations.len(),
+            if permutations.len() >= args.max_permutations { " (capped by --max-permutations)" } else { "" }
+        );
+        manager.extend_labels(permutations);
+    }
+    if let Some(nested) = nested {
+        say!(
+            "{} {} multi-label names generated{}",
+            "[+]".green(),
+            nested.len(),
+            if nested.len() >= args.max_nested { " (capped by --max-nested)" } else { "" }
+        );
+        manager.extend_labels(nested);
+    }
+    let failed = manager.failed_wordlists();
+    if !failed.is_empty() {
+        say!("{} {} wordlists failed to load:", "[!]".yellow(), failed.len());
+        for path in failed {
+            say!("    {}", path.display());
+        }
+    }
+    Ok(Some(manager))
+}
+
+/// Drop and flag results according to the filter options (`--distinct-from-apex`,
+/// `--exclude-private`, `--min-ttl`, ...). `apex_ips` are the apex's addresses, only
+/// looked at with `--distinct-from-apex`.
+fn apply_filters(args: &Args, results: &mut Vec<ScanResult>, apex_ips: &[IpAddr]) {
+    if args.distinct_from_apex {
+        let filtered = scanner::retain_distinct_from(results, apex_ips);
+        say!(
+            "{} Filtered {} subdomains sharing the apex's {} addresses",
+            "[*]".blue(),
+            filtered,
+            apex_ips.len()
+        );
+    }
+    scanner::flag_load_balanced(results, args.min_ips_flag);
+    let private = scanner::flag_private(results);
+    if args.exclude_private {
+        results.retain(|result| !result.private);
+        say!("{} Dropped {} subdomains resolving only to private addresses", "[*]".blue(), private);
+    } else if args.only_private {
+        let dropped = results.len() - private;
+        results.retain(|result| result.private);
+        say!("{} Dropped {} subdomains with public addresses", "[*]".blue(), dropped);
+    }
+    if let Some(min_ttl) = args.min_ttl {
+        let low = scanner::flag_low_ttl(results, min_ttl);
+        if args.drop_low_ttl {
+            results.retain(|result| !result.low_ttl);
+            say!("{} Dropped {} subdomains with a TTL below {}s", "[*]".blue(), low, min_ttl);
+        } else {
+            say!("{} {} subdomains have a TTL below {}s", "[*]".blue(), low, min_ttl);
+        }
+    }
+}
+
+/// Whether `result` survives the drops of `apply_filters`, judged from the result alone
+/// so that findings can be filtered as they are streamed.
+fn stream_filter(args: &Args, apex_ips: Arc<RwLock<Vec<IpAddr>>>) -> impl Fn(&ScanResult) -> bool + Send + Sync + 'static {
+    let (distinct, exclude_private, only_private) = (args.distinct_from_apex, args.exclude_private, args.only_private);
+    let min_ttl = args.min_ttl.filter(|_| args.drop_low_ttl);
+    move |result| {
+        let apex_ips = apex_ips.read().unwrap();
+        let private = !result.ips.is_empty() && result.ips.iter().all(scanner::is_bogon);
+        let shares_apex = !result.ips.is_empty() && result.ips.iter().all(|ip| apex_ips.contains(ip));
+        let low_ttl = min_ttl.is_some_and(|min_ttl| result.ttl.is_some_and(|ttl| ttl < min_ttl));
+        let dropped = (distinct && shares_apex) || (exclude_private && private) || (only_private && !private) || low_ttl;
+        !dropped
+    }
+}
+
+/// Sinks that receive findings as the scan loop finds them, one per streaming option
+/// given, each behind the filters of `stream_filter`.
+fn build_sinks(args: &Args, apex_ips: Arc<RwLock<Vec<IpAddr>>>) -> Result<Vec<Box<dyn ResultSink>>, sink::SinkError> {
+    let mut sinks: Vec<Box<dyn ResultSink>> = Vec::new();
+    if let Some(path) = &args.stream_ndjson {
+        sinks.push(Box::new(NdjsonSink::create(path)?));
+    }
+    if args.stream_stdout {
+        sinks.push(Box::new(StdoutSink));
+    }
+    if let Some(url) = &args.webhook {
+        sinks.push(Box::new(WebhookSink::new(url, args.webhook_batch)));
+    }
+    let keep: Arc<dyn Fn(&ScanResult) -> bool + Send + Sync> = Arc::new(stream_filter(args, apex_ips));
+    Ok(sinks
+        .into_iter()
+        .map(|sink| Box::new(FilteredSink::new(sink, keep.clone())) as Box<dyn ResultSink>)
+        .collect())
+}
+
+fn print_email_posture(posture: &EmailPosture) {
+    let missing = || "missing".red().to_string();
+    say!("\n{}", "Email Security Posture:".bright_blue().bold());
+    say!(
+        "{} {}",
+        "SPF:  ".blue(),
+        match &posture.spf_policy {
+            Some(policy) if policy == "-all" => policy.green().to_string(),
+            Some(policy) => policy.yellow().to_string(),
+            None if posture.spf.is_some() => "no all mechanism".yellow().to_string(),
+            None => missing(),
+        }
+    );
+    say!(
+        "{} {}",
+        "DMARC:".blue(),
+        match &posture.dmarc_policy {
+            Some(policy) if policy == "none" => format!("p={}", policy).yellow().to_string(),
+            Some(policy) => format!("p={}", policy).green().to_string(),
+            None if posture.dmarc.is_some() => "no policy".yellow().to_string(),
+            None => missing(),
+        }
+    );
+    say!(
+        "{} {}",
+        "DKIM: ".blue(),
+        if posture.dkim_selectors.is_empty() {
+            "no common selector found".yellow().to_string()
+        } else {
+            posture.dkim_selectors.join(", ").green().to_string()
+        }
+    );
+}
+
+/// Names of the discovery and enrichment phases enabled for this run.
+fn enabled_phases(args: &Args) -> Vec<String> {
+    let phases = [
+        ("wordlist", !args.wordlist.is_empty() || !args.wordlist_dir.is_empty()),
+        ("wayback", args.wayback),
+        ("crtsh", args.crtsh),
+        ("parse_txt", args.parse_txt),
+        ("axfr", args.try_axfr),
+        ("cidr", !args.cidr.is_empty()),
+        ("dns", true),
+        ("recursive", args.recursive),
+        ("permute", args.permute),
+        ("mutate_numbers", args.mutate_numbers),
+        ("smart_gen", args.smart_gen),
+        ("rdns", args.rdns),
+        ("cname", !args.match_cname.is_empty()),
+        ("takeover_check", args.takeover_check),
+        ("check_ports", !args.check_ports.is_empty()),
+        ("probe", args.probe),
+        ("screenshots", args.screenshots.is_some()),
+        ("email_posture", args.email_posture),
+    ];
+    phases
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+/// Replace every domain with its registrable apex, reporting what was collapsed.
+fn collapse_to_apex(domains: Vec<String>) -> Vec<String> {
+    let mut apexes: Vec<String> = Vec::new();
+    for domain in domains {
+        let apex = registrable_domain(&domain);
+        if apex != domain || apexes.contains(&apex) {
+            say!("{} Collapsed {} -> {}", "[*]".blue(), domain, apex);
+        }
+        if !apexes.contains(&apex) {
+            apexes.push(apex);
+        }
+    }
+    apexes
+}
+
+/// Parse a domains file: one domain or URL per line, blank lines and `#` comments ignored.
+fn parse_domains(content: &str) -> Vec<String> {
+    let mut domains: Vec<String> = Vec::new();
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match extract_domain(line) {
+            Some(domain) if !domains.contains(&domain) => domains.push(domain),
+            Some(_) => {}
+            None => eprintln!("Skipping invalid domain: {}", line),
+        }
+    }
+    domains
+}
+
+/// Parse affixes given one per line or as comma-separated lists, blank lines, empty
+/// entries and `#` comment lines ignored. Whitespace and hyphens around an affix are
+/// dropped, so ` -dev` and `int-` read as `dev` and `int`.
+fn parse_affixes(content: &str) -> Vec<String> {
+    let mut affixes: Vec<String> = Vec::new();
+    for line in content.lines().map(str::trim).filter(|line| !line.starts_with('#')) {
+        for entry in line.split(',') {
+            let affix = entry.trim().trim_matches('-').to_lowercase();
+            if !affix.is_empty() && !affixes.contains(&affix) {
+                affixes.push(affix);
+            }
+        }
+    }
+    affixes
+}
+
+/// Clean up `--permutation-affixes` the way affix files are read, then append the
+/// affixes of `--affix-file` not already listed.
+fn load_affixes(args: &mut Args) -> std::io::Result<()> {
+    let mut affixes = parse_affixes(&args.permutation_affixes.join(","));
+    if let Some(path) = &args.affix_file {
+        let listed = parse_affixes(&std::fs::read_to_string(path)?);
+        say!("{} {} affixes from {}", "[*]".blue(), listed.len(), path.display());
+        for affix in listed {
+            if !affixes.contains(&affix) {
+                affixes.push(affix);
+            }
+        }
+    }
+    args.permutation_affixes = affixes;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    /// Serve DNS answering A queries for `www.example.com` with 127.0.0.2; every other
+    /// name is NXDOMAIN.
+    fn nameserver() -> u16 {
+        use trust_dns_resolver::proto::op::{Message, MessageType, ResponseCode};
+        use trust_dns_resolver::proto::rr::{rdata, RData, Record};
+        use trust_dns_resolver::proto::serialize::binary::BinEncodable;
+
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = socket.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            while let Ok((len, peer)) = socket.recv_from(&mut buf) {
+                let Ok(query) = Message::from_vec(&buf[..len]) else { continue };
+                let mut response = Message::new();
+                response.set_id(query.id()).set_message_type(MessageType::Response).set_recursion_available(true);
+                response.set_response_code(ResponseCode::NXDomain);
+                for question in query.queries() {
+                    response.add_query(question.clone());
+                    if question.name().to_utf8() == "www.example.com." {
+                        response.set_response_code(ResponseCode::NoError);
+                        if question.query_type() == RecordType::A {
+                            let data = RData::A(rdata::A([127, 0, 0, 2].into()));
+                            response.add_answer(Record::from_rdata(question.name().clone(), 60, data));
+                        }
+                    }
+                }
+                let _ = socket.send_to(&response.to_bytes().unwrap(), peer);
+            }
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn test_wordlist_scanner_wiring() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "www\napi\n# comment\nWWW").unwrap();
+
+        let resolver = format!("127.0.0.1:{}", nameserver());
+        let args = Args::parse_from(["SubTahu", "-d", "example.com", "-w", file.path().to_str().unwrap(), "--resolvers", &resolver]);
+        let resolvers = scanner::parse_resolvers(&args.resolvers).unwrap();
+        let scanner = Scanner::with_resolvers(&resolvers, args.intensity.profile().concurrency)
+            .await
+            .unwrap()
+            .with_wildcard_filter(false);
+
+        let wordlists = load_wordlists(&args).unwrap().expect("a wordlist was given");
+        let domain = extract_domain(args.domain.as_deref().unwrap()).unwrap();
+        let candidates = wordlists.build_candidates(&domain, args.label_case);
+        assert_eq!(candidates, vec!["api.example.com", "www.example.com"]);
+
+        let (results, stats) = scanner.scan_domains(&domain, candidates).await.unwrap();
+        assert_eq!((stats.total, stats.valid), (2, 1));
+        assert_eq!(results[0].subdomain, "www.example.com");
+
+        let no_wordlists = Args::parse_from(["SubTahu", "-d", "example.com"]);
+        assert!(load_wordlists(&no_wordlists).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_verify_trusted_flag() {
+        let parse = |extra: &[&str]| Args::parse_from(["SubTahu", "-d", "example.com"].iter().chain(extra)).verify_trusted;
+        assert_eq!(parse(&[]), None);
+        assert_eq!(parse(&["--verify-trusted"]), Some(vec!["8.8.8.8".to_string(), "1.1.1.1".to_string()]));
+        assert_eq!(parse(&["--verify-trusted", "9.9.9.9"]), Some(vec!["9.9.9.9".to_string()]));
+    }
+
+    #[test]
+    fn test_port_flags() {
+        let parse = |extra: &[&str]| Args::try_parse_from(["SubTahu", "-d", "example.com"].iter().chain(extra));
+        assert_eq!(parse(&["--ports", "80,8443"]).unwrap().check_ports, vec![80, 8443]);
+        assert_eq!(parse(&["--top-ports", "5"]).unwrap().top_ports, Some(5));
+        assert!(parse(&["--top-ports", "101"]).is_err());
+        assert!(parse(&["--top-ports", "5", "--ports", "80"]).is_err());
+        assert_eq!(scanner::top_ports(5), vec![80, 23, 443, 21, 22]);
+        assert_eq!(scanner::top_ports(1000).len(), scanner::TOP_PORTS.len());
+    }
+
+    #[test]
+    fn test_output_format_flags() {
+        let args = Args::parse_from(["SubTahu", "-d", "example.com", "--output-format", "csv"]);
+        assert_eq!(args.format, OutputFormat::Csv);
+        let args = Args::parse_from(["SubTahu", "-d", "example.com", "-f", "json"]);
+        assert_eq!(args.format, OutputFormat::Json);
+    }
+
+    #[test]
+    fn test_json_request_apply() {
+        let mut args = Args::parse_from(["SubTahu", "--json-stdin", "-o", "report.json", "-c", "50"]);
+        let request: JsonRequest = serde_json::from_str(
+            r#"{
+                "domain": "example.com",
+                "wordlists": ["words.txt"],
+                "wordlist_dirs": ["lists"],
+                "sources": ["wayback", "ctlogs", "txt", "axfr"],
+                "intensity": "stealthy",
+                "check_ports": [80, 443],
+                "match_cname": ["github.io"]
+            }"#,
+        )
+        .unwrap();
+        request.apply(&mut args).unwrap();
+
+        assert_eq!(args.domain.as_deref(), Some("example.com"));
+        assert_eq!(args.wordlist, vec![PathBuf::from("words.txt")]);
+        assert_eq!(args.wordlist_dir, vec![PathBuf::from("lists")]);
+        assert!(args.wayback && args.crtsh && args.parse_txt && args.try_axfr);
+        // Options missing from the request keep their command-line value
+        assert_eq!(args.concurrency, Some(50));
+        assert_eq!(args.intensity, Intensity::Stealthy);
+        assert_eq!(args.check_ports, vec![80, 443]);
+        assert_eq!(args.match_cname, vec!["github.io".to_string()]);
+        // The report always goes to stdout, as JSON unless the request says otherwise
+        assert_eq!(args.format, OutputFormat::Json);
+        assert_eq!(args.output, None);
+
+        let request: JsonRequest = serde_json::from_str(r#"{"domain": "example.com", "sources": ["dns"]}"#).unwrap();
+        assert_eq!(request.apply(&mut args).unwrap_err(), "Unknown source in JSON request: dns");
+        assert!(serde_json::from_str::<JsonRequest>(r#"{"domain": "example.com", "threads": 4}"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_affixes() {
+        assert_eq!(parse_affixes("dev,qa,stage"), vec!["dev", "qa", "stage"]);
+        assert_eq!(parse_affixes("  dev , QA,\t-int- "), vec!["dev", "qa", "int"]);
+        assert_eq!(parse_affixes("dev,,qa,\n,\n\n - ,"), vec!["dev", "qa"]);
+        assert_eq!(parse_affixes("# environments\ndev\nperf, sandbox\n# dev,skipped\ndev"), vec!["dev", "perf", "sandbox"]);
+        assert!(parse_affixes("").is_empty());
+    }
+
+    #[test]
+    fn test_load_affixes() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "# ours\npreprod\n -dev\n\nsandbox, perf").unwrap();
+        let path = file.path().to_str().unwrap();
+
+        let mut args = Args::parse_from(["SubTahu", "-d", "example.com", "--permutation-affixes", " dev,,QA ", "--affix-file", path]);
+        load_affixes(&mut args).unwrap();
+        assert_eq!(args.permutation_affixes, vec!["dev", "qa", "preprod", "sandbox", "perf"]);
+
+        let mut args = Args::parse_from(["SubTahu", "-d", "example.com", "--affix-file", "/nonexistent/affixes.txt"]);
+        assert!(load_affixes(&mut args).is_err());
+    }
+}
@@ -0,0 +1,519 @@
+use crate::probe::{HttpResponse, ProbeResult};
+use crate::scanner::{ScanResult, ScanStats};
+use crate::txt::EmailPosture;
+use crate::utils::to_unicode_domain;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// One subdomain per line
+    Text,
+    /// One IP (or hostname when unresolved) per line, ready for `nmap -iL`
+    Nmap,
+    /// Header row plus one row per subdomain, columns chosen by `--csv-fields`
+    Csv,
+    /// A single report document with manifest, stats and every result
+    Json,
+}
+
+/// Bumped whenever the JSON report layout changes incompatibly.
+pub const REPORT_SCHEMA_VERSION: u32 = 1;
+
+/// Describes the run that produced a report.
+#[derive(Debug, Clone, Serialize)]
+pub struct Manifest {
+    pub tool: String,
+    pub version: String,
+    /// Unix timestamp (seconds) at which the report was written
+    pub generated_at: u64,
+    pub targets: Vec<String>,
+    /// Discovery and enrichment phases that ran, e.g. `wordlist`, `wayback`, `check_ports`
+    pub phases: Vec<String>,
+}
+
+impl Manifest {
+    pub fn new(targets: Vec<String>, phases: Vec<String>) -> Self {
+        Manifest {
+            tool: env!("CARGO_PKG_NAME").to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            generated_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            targets,
+            phases,
+        }
+    }
+}
+
+/// The consolidated JSON report: every finding with all gathered fields, plus run metadata.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub schema_version: u32,
+    pub manifest: Manifest,
+    pub stats: ScanStats,
+    pub results: Vec<ScanResult>,
+    /// One entry per target when `--email-posture` was given
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub email_posture: Vec<EmailPosture>,
+}
+
+impl Report {
+    pub fn new(manifest: Manifest, stats: ScanStats, results: Vec<ScanResult>) -> Self {
+        Report {
+            schema_version: REPORT_SCHEMA_VERSION,
+            manifest,
+            stats,
+            results,
+            email_posture: Vec::new(),
+        }
+    }
+
+    pub fn with_email_posture(mut self, email_posture: Vec<EmailPosture>) -> Self {
+        self.email_posture = email_posture;
+        self
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+/// Columns available in CSV output.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum CsvField {
+    Subdomain,
+    Ips,
+    Cname,
+    #[value(name = "open_ports")]
+    OpenPorts,
+    #[value(name = "load_balanced")]
+    LoadBalanced,
+    Ttl,
+    Source,
+    #[value(name = "record_types")]
+    RecordTypes,
+    Private,
+    Status,
+    #[value(name = "latency_ms")]
+    LatencyMs,
+    #[value(name = "http_status")]
+    HttpStatus,
+    #[value(name = "https_status")]
+    HttpsStatus,
+    Title,
+    #[value(name = "favicon_hash")]
+    FaviconHash,
+    Tech,
+}
+
+impl CsvField {
+    fn name(&self) -> &'static str {
+        match self {
+            CsvField::Subdomain => "subdomain",
+            CsvField::Ips => "ips",
+            CsvField::Cname => "cname",
+            CsvField::OpenPorts => "open_ports",
+            CsvField::LoadBalanced => "load_balanced",
+            CsvField::Ttl => "ttl",
+            CsvField::Source => "source",
+            CsvField::RecordTypes => "record_types",
+            CsvField::Private => "private",
+            CsvField::Status => "status",
+            CsvField::LatencyMs => "latency_ms",
+            CsvField::HttpStatus => "http_status",
+            CsvField::HttpsStatus => "https_status",
+            CsvField::Title => "title",
+            CsvField::FaviconHash => "favicon_hash",
+            CsvField::Tech => "tech",
+        }
+    }
+
+    fn value(&self, result: &ScanResult) -> String {
+        match self {
+            CsvField::Subdomain => result.subdomain.clone(),
+            CsvField::Ips => join(&result.ips),
+            CsvField::Cname => result.cname.clone().unwrap_or_default(),
+            CsvField::OpenPorts => join(&result.open_ports),
+            CsvField::LoadBalanced => result.load_balanced.to_string(),
+            CsvField::Ttl => result.ttl.map(|ttl| ttl.to_string()).unwrap_or_default(),
+            CsvField::Source => result.source.to_string(),
+            CsvField::RecordTypes => join(&result.record_types),
+            CsvField::Private => result.private.to_string(),
+            CsvField::Status => result.status.to_string(),
+            CsvField::LatencyMs => result.latency_ms.map(|ms| ms.to_string()).unwrap_or_default(),
+            CsvField::HttpStatus => probe_status(result, |probe| &probe.http),
+            CsvField::HttpsStatus => probe_status(result, |probe| &probe.https),
+            CsvField::Title => result
+                .probe
+                .iter()
+                .flat_map(|probe| [&probe.https, &probe.http])
+                .find_map(|response| response.as_ref()?.title.clone())
+                .unwrap_or_default(),
+            CsvField::FaviconHash => {
+                result.probe.as_ref().and_then(|probe| probe.favicon_hash).map(|hash| hash.to_string()).unwrap_or_default()
+            }
+            CsvField::Tech => result.probe.as_ref().map(|probe| join(&probe.tech)).unwrap_or_default(),
+        }
+    }
+}
+
+/// Column set used when `--csv-fields` is not given.
+pub const DEFAULT_CSV_FIELDS: &str = "subdomain,ips";
+
+#[derive(Debug, Clone)]
+pub struct OutputOptions {
+    pub format: OutputFormat,
+    pub csv_fields: Vec<CsvField>,
+    /// Emit one row per (subdomain, IP) pair instead of one per subdomain
+    pub flatten: bool,
+}
+
+/// Render scan results in the requested output format.
+pub fn format_results(results: &[ScanResult], options: &OutputOptions) -> String {
+    if options.flatten {
+        let flat = flatten_results(results);
+        return match options.format {
+            OutputFormat::Text => flat
+                .iter()
+                .map(|result| match result.ips.first() {
+                    Some(ip) => format!("{} {}", to_unicode_domain(&result.subdomain), ip),
+                    None => to_unicode_domain(&result.subdomain),
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => format_results(&flat, &OutputOptions { flatten: false, ..options.clone() }),
+        };
+    }
+
+    match options.format {
+        OutputFormat::Csv => format_csv(results, &options.csv_fields, true),
+        OutputFormat::Json => serde_json::to_string_pretty(results).unwrap_or_default(),
+        OutputFormat::Text => results
+            .iter()
+            .map(|result| to_unicode_domain(&result.subdomain))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        OutputFormat::Nmap => {
+            let mut targets: Vec<String> = Vec::new();
+            for result in results {
+                let entries = if result.ips.is_empty() {
+                    vec![result.subdomain.clone()]
+                } else {
+                    result.ips.iter().map(|ip| ip.to_string()).collect()
+                };
+                for entry in entries {
+                    if !targets.contains(&entry) {
+                        targets.push(entry);
+                    }
+                }
+            }
+            targets.join("\n")
+        }
+    }
+}
+
+/// Split every result into one copy per IP address. Results without addresses are kept as they are.
+pub fn flatten_results(results: &[ScanResult]) -> Vec<ScanResult> {
+    results
+        .iter()
+        .flat_map(|result| {
+            if result.ips.len() <= 1 {
+                return vec![result.clone()];
+            }
+            result
+                .ips
+                .iter()
+                .map(|ip| ScanResult { ips: vec![*ip], ..result.clone() })
+                .collect()
+        })
+        .collect()
+}
+
+/// Count valid findings by the length of their leftmost label.
+pub fn label_length_histogram(results: &[ScanResult]) -> BTreeMap<usize, usize> {
+    let mut histogram = BTreeMap::new();
+    for result in results {
+        let label = result.subdomain.split('.').next().unwrap_or_default();
+        *histogram.entry(label.len()).or_insert(0) += 1;
+    }
+    histogram
+}
+
+pub fn write_report(path: &Path, report: &Report) -> std::io::Result<()> {
+    std::fs::write(path, report.to_json()?)
+}
+
+fn format_csv(results: &[ScanResult], fields: &[CsvField], header: bool) -> String {
+    let mut rows = Vec::new();
+    if header {
+        rows.push(fields.iter().map(|field| field.name()).collect::<Vec<_>>().join(","));
+    }
+    for result in results {
+        let row: Vec<String> = fields.iter().map(|field| csv_escape(&field.value(result))).collect();
+        rows.push(row.join(","));
+    }
+    rows.join("\n")
+}
+
+fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn join<T: ToString>(values: &[T]) -> String {
+    values.iter().map(|value| value.to_string()).collect::<Vec<_>>().join(";")
+}
+
+/// Status code of one scheme's `--probe` response, empty when there was none.
+fn probe_status(result: &ScanResult, scheme: fn(&ProbeResult) -> &Option<HttpResponse>) -> String {
+    result.probe.as_ref().and_then(|probe| scheme(probe).as_ref()).map(|response| response.status.to_string()).unwrap_or_default()
+}
+
+pub fn write_results(path: &Path, results: &[ScanResult], options: &OutputOptions) -> std::io::Result<()> {
+    std::fs::write(path, format_results(results, options))
+}
+
+/// Append results to an existing output file, as done after each domain of a batch.
+/// A CSV header is only written when the file is still empty.
+pub fn append_results(path: &Path, results: &[ScanResult], options: &OutputOptions) -> std::io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let content = match options.format {
+        OutputFormat::Csv if options.flatten => {
+            format_csv(&flatten_results(results), &options.csv_fields, file.metadata()?.len() == 0)
+        }
+        OutputFormat::Csv => format_csv(results, &options.csv_fields, file.metadata()?.len() == 0),
+        _ => format_results(results, options),
+    };
+    writeln!(file, "{}", content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Source;
+
+    #[test]
+    fn test_format_results() {
+        let results = vec![
+            ScanResult {
+                subdomain: "www.example.com".to_string(),
+                ips: vec!["93.184.216.34".parse().unwrap()],
+                ..Default::default()
+            },
+            ScanResult {
+                subdomain: "cdn.example.com".to_string(),
+                ips: vec!["93.184.216.34".parse().unwrap(), "2606:2800:220:1::1".parse().unwrap()],
+                ..Default::default()
+            },
+            ScanResult {
+                subdomain: "old.example.com".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let options = |format| OutputOptions { format, csv_fields: vec![CsvField::Subdomain], flatten: false };
+
+        assert_eq!(
+            format_results(&results, &options(OutputFormat::Text)),
+            "www.example.com\ncdn.example.com\nold.example.com"
+        );
+        assert_eq!(
+            format_results(&results, &options(OutputFormat::Nmap)),
+            "93.184.216.34\n2606:2800:220:1::1\nold.example.com"
+        );
+    }
+
+    #[test]
+    fn test_csv_fields() {
+        let results = vec![ScanResult {
+            subdomain: "cdn.example.com".to_string(),
+            ips: vec!["93.184.216.34".parse().unwrap(), "2606:2800:220:1::1".parse().unwrap()],
+            cname: Some("cdn.example.net".to_string()),
+            ..Default::default()
+        }];
+
+        let options = OutputOptions {
+            format: OutputFormat::Csv,
+            csv_fields: vec![CsvField::Ips, CsvField::Subdomain],
+            flatten: false,
+        };
+        assert_eq!(
+            format_results(&results, &options),
+            "ips,subdomain\n93.184.216.34;2606:2800:220:1::1,cdn.example.com"
+        );
+
+        let options = OutputOptions {
+            format: OutputFormat::Csv,
+            csv_fields: vec![CsvField::Subdomain, CsvField::Cname, CsvField::OpenPorts],
+            flatten: false,
+        };
+        assert_eq!(
+            format_results(&results, &options),
+            "subdomain,cname,open_ports\ncdn.example.com,cdn.example.net,"
+        );
+
+        let probed = vec![ScanResult {
+            subdomain: "www.example.com".to_string(),
+            probe: Some(ProbeResult {
+                http: Some(HttpResponse {
+                    status: 301,
+                    final_url: "https://www.example.com/".to_string(),
+                    title: None,
+                    content_length: Some(0),
+                }),
+                https: Some(HttpResponse {
+                    status: 200,
+                    final_url: "https://www.example.com/".to_string(),
+                    title: Some("Example, Inc".to_string()),
+                    content_length: Some(1256),
+                }),
+                tls_valid: Some(true),
+                tls_names: vec!["www.example.com".to_string()],
+                favicon_hash: Some(-1_252_041_730),
+                tech: vec!["nginx".to_string()],
+            }),
+            ..Default::default()
+        }];
+        let options = OutputOptions {
+            format: OutputFormat::Csv,
+            csv_fields: vec![
+                CsvField::Subdomain,
+                CsvField::HttpStatus,
+                CsvField::HttpsStatus,
+                CsvField::Title,
+                CsvField::FaviconHash,
+                CsvField::Tech,
+            ],
+            flatten: false,
+        };
+        assert_eq!(
+            format_results(&probed, &options),
+            "subdomain,http_status,https_status,title,favicon_hash,tech\nwww.example.com,301,200,\"Example, Inc\",-1252041730,nginx"
+        );
+        assert_eq!(
+            format_results(&results, &options),
+            "subdomain,http_status,https_status,title,favicon_hash,tech\ncdn.example.com,,,,,"
+        );
+
+        assert_eq!(csv_escape("a,\"b\""), "\"a,\"\"b\"\"\"");
+    }
+
+    #[test]
+    fn test_exact_json_and_csv() {
+        let results = vec![ScanResult {
+            subdomain: "old.example.com".to_string(),
+            ips: vec!["10.0.0.1".parse().unwrap()],
+            source: Source::Wayback,
+            ..Default::default()
+        }];
+        let csv = OutputOptions {
+            format: OutputFormat::Csv,
+            csv_fields: vec![CsvField::Subdomain, CsvField::Ips, CsvField::Source, CsvField::Status],
+            flatten: false,
+        };
+        assert_eq!(format_results(&results, &csv), "subdomain,ips,source,status\nold.example.com,10.0.0.1,wayback,valid");
+        assert_eq!(format_results(&[], &csv), "subdomain,ips,source,status");
+
+        let json = OutputOptions { format: OutputFormat::Json, ..csv };
+        assert_eq!(
+            format_results(&results, &json),
+            r#"[
+  {
+    "subdomain": "old.example.com",
+    "status": "valid",
+    "ips": [
+      "10.0.0.1"
+    ],
+    "record_types": [],
+    "open_ports": [],
+    "probe": null,
+    "screenshot": null,
+    "load_balanced": false,
+    "cname": null,
+    "cname_chain": [],
+    "cname_issue": null,
+    "dangling": false,
+    "takeover": null,
+    "ttl": null,
+    "latency_ms": null,
+    "low_ttl": false,
+    "private": false,
+    "source": "wayback"
+  }
+]"#
+        );
+        assert_eq!(format_results(&[], &json), "[]");
+    }
+
+    #[test]
+    fn test_flatten_results() {
+        let results = vec![
+            ScanResult {
+                subdomain: "cdn.example.com".to_string(),
+                ips: vec!["10.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap(), "10.0.0.3".parse().unwrap()],
+                load_balanced: true,
+                ..Default::default()
+            },
+            ScanResult {
+                subdomain: "old.example.com".to_string(),
+                ..Default::default()
+            },
+        ];
+        let options = |format, csv_fields| OutputOptions { format, csv_fields, flatten: true };
+
+        assert_eq!(
+            format_results(&results, &options(OutputFormat::Text, vec![])),
+            "cdn.example.com 10.0.0.1\ncdn.example.com 10.0.0.2\ncdn.example.com 10.0.0.3\nold.example.com"
+        );
+        assert_eq!(
+            format_results(&results, &options(OutputFormat::Csv, vec![CsvField::Subdomain, CsvField::Ips, CsvField::LoadBalanced])),
+            "subdomain,ips,load_balanced\ncdn.example.com,10.0.0.1,true\ncdn.example.com,10.0.0.2,true\ncdn.example.com,10.0.0.3,true\nold.example.com,,false"
+        );
+
+        let flat = flatten_results(&results);
+        assert_eq!(flat.len(), 4);
+        assert!(flat.iter().all(|result| result.ips.len() <= 1));
+    }
+
+    #[test]
+    fn test_report_json() {
+        let results = vec![ScanResult {
+            subdomain: "www.example.com".to_string(),
+            ips: vec!["93.184.216.34".parse().unwrap()],
+            open_ports: vec![443],
+            ..Default::default()
+        }];
+        let stats = ScanStats { total: 3, valid: 1, invalid: 2, skipped: 0, discrepancies: 0, unverified: 0, wildcards: 0, dangling: 0, timed_out: 0, servfail: 0, refused: 0, errors: 0, elapsed_ms: 120 };
+        let manifest = Manifest::new(vec!["example.com".to_string()], vec!["wordlist".to_string()]);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&Report::new(manifest, stats, results).to_json().unwrap()).unwrap();
+        assert_eq!(json["schema_version"], REPORT_SCHEMA_VERSION);
+        assert_eq!(json["manifest"]["targets"][0], "example.com");
+        assert_eq!(json["stats"]["valid"], 1);
+        assert_eq!(json["results"][0]["subdomain"], "www.example.com");
+        assert_eq!(json["results"][0]["ips"][0], "93.184.216.34");
+        assert_eq!(json["results"][0]["open_ports"][0], 443);
+        assert!(json["results"][0]["cname"].is_null());
+    }
+
+    #[test]
+    fn test_label_length_histogram() {
+        let results: Vec<ScanResult> = ["www.example.com", "api.example.com", "mail.example.com", "a.b.example.com"]
+            .iter()
+            .map(|name| ScanResult { subdomain: name.to_string(), ..Default::default() })
+            .collect();
+        let histogram = label_length_histogram(&results);
+        assert_eq!(histogram.into_iter().collect::<Vec<_>>(), vec![(1, 1), (3, 2), (4, 1)]);
+    }
+}
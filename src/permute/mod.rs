@@ -0,0 +1,382 @@
+use crate::wordlist::WordlistManager;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, BinaryHeap, HashMap, HashSet};
+
+/// Numeric suffixes appended to every label when generating permutations.
+pub const PERMUTATION_DIGITS: &[&str] = &["1", "2", "3", "01", "02"];
+
+/// Default highest number tried by `generate_number_mutations`.
+pub const DEFAULT_MAX_NUMBER: u32 = 10;
+
+/// Default cap on the names `generate_smart` returns.
+pub const DEFAULT_SMART_CANDIDATES: usize = 500;
+
+/// Share of a word's probability taken from what followed the previous word; the
+/// rest comes from how common the word is overall, so unseen pairs still score.
+const BIGRAM_WEIGHT: f64 = 0.7;
+
+/// Most likely next words tried after each word while searching.
+const BRANCHING: usize = 16;
+
+/// Partial names the search may expand per requested candidate before giving up.
+const SEARCH_BUDGET: usize = 20;
+
+/// Marks the start of a name as a context and its end as a next word.
+const START: &str = "^";
+const END: &str = "$";
+
+/// `label-affix` and `affix-label` for each affix, then `label1`-style numeric suffixes.
+/// Variants are not validated here.
+pub fn label_variants(label: &str, affixes: &[String]) -> Vec<String> {
+    let mut variants = Vec::with_capacity(affixes.len() * 2 + PERMUTATION_DIGITS.len());
+    for affix in affixes {
+        variants.push(format!("{}-{}", label, affix));
+        variants.push(format!("{}-{}", affix, label));
+    }
+    variants.extend(PERMUTATION_DIGITS.iter().map(|digits| format!("{}{}", label, digits)));
+    variants
+}
+
+/// Mutate the leftmost label of every found subdomain of `base_domain`, keeping the
+/// rest of the name: `api.example.com` gives `api-dev.example.com`, `dev-api.example.com`,
+/// `api2.example.com` and so on. Variants failing `WordlistManager::validate_word` and
+/// names already in `found` are dropped; the result is deduplicated and sorted.
+pub fn generate_permutations(found: &[String], base_domain: &str, affixes: &[String]) -> Vec<String> {
+    let suffix = format!(".{}", base_domain.trim_end_matches('.').to_lowercase());
+    let found: BTreeSet<String> = found.iter().map(|name| name.to_lowercase()).collect();
+
+    let mut permutations = BTreeSet::new();
+    for name in &found {
+        let Some(relative) = name.strip_suffix(&suffix) else {
+            continue;
+        };
+        let (label, parent) = match relative.split_once('.') {
+            Some((label, parent)) => (label, format!(".{}", parent)),
+            None => (relative, String::new()),
+        };
+        for variant in label_variants(label, affixes) {
+            let relative = format!("{}{}", variant, parent);
+            if !WordlistManager::validate_word(&relative) {
+                continue;
+            }
+            let candidate = format!("{}{}", relative, suffix);
+            if !found.contains(&candidate) {
+                permutations.insert(candidate);
+            }
+        }
+    }
+    permutations.into_iter().collect()
+}
+
+/// Numeric variants of the leftmost label of every found subdomain of `base_domain`.
+/// A label ending in digits has them replaced by 0 to `max_number`, keeping any zero
+/// padding (`app1` gives `app2`, `web-01` gives `web-02`); any other label gets
+/// `label1`, `label01`, `label-1` and `label-01` style suffixes from 1 to `max_number`.
+/// Variants failing `WordlistManager::validate_word` and names already in `found`
+/// are dropped; the result is deduplicated and sorted.
+pub fn generate_number_mutations(found: &[String], base_domain: &str, max_number: u32) -> Vec<String> {
+    let suffix = format!(".{}", base_domain.trim_end_matches('.').to_lowercase());
+    let found: BTreeSet<String> = found.iter().map(|name| name.to_lowercase()).collect();
+
+    let mut mutations = BTreeSet::new();
+    for name in &found {
+        let Some(relative) = name.strip_suffix(&suffix) else {
+            continue;
+        };
+        let (label, parent) = match relative.split_once('.') {
+            Some((label, parent)) => (label, format!(".{}", parent)),
+            None => (relative, String::new()),
+        };
+        let stem = label.trim_end_matches(|c: char| c.is_ascii_digit());
+        let digits = &label[stem.len()..];
+        let mut variants = Vec::new();
+        if digits.is_empty() {
+            for n in 1..=max_number {
+                variants.extend([
+                    format!("{}{}", label, n),
+                    format!("{}{:02}", label, n),
+                    format!("{}-{}", label, n),
+                    format!("{}-{:02}", label, n),
+                ]);
+            }
+        } else {
+            let width = if digits.starts_with('0') { digits.len() } else { 1 };
+            variants.extend((0..=max_number).map(|n| format!("{}{:0width$}", stem, n, width = width)));
+        }
+        for variant in variants {
+            let relative = format!("{}{}", variant, parent);
+            if !WordlistManager::validate_word(&relative) {
+                continue;
+            }
+            let candidate = format!("{}{}", relative, suffix);
+            if !found.contains(&candidate) {
+                mutations.insert(candidate);
+            }
+        }
+    }
+    mutations.into_iter().collect()
+}
+
+/// Word bigram model of names relative to the target domain, split on `-` and `.`:
+/// `api-dev.eu` is `^ api dev eu $`.
+struct TokenModel {
+    /// How often each word followed each context word
+    followers: HashMap<String, HashMap<String, u32>>,
+    /// How often each word appeared, with the end of a name counted once per name
+    counts: HashMap<String, u32>,
+    total: u32,
+    /// Separator first seen before each word
+    separators: HashMap<String, char>,
+    /// Most words in one training name
+    longest: usize,
+}
+
+impl TokenModel {
+    fn train<'a>(names: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut model = TokenModel {
+            followers: HashMap::new(),
+            counts: HashMap::new(),
+            total: 0,
+            separators: HashMap::new(),
+            longest: 0,
+        };
+        for name in names {
+            let mut previous = START.to_string();
+            let mut words = 0;
+            let mut rest = name;
+            while !rest.is_empty() {
+                let separator = rest.chars().next().filter(|c| *c == '-' || *c == '.');
+                if let Some(separator) = separator {
+                    rest = &rest[1..];
+                    let end = rest.find(['-', '.']).unwrap_or(rest.len());
+                    model.separators.entry(rest[..end].to_string()).or_insert(separator);
+                }
+                let end = rest.find(['-', '.']).unwrap_or(rest.len());
+                let word = &rest[..end];
+                rest = &rest[end..];
+                if word.is_empty() {
+                    continue;
+                }
+                model.observe(&previous, word);
+                previous = word.to_string();
+                words += 1;
+            }
+            if words > 0 {
+                model.observe(&previous, END);
+                model.longest = model.longest.max(words);
+            }
+        }
+        model
+    }
+
+    fn observe(&mut self, previous: &str, word: &str) {
+        *self.followers.entry(previous.to_string()).or_default().entry(word.to_string()).or_default() += 1;
+        *self.counts.entry(word.to_string()).or_default() += 1;
+        self.total += 1;
+    }
+
+    /// The `BRANCHING` most likely words after `previous` with their log probabilities,
+    /// best first.
+    fn next_words(&self, previous: &str) -> Vec<(String, f64)> {
+        let followers = self.followers.get(previous);
+        let seen = followers.map_or(0, |followers| followers.values().sum::<u32>());
+        let mut words: Vec<(String, f64)> = self
+            .counts
+            .iter()
+            .map(|(word, &count)| {
+                let bigram = match followers.and_then(|followers| followers.get(word)) {
+                    Some(&pair) => pair as f64 / seen as f64,
+                    None => 0.0,
+                };
+                let unigram = count as f64 / self.total as f64;
+                (word.clone(), (BIGRAM_WEIGHT * bigram + (1.0 - BIGRAM_WEIGHT) * unigram).ln())
+            })
+            .collect();
+        words.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        words.truncate(BRANCHING);
+        words
+    }
+
+    /// Join words with the separator first seen before each, `-` for unseen ones.
+    fn join(&self, words: &[String]) -> String {
+        let mut name = String::new();
+        for word in words {
+            if !name.is_empty() {
+                name.push(self.separators.get(word).copied().unwrap_or('-'));
+            }
+            name.push_str(word);
+        }
+        name
+    }
+}
+
+/// A name under construction in `generate_smart`'s best-first search.
+struct Partial {
+    log_prob: f64,
+    words: Vec<String>,
+    complete: bool,
+}
+
+impl PartialEq for Partial {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Partial {}
+
+impl PartialOrd for Partial {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Partial {
+    /// Most probable first, ties broken towards the alphabetically smaller name.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.log_prob.total_cmp(&other.log_prob).then_with(|| other.words.cmp(&self.words))
+    }
+}
+
+/// Train a word bigram model on the found subdomains of `base_domain` and return up
+/// to `max` of the most probable new names it produces, most probable first:
+/// from `api-dev`, `web-prod` and `api-prod` it proposes `web-dev`. Names repeat no
+/// word, are no longer than the longest found name, and skip anything already in
+/// `found` or failing `WordlistManager::validate_word`.
+pub fn generate_smart(found: &[String], base_domain: &str, max: usize) -> Vec<String> {
+    let suffix = format!(".{}", base_domain.trim_end_matches('.').to_lowercase());
+    let found: BTreeSet<String> = found.iter().map(|name| name.to_lowercase()).collect();
+    let model = TokenModel::train(found.iter().filter_map(|name| name.strip_suffix(&suffix)));
+
+    let mut next_words: HashMap<String, Vec<(String, f64)>> = HashMap::new();
+    let mut queue = BinaryHeap::from([Partial { log_prob: 0.0, words: Vec::new(), complete: false }]);
+    let mut generated = Vec::new();
+    let mut emitted = HashSet::new();
+    let mut budget = max.saturating_mul(SEARCH_BUDGET);
+    while generated.len() < max && budget > 0 {
+        let Some(partial) = queue.pop() else {
+            break;
+        };
+        budget -= 1;
+        if partial.complete {
+            let candidate = format!("{}{}", model.join(&partial.words), suffix);
+            let relative = &candidate[..candidate.len() - suffix.len()];
+            if WordlistManager::validate_word(relative) && !found.contains(&candidate) && emitted.insert(candidate.clone()) {
+                generated.push(candidate);
+            }
+            continue;
+        }
+        let previous = partial.words.last().map_or(START, String::as_str);
+        let followers = next_words.entry(previous.to_string()).or_insert_with(|| model.next_words(previous));
+        for (word, log_prob) in followers.iter() {
+            let complete = word == END;
+            if complete && partial.words.is_empty() {
+                continue;
+            }
+            if !complete && (partial.words.len() >= model.longest || partial.words.contains(word)) {
+                continue;
+            }
+            let mut words = partial.words.clone();
+            if !complete {
+                words.push(word.clone());
+            }
+            queue.push(Partial { log_prob: partial.log_prob + log_prob, words, complete });
+        }
+    }
+    generated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_permutations() {
+        let found = vec![
+            "api.example.com".to_string(),
+            "API-DEV.example.com".to_string(),
+            "v2.api.example.com".to_string(),
+            "example.com".to_string(),
+            "www.other.org".to_string(),
+        ];
+        let affixes = vec!["dev".to_string(), "-bad".to_string()];
+        let permutations = generate_permutations(&found, "example.com", &affixes);
+
+        assert_eq!(
+            permutations.iter().filter(|name| !name.ends_with(".api.example.com")).collect::<Vec<_>>(),
+            vec![
+                "api--bad.example.com",
+                "api-dev--bad.example.com",
+                "api-dev-dev.example.com",
+                "api-dev01.example.com",
+                "api-dev02.example.com",
+                "api-dev1.example.com",
+                "api-dev2.example.com",
+                "api-dev3.example.com",
+                "api01.example.com",
+                "api02.example.com",
+                "api1.example.com",
+                "api2.example.com",
+                "api3.example.com",
+                "dev-api-dev.example.com",
+                "dev-api.example.com",
+            ]
+        );
+        // Only the leftmost label is mutated
+        assert!(permutations.contains(&"v2-dev.api.example.com".to_string()));
+        assert!(permutations.contains(&"dev-v2.api.example.com".to_string()));
+        // `api-dev` was already found, and a label cannot start with `-`
+        assert!(!permutations.contains(&"api-dev.example.com".to_string()));
+        assert!(!permutations.iter().any(|name| name.starts_with("-bad")));
+    }
+
+    #[test]
+    fn test_generate_number_mutations() {
+        let found: Vec<String> =
+            ["app1", "web-01", "db007.eu", "mail", "v2.api"].iter().map(|label| format!("{}.example.com", label)).collect();
+        let mutations = generate_number_mutations(&found, "example.com", 3);
+
+        let variants = |prefix: &str| -> Vec<&str> {
+            mutations
+                .iter()
+                .filter(|name| name.starts_with(prefix))
+                .map(|name| name.trim_end_matches(".example.com"))
+                .collect()
+        };
+        assert_eq!(variants("app"), vec!["app0", "app2", "app3"]);
+        assert_eq!(variants("web"), vec!["web-00", "web-02", "web-03"]);
+        assert_eq!(variants("db"), vec!["db000.eu", "db001.eu", "db002.eu", "db003.eu"]);
+        assert_eq!(
+            variants("mail"),
+            vec!["mail-01", "mail-02", "mail-03", "mail-1", "mail-2", "mail-3", "mail01", "mail02", "mail03", "mail1", "mail2", "mail3"]
+        );
+        // Only the leftmost label is mutated, and found names are not repeated
+        assert_eq!(variants("v"), vec!["v0.api", "v1.api", "v3.api"]);
+        assert!(!mutations.iter().any(|name| found.contains(name)));
+    }
+
+    #[test]
+    fn test_generate_smart() {
+        let found: Vec<String> = ["api-dev", "web-prod", "api-prod", "mail", "v2.API", "dev-Portal"]
+            .iter()
+            .map(|label| format!("{}.example.com", label))
+            .chain(["www.other.org".to_string()])
+            .collect();
+        let generated = generate_smart(&found, "example.com", 50);
+
+        assert!(generated.contains(&"web-dev.example.com".to_string()));
+        assert!(generated.contains(&"api.example.com".to_string()));
+        // `api` was seen after a `.`, so it is joined with one
+        assert!(generated.contains(&"web.api.example.com".to_string()));
+        assert!(generated.iter().all(|name| name.ends_with(".example.com")));
+        assert!(!generated.iter().any(|name| found.contains(name) || name == "v2.api.example.com"));
+        assert!(generated.iter().all(|name| name.trim_end_matches(".example.com").split(['-', '.']).count() <= 2));
+        let unique: BTreeSet<&String> = generated.iter().collect();
+        assert_eq!(unique.len(), generated.len());
+
+        // Deterministic, and capped
+        assert_eq!(generate_smart(&found, "example.com", 50), generated);
+        assert_eq!(generate_smart(&found, "example.com", 3), generated[..3]);
+        assert!(generate_smart(&[], "example.com", 10).is_empty());
+    }
+}
@@ -0,0 +1,666 @@
+use crate::console::progress_bar;
+use crate::scanner::ScanResult;
+use colored::*;
+use futures::stream::{self, StreamExt};
+use reqwest::{redirect, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::time::Duration;
+
+/// Redirects followed per request before the last response is kept as is.
+pub const MAX_REDIRECTS: usize = 5;
+
+/// Timeout for each probe request, redirects included.
+pub const DEFAULT_PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How much of an HTML body is read looking for its `<title>`.
+const TITLE_SCAN_BYTES: usize = 64 * 1024;
+
+/// Largest favicon read; bigger bodies are not hashed.
+const MAX_FAVICON_BYTES: usize = 1024 * 1024;
+
+/// DER encoding of the subjectAltName extension OID, 2.5.29.17.
+const SUBJECT_ALT_NAME_OID: &[u8] = &[0x55, 0x1d, 0x11];
+
+/// DER encoding of the commonName attribute OID, 2.5.4.3.
+const COMMON_NAME_OID: &[u8] = &[0x55, 0x04, 0x03];
+
+#[derive(Debug)]
+pub enum ProbeError {
+    ClientError(String),
+}
+
+impl std::fmt::Display for ProbeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProbeError::ClientError(e) => write!(f, "Failed to build HTTP client: {}", e),
+        }
+    }
+}
+
+impl Error for ProbeError {}
+
+/// The last response of a request, after redirects were followed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub final_url: String,
+    /// `<title>` of an HTML page, whitespace collapsed
+    pub title: Option<String>,
+    /// Body size in bytes, from `Content-Length` or a fully read HTML body
+    pub content_length: Option<u64>,
+}
+
+/// What a host answered over plain HTTP and over HTTPS; `None` when a scheme gave
+/// no response (refused, timed out, TLS handshake failure).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProbeResult {
+    pub http: Option<HttpResponse>,
+    pub https: Option<HttpResponse>,
+    /// Whether the HTTPS certificate verified; `None` without an HTTPS response
+    pub tls_valid: Option<bool>,
+    /// Common names and DNS subject alternative names of the HTTPS certificate
+    pub tls_names: Vec<String>,
+    /// Shodan-style MMH3 hash of `/favicon.ico` (`http.favicon.hash:<value>`)
+    pub favicon_hash: Option<i32>,
+    /// Technologies recognized by `TECH_RULES` in either scheme's response, sorted
+    pub tech: Vec<String>,
+}
+
+impl ProbeResult {
+    pub fn is_live(&self) -> bool {
+        self.http.is_some() || self.https.is_some()
+    }
+}
+
+impl std::fmt::Display for ProbeResult {
+    /// `http: 301 -> https://example.com/, https: 200 -> https://example.com/ "Example" (1256 bytes) (invalid cert)`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.is_live() {
+            return write!(f, "no web service");
+        }
+        let mut parts = Vec::new();
+        for (scheme, response) in [("http", &self.http), ("https", &self.https)] {
+            if let Some(response) = response {
+                let mut part = format!("{}: {} -> {}", scheme, response.status, response.final_url);
+                if let Some(title) = &response.title {
+                    part.push_str(&format!(" {:?}", title));
+                }
+                if let Some(length) = response.content_length {
+                    part.push_str(&format!(" ({} bytes)", length));
+                }
+                if scheme == "https" && self.tls_valid == Some(false) {
+                    part.push_str(" (invalid cert)");
+                }
+                parts.push(part);
+            }
+        }
+        write!(f, "{}", parts.join(", "))?;
+        if !self.tech.is_empty() {
+            write!(f, " | {}", self.tech.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// One scheme's response with what was read from it besides `HttpResponse`.
+struct Fetched {
+    response: HttpResponse,
+    /// Names in the certificate, empty over plain HTTP
+    tls_names: Vec<String>,
+    tech: Vec<String>,
+}
+
+pub struct HttpProber {
+    client: reqwest::Client,
+    /// Skips certificate checks, to still reach HTTPS services the client rejects.
+    insecure_client: reqwest::Client,
+    concurrency: usize,
+}
+
+impl HttpProber {
+    /// Probes run `concurrency` hosts at a time.
+    pub fn new(concurrency: usize) -> Result<Self, ProbeError> {
+        Ok(HttpProber {
+            client: build_client(false)?,
+            insecure_client: build_client(true)?,
+            concurrency: concurrency.max(1),
+        })
+    }
+
+    /// Request `host` over both schemes. Failures only leave that scheme empty. An
+    /// HTTPS service with a certificate that does not verify is still recorded, with
+    /// `tls_valid` set to false.
+    pub async fn probe(&self, host: &str) -> ProbeResult {
+        let (http, (https, tls_valid)) = tokio::join!(self.request(&self.client, "http", host), self.probe_https(host));
+        let mut tech: Vec<String> = http.iter().chain(&https).flat_map(|fetched| fetched.tech.clone()).collect();
+        tech.sort();
+        tech.dedup();
+        let tls_names = https.as_ref().map(|fetched| fetched.tls_names.clone()).unwrap_or_default();
+        let (http, https) = (http.map(|fetched| fetched.response), https.map(|fetched| fetched.response));
+        let favicon_hash = match https.as_ref().or(http.as_ref()) {
+            Some(page) => self.fetch_favicon(&page.final_url).await.map(|icon| favicon_hash(&icon)),
+            None => None,
+        };
+        ProbeResult { http, https, tls_valid, tls_names, favicon_hash, tech }
+    }
+
+    /// `/favicon.ico` on the server of `page_url`, if it answers 200 with a non-empty
+    /// body of at most `MAX_FAVICON_BYTES`. Certificates are not checked.
+    async fn fetch_favicon(&self, page_url: &str) -> Option<Vec<u8>> {
+        let url = reqwest::Url::parse(page_url).ok()?.join("/favicon.ico").ok()?;
+        let mut response = self.insecure_client.get(url).send().await.ok()?;
+        if response.status() != StatusCode::OK {
+            return None;
+        }
+        let mut icon = Vec::new();
+        while let Some(chunk) = response.chunk().await.ok()? {
+            icon.extend_from_slice(&chunk);
+            if icon.len() > MAX_FAVICON_BYTES {
+                return None;
+            }
+        }
+        (!icon.is_empty()).then_some(icon)
+    }
+
+    async fn probe_https(&self, host: &str) -> (Option<Fetched>, Option<bool>) {
+        if let Some(fetched) = self.request(&self.client, "https", host).await {
+            return (Some(fetched), Some(true));
+        }
+        match self.request(&self.insecure_client, "https", host).await {
+            Some(fetched) => (Some(fetched), Some(false)),
+            None => (None, None),
+        }
+    }
+
+    /// Probe every result and store the outcome in its `probe` field.
+    pub async fn probe_all(&self, results: &mut [ScanResult]) {
+        say!("{} Probing {} subdomains over HTTP and HTTPS", "[*]".blue(), results.len());
+        let progress = progress_bar(results.len() as u64, "Probing HTTP(S)...");
+
+        let probes: Vec<(usize, ProbeResult)> = stream::iter(results.iter().enumerate())
+            .map(|(index, result)| {
+                let progress = progress.clone();
+                async move {
+                    let probe = self.probe(&result.subdomain).await;
+                    progress.inc(1);
+                    (index, probe)
+                }
+            })
+            .buffer_unordered(self.concurrency)
+            .collect()
+            .await;
+        progress.finish_with_message("probe completed");
+
+        let live = probes.iter().filter(|(_, probe)| probe.is_live()).count();
+        for (index, probe) in probes {
+            results[index].probe = Some(probe);
+        }
+        say!("{} {} subdomains answered over HTTP(S)", "[+]".green(), live);
+    }
+
+    /// `HEAD` first, then `GET` for servers that reject or fail it and for HTML
+    /// pages, whose title and technologies need the body.
+    async fn request(&self, client: &reqwest::Client, scheme: &str, host: &str) -> Option<Fetched> {
+        let url = format!("{}://{}/", scheme, host);
+        let head = client.head(&url).send().await.ok();
+        let rejected = [StatusCode::METHOD_NOT_ALLOWED, StatusCode::NOT_IMPLEMENTED];
+        let from_head = |response: &reqwest::Response| Fetched {
+            response: HttpResponse {
+                status: response.status().as_u16(),
+                final_url: response.url().to_string(),
+                title: None,
+                content_length: content_length(response),
+            },
+            tls_names: peer_certificate_names(response),
+            tech: detect_technologies(response.headers(), ""),
+        };
+        match head {
+            Some(response) if !rejected.contains(&response.status()) && !is_html(&response) => Some(from_head(&response)),
+            head => match client.get(&url).send().await {
+                Ok(response) => {
+                    let tls_names = peer_certificate_names(&response);
+                    let headers = response.headers().clone();
+                    let (response, body) = read_response(response).await;
+                    Some(Fetched { response, tls_names, tech: detect_technologies(&headers, &body) })
+                }
+                Err(_) => head.as_ref().map(from_head),
+            },
+        }
+    }
+}
+
+fn build_client(accept_invalid_certs: bool) -> Result<reqwest::Client, ProbeError> {
+    reqwest::Client::builder()
+        .redirect(redirect::Policy::limited(MAX_REDIRECTS))
+        .timeout(DEFAULT_PROBE_TIMEOUT)
+        .danger_accept_invalid_certs(accept_invalid_certs)
+        .tls_info(true)
+        .build()
+        .map_err(|e| ProbeError::ClientError(e.to_string()))
+}
+
+fn is_html(response: &reqwest::Response) -> bool {
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("html"))
+}
+
+/// The `Content-Length` header; the body itself is not read. Unlike
+/// `Response::content_length`, this is right for `HEAD` responses too.
+fn content_length(response: &reqwest::Response) -> Option<u64> {
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+}
+
+/// Status, final URL and size of a `GET` response, plus the title of an HTML body.
+/// Also returns the start of that body, empty for other content.
+async fn read_response(mut response: reqwest::Response) -> (HttpResponse, String) {
+    let status = response.status().as_u16();
+    let final_url = response.url().to_string();
+    let mut length = content_length(&response);
+    let mut title = None;
+    let mut text = String::new();
+    if is_html(&response) {
+        let mut body = Vec::new();
+        let mut complete = false;
+        loop {
+            match response.chunk().await {
+                Ok(Some(chunk)) => body.extend_from_slice(&chunk),
+                Ok(None) => {
+                    complete = true;
+                    break;
+                }
+                Err(_) => break,
+            }
+            if body.len() >= TITLE_SCAN_BYTES {
+                break;
+            }
+        }
+        if complete {
+            length = length.or(Some(body.len() as u64));
+        }
+        text = String::from_utf8_lossy(&body).into_owned();
+        title = parse_title(&text);
+    }
+    (HttpResponse { status, final_url, title, content_length: length }, text)
+}
+
+/// Where a `TECH_RULES` entry looks. Patterns are matched case-insensitively.
+pub enum Evidence {
+    /// A response header containing the pattern; an empty pattern matches any value
+    Header(&'static str, &'static str),
+    /// A cookie set by the response, by exact name
+    Cookie(&'static str),
+    /// Text in the first `TITLE_SCAN_BYTES` of an HTML body
+    Body(&'static str),
+}
+
+/// Built-in technology fingerprints, Wappalyzer-style: a technology is reported when
+/// any of its evidence matches.
+pub const TECH_RULES: &[(&str, &[Evidence])] = &[
+    ("nginx", &[Evidence::Header("server", "nginx")]),
+    ("OpenResty", &[Evidence::Header("server", "openresty")]),
+    ("Apache HTTP Server", &[Evidence::Header("server", "apache")]),
+    ("Apache Tomcat", &[Evidence::Header("server", "tomcat"), Evidence::Body("<title>apache tomcat")]),
+    ("Microsoft IIS", &[Evidence::Header("server", "microsoft-iis")]),
+    ("LiteSpeed", &[Evidence::Header("server", "litespeed")]),
+    ("Caddy", &[Evidence::Header("server", "caddy")]),
+    ("Envoy", &[Evidence::Header("server", "envoy"), Evidence::Header("x-envoy-upstream-service-time", "")]),
+    ("Gunicorn", &[Evidence::Header("server", "gunicorn")]),
+    ("Cloudflare", &[Evidence::Header("server", "cloudflare"), Evidence::Header("cf-ray", "")]),
+    ("Amazon CloudFront", &[Evidence::Header("x-amz-cf-id", "")]),
+    ("Amazon S3", &[Evidence::Header("server", "amazons3")]),
+    ("Akamai", &[Evidence::Header("server", "akamaighost")]),
+    ("Fastly", &[Evidence::Header("x-fastly-request-id", "")]),
+    ("Varnish", &[Evidence::Header("x-varnish", ""), Evidence::Header("via", "varnish")]),
+    ("PHP", &[Evidence::Header("x-powered-by", "php"), Evidence::Cookie("PHPSESSID")]),
+    ("ASP.NET", &[
+        Evidence::Header("x-powered-by", "asp.net"),
+        Evidence::Header("x-aspnet-version", ""),
+        Evidence::Cookie("ASP.NET_SessionId"),
+    ]),
+    ("Java", &[Evidence::Cookie("JSESSIONID")]),
+    ("Express", &[Evidence::Header("x-powered-by", "express")]),
+    ("Next.js", &[Evidence::Header("x-powered-by", "next.js"), Evidence::Body("__next_data__")]),
+    ("Laravel", &[Evidence::Cookie("laravel_session")]),
+    ("Django", &[Evidence::Body("csrfmiddlewaretoken")]),
+    ("WordPress", &[Evidence::Body("/wp-content/"), Evidence::Body("/wp-includes/"), Evidence::Header("link", "api.w.org")]),
+    ("Drupal", &[Evidence::Header("x-drupal-cache", ""), Evidence::Header("x-generator", "drupal"), Evidence::Body("drupal-settings-json")]),
+    ("Joomla", &[Evidence::Body("content=\"joomla")]),
+    ("Jenkins", &[Evidence::Header("x-jenkins", "")]),
+    ("GitLab", &[Evidence::Cookie("_gitlab_session")]),
+    ("Grafana", &[Evidence::Body("grafanabootdata")]),
+    ("Kibana", &[Evidence::Header("kbn-name", "")]),
+    ("Atlassian Confluence", &[Evidence::Header("x-confluence-request-time", "")]),
+    ("Atlassian Jira", &[Evidence::Header("x-arequestid", ""), Evidence::Body("jira.webresources")]),
+    ("Microsoft Outlook Web App", &[Evidence::Header("x-owa-version", ""), Evidence::Body("/owa/auth/")]),
+    ("phpMyAdmin", &[Evidence::Cookie("phpMyAdmin"), Evidence::Body("phpmyadmin")]),
+    ("Shopify", &[Evidence::Header("x-shopid", "")]),
+    ("React", &[Evidence::Body("data-reactroot")]),
+    ("Angular", &[Evidence::Body("ng-version=")]),
+    ("jQuery", &[Evidence::Body("jquery")]),
+    ("Google Tag Manager", &[Evidence::Body("googletagmanager.com")]),
+];
+
+/// Names of the `TECH_RULES` entries matching a response's headers and HTML `body`.
+pub fn detect_technologies(headers: &reqwest::header::HeaderMap, body: &str) -> Vec<String> {
+    let body = body.to_lowercase();
+    let cookies: Vec<String> = headers
+        .get_all(reqwest::header::SET_COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .filter_map(|cookie| cookie.split_once('=').map(|(name, _)| name.trim().to_lowercase()))
+        .collect();
+    let matches = |evidence: &Evidence| match evidence {
+        Evidence::Header(name, pattern) => headers
+            .get_all(*name)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .any(|value| value.to_lowercase().contains(pattern)),
+        Evidence::Cookie(name) => cookies.contains(&name.to_lowercase()),
+        Evidence::Body(pattern) => !body.is_empty() && body.contains(pattern),
+    };
+    TECH_RULES
+        .iter()
+        .filter(|(_, evidence)| evidence.iter().any(matches))
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+/// The favicon hash Shodan indexes: MurmurHash3 (x86, 32-bit, seed 0) of the
+/// base64 encoding with a newline every 76 characters and at the end, as Python's
+/// `mmh3.hash(base64.encodebytes(data))` computes it.
+pub fn favicon_hash(data: &[u8]) -> i32 {
+    let encoded = base64_lines(data);
+    murmur3_32(encoded.as_bytes(), 0) as i32
+}
+
+/// Standard base64 in MIME-style lines of 76 characters, each ending in `\n`.
+fn base64_lines(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len() * 4 / 3 + data.len() / 57 + 4);
+    for line in data.chunks(57) {
+        for group in line.chunks(3) {
+            let bytes = [group[0], *group.get(1).unwrap_or(&0), *group.get(2).unwrap_or(&0)];
+            let n = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+            for i in 0..4 {
+                if i <= group.len() {
+                    encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+                } else {
+                    encoded.push('=');
+                }
+            }
+        }
+        encoded.push('\n');
+    }
+    encoded
+}
+
+/// MurmurHash3, x86 32-bit variant.
+fn murmur3_32(data: &[u8], seed: u32) -> u32 {
+    const C1: u32 = 0xcc9e_2d51;
+    const C2: u32 = 0x1b87_3593;
+    let mut hash = seed;
+    let blocks = data.chunks_exact(4);
+    let tail = blocks.remainder();
+    for block in blocks {
+        let k = u32::from_le_bytes([block[0], block[1], block[2], block[3]]);
+        hash ^= k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+        hash = hash.rotate_left(13).wrapping_mul(5).wrapping_add(0xe654_6b64);
+    }
+    if !tail.is_empty() {
+        let k = tail.iter().rev().fold(0u32, |k, &byte| k << 8 | byte as u32);
+        hash ^= k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+    }
+    hash ^= data.len() as u32;
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2_ae35);
+    hash ^ (hash >> 16)
+}
+
+fn peer_certificate_names(response: &reqwest::Response) -> Vec<String> {
+    response
+        .extensions()
+        .get::<reqwest::tls::TlsInfo>()
+        .and_then(|info| info.peer_certificate())
+        .map(certificate_names)
+        .unwrap_or_default()
+}
+
+/// Names in a DER-encoded X.509 certificate: the DNS entries of its subjectAltName
+/// extension and any commonName attribute (issuer's included), lowercased, sorted and
+/// deduplicated. Malformed input yields whatever was read before the damage.
+pub fn certificate_names(der: &[u8]) -> Vec<String> {
+    let mut names = Vec::new();
+    collect_certificate_names(der, &mut names);
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Walk a run of DER elements, descending into constructed ones. An extension value
+/// or attribute value follows its OID in the same sequence, with at most a `critical`
+/// boolean in between.
+fn collect_certificate_names(mut data: &[u8], names: &mut Vec<String>) {
+    let mut oid: Option<&[u8]> = None;
+    while let Some((tag, contents, rest)) = der_element(data) {
+        data = rest;
+        match (tag, oid) {
+            // OCTET STRING wrapping a SEQUENCE of GeneralName; dNSName is [2]
+            (0x04, Some(SUBJECT_ALT_NAME_OID)) => {
+                if let Some((0x30, mut general_names, _)) = der_element(contents) {
+                    while let Some((tag, value, rest)) = der_element(general_names) {
+                        if tag == 0x82 {
+                            names.push(String::from_utf8_lossy(value).to_lowercase());
+                        }
+                        general_names = rest;
+                    }
+                }
+            }
+            // UTF8String, PrintableString, TeletexString or IA5String
+            (0x0c | 0x13 | 0x14 | 0x16, Some(COMMON_NAME_OID)) => {
+                names.push(String::from_utf8_lossy(contents).to_lowercase());
+            }
+            _ if tag & 0x20 != 0 => collect_certificate_names(contents, names),
+            _ => {}
+        }
+        oid = match tag {
+            0x06 => Some(contents),
+            0x01 => oid,
+            _ => None,
+        };
+    }
+}
+
+/// Split the first DER element off `data`: its tag, contents and the bytes after it.
+/// Only single-byte tags and definite lengths, which is all DER allows for X.509.
+fn der_element(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, data) = data.split_first()?;
+    let (&first, mut data) = data.split_first()?;
+    let len = if first < 0x80 {
+        first as usize
+    } else {
+        let octets = (first & 0x7f) as usize;
+        if octets == 0 || octets > 4 || data.len() < octets {
+            return None;
+        }
+        let len = data[..octets].iter().fold(0usize, |len, &byte| len << 8 | byte as usize);
+        data = &data[octets..];
+        len
+    };
+    (data.len() >= len).then(|| (tag, &data[..len], &data[len..]))
+}
+
+/// The text of the first `<title>` element, whitespace collapsed; `None` if empty.
+fn parse_title(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let open = lower.find("<title")?;
+    let start = open + lower[open..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+    let title = html[start..end].split_whitespace().collect::<Vec<_>>().join(" ");
+    (!title.is_empty()).then_some(title)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Plain HTTP server: `/` redirects to `/home`, an HTML page; `HEAD` gets 405.
+    /// `/favicon.ico` is the four bytes `icon`.
+    async fn http_server() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = vec![0; 1024];
+                let len = stream.read(&mut request).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..len]).to_string();
+                let response = if request.starts_with("HEAD") {
+                    "HTTP/1.1 405 Method Not Allowed\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+                } else if request.starts_with("GET /favicon.ico ") {
+                    "HTTP/1.1 200 OK\r\nContent-Type: image/x-icon\r\nContent-Length: 4\r\nConnection: close\r\n\r\nicon".to_string()
+                } else if request.starts_with("GET / ") {
+                    "HTTP/1.1 302 Found\r\nLocation: /home\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+                } else {
+                    let body = "<html><head><TITLE>\n  Home\n  Page </TITLE></head><body>hi</body></html>";
+                    format!(
+                        "HTTP/1.1 200 OK\r\nServer: nginx/1.25.3\r\nSet-Cookie: PHPSESSID=abc; path=/\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                };
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn test_probe() {
+        let port = http_server().await;
+        let host = format!("127.0.0.1:{}", port);
+        let prober = HttpProber::new(4).unwrap();
+
+        // HEAD is refused, so GET follows the redirect and reads the title
+        let result = prober.probe(&host).await;
+        assert_eq!(
+            result.http,
+            Some(HttpResponse {
+                status: 200,
+                final_url: format!("http://{}/home", host),
+                title: Some("Home Page".to_string()),
+                content_length: Some(71),
+            })
+        );
+        // Plain HTTP server, so the TLS handshake fails
+        assert_eq!((&result.https, result.tls_valid), (&None, None));
+        assert_eq!(result.favicon_hash, Some(favicon_hash(b"icon")));
+        assert_eq!(result.tech, vec!["PHP", "nginx"]);
+        assert_eq!(result.to_string(), format!("http: 200 -> http://{}/home \"Home Page\" (71 bytes) | PHP, nginx", host));
+
+        // Nothing listens on a just-closed port: no web service, no error
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let result = prober.probe(&closed.to_string()).await;
+        assert!(!result.is_live());
+        assert_eq!(result.favicon_hash, None);
+        assert_eq!(result.to_string(), "no web service");
+
+        let mut results = vec![ScanResult { subdomain: host, ..Default::default() }];
+        prober.probe_all(&mut results).await;
+        assert!(results[0].probe.as_ref().is_some_and(ProbeResult::is_live));
+    }
+
+    #[test]
+    fn test_certificate_names() {
+        fn der(tag: u8, contents: &[u8]) -> Vec<u8> {
+            let mut element = vec![tag];
+            match contents.len() {
+                len if len < 0x80 => element.push(len as u8),
+                len => element.extend([0x82, (len >> 8) as u8, len as u8]),
+            }
+            element.extend_from_slice(contents);
+            element
+        }
+        let seq = |parts: &[Vec<u8>]| der(0x30, &parts.concat());
+        let oid = |oid: &[u8]| der(0x06, oid);
+
+        let common_name = |name: &str| der(0x31, &seq(&[oid(COMMON_NAME_OID), der(0x0c, name.as_bytes())]));
+        let general_names = seq(&[
+            der(0x82, b"API.example.com"),
+            der(0x81, b"admin@example.com"),
+            der(0x82, b"*.dev.example.com"),
+        ]);
+        let san = seq(&[oid(SUBJECT_ALT_NAME_OID), der(0x01, &[0x00]), der(0x04, &general_names)]);
+        // An unrelated extension with a [2] tag must not be read as a name
+        let other = seq(&[oid(&[0x55, 0x1d, 0x0f]), der(0x04, &der(0x82, b"not.a.name"))]);
+        let tbs = seq(&[
+            der(0x02, &[0x01]),
+            seq(&[common_name("Example CA")]),
+            der(0x04, &[0u8; 300]),
+            seq(&[common_name("www.example.com")]),
+            der(0xa3, &seq(&[other, san])),
+        ]);
+        let certificate = seq(&[tbs, seq(&[oid(&[0x2a, 0x86, 0x48])]), der(0x03, &[0x00])]);
+
+        assert_eq!(
+            certificate_names(&certificate),
+            vec!["*.dev.example.com", "api.example.com", "example ca", "www.example.com"]
+        );
+        // Truncated input keeps what was read and never panics
+        assert!(certificate_names(&certificate[..certificate.len() / 2]).is_empty());
+        assert!(certificate_names(&[0x30, 0x84, 0xff]).is_empty());
+    }
+
+    #[test]
+    fn test_detect_technologies() {
+        use reqwest::header::{HeaderMap, HeaderValue};
+
+        let mut headers = HeaderMap::new();
+        headers.insert("server", HeaderValue::from_static("Microsoft-IIS/10.0"));
+        headers.insert("x-aspnet-version", HeaderValue::from_static("4.0.30319"));
+        headers.append("set-cookie", HeaderValue::from_static("theme=dark"));
+        headers.append("set-cookie", HeaderValue::from_static("JSESSIONID=1; HttpOnly"));
+        let body = r#"<link rel="stylesheet" href="/WP-Content/themes/x.css">"#;
+        assert_eq!(detect_technologies(&headers, body), vec!["Microsoft IIS", "ASP.NET", "Java", "WordPress"]);
+
+        // Body rules need a body, and a cookie must match by its whole name
+        let mut headers = HeaderMap::new();
+        headers.insert("set-cookie", HeaderValue::from_static("XJSESSIONID=1"));
+        assert!(detect_technologies(&headers, "").is_empty());
+    }
+
+    #[test]
+    fn test_favicon_hash() {
+        assert_eq!(murmur3_32(b"", 0), 0);
+        assert_eq!(murmur3_32(b"hello", 0), 0x248b_fa47);
+        assert_eq!(murmur3_32(b"The quick brown fox jumps over the lazy dog", 0), 0x2e4f_f723);
+        assert_eq!(murmur3_32(b"", 1), 0x514e_28b7);
+
+        assert_eq!(base64_lines(b""), "");
+        assert_eq!(base64_lines(b"f"), "Zg==\n");
+        assert_eq!(base64_lines(b"foob"), "Zm9vYg==\n");
+        assert_eq!(base64_lines(b"foobar"), "Zm9vYmFy\n");
+        let long = base64_lines(&[0u8; 60]);
+        assert_eq!(long, format!("{}\n{}\n", "A".repeat(76), "AAAA"));
+
+        assert_eq!(favicon_hash(b"hello"), murmur3_32(b"aGVsbG8=\n", 0) as i32);
+    }
+
+    #[test]
+    fn test_parse_title() {
+        assert_eq!(parse_title("<title>Example</title>").as_deref(), Some("Example"));
+        assert_eq!(parse_title("<Title lang=\"en\">A\n  B</Title>").as_deref(), Some("A B"));
+        assert_eq!(parse_title("<title> </title>"), None);
+        assert_eq!(parse_title("<html>no title</html>"), None);
+    }
+}
@@ -1,19 +1,43 @@
+use clap::ValueEnum;
 use colored::*;
 use futures::stream::{self, StreamExt};
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::ProgressBar;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
 use tokio::net::TcpStream;
+use crate::checkpoint::{CheckpointEntry, CheckpointError, ScanCheckpoint};
+use crate::console::quiet_progress_bar;
+use crate::probe::ProbeResult;
+use crate::sink::ResultSink;
+use crate::takeover::TakeoverFinding;
+use crate::utils::{to_ascii_domain, to_unicode_domain};
+use trust_dns_resolver::config::{LookupIpStrategy, NameServerConfigGroup, Protocol, ResolverConfig, ResolverOpts};
+use trust_dns_resolver::error::ResolveErrorKind;
+use trust_dns_resolver::proto::op::ResponseCode;
+use trust_dns_resolver::proto::rr::{RData, RecordType};
+use trust_dns_resolver::system_conf::read_system_conf;
+use trust_dns_resolver::TokioAsyncResolver;
 use std::time::Duration;
 
 #[derive(Debug)]
 pub enum ScanError {
     EmptyInput,
+    ConfigError(String),
+    /// A record lookup failed or timed out, see `Scanner::lookup_records`
+    LookupFailed(String),
 }
 
 impl std::fmt::Display for ScanError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ScanError::EmptyInput => write!(f, "No subdomains provided for scanning"),
+            ScanError::ConfigError(e) => write!(f, "Resolver configuration error: {}", e),
+            ScanError::LookupFailed(e) => write!(f, "Lookup failed: {}", e),
         }
     }
 }
@@ -23,127 +47,3197 @@ impl std::error::Error for ScanError {}
 #[derive(Debug, PartialEq)]
 enum ScanStatus {
     Valid,
-    Invalid,
+    Invalid(InvalidReason),
+    /// Not looked up at all, e.g. a `.onion` name with no Tor proxy configured
+    Skipped,
+    /// NXDOMAIN from the scan resolver, but the verification resolver disagreed
+    Discrepancy(String),
+    /// Resolved by the scan resolvers, but not by the trusted ones
+    Unverified(String),
+    /// Resolved only to the domain's wildcard addresses
+    Wildcard,
+    /// No addresses, but a CNAME to this takeover-prone target
+    Dangling(String),
+}
+
+/// Why a candidate was classified `Invalid`, shown at `-vvv`.
+#[derive(Debug, Clone, PartialEq)]
+enum InvalidReason {
+    /// The name does not exist
+    NxDomain,
+    /// The name exists but has no A/AAAA records
+    NoData,
+    /// The lookup answered but yielded no addresses
+    NoAddresses,
+    /// The lookup failed
+    LookupFailed(String),
+    /// The nameserver answered SERVFAIL, after all retries
+    ServFail,
+    /// The nameserver refused the query, after all retries
+    Refused,
+    /// No answer within the timeout, after all retries
+    TimedOut,
+    /// The hidden service did not answer on port 80 through the Tor proxy
+    Unreachable,
+}
+
+impl std::fmt::Display for InvalidReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidReason::NxDomain => write!(f, "NXDOMAIN"),
+            InvalidReason::NoData => write!(f, "NODATA"),
+            InvalidReason::NoAddresses => write!(f, "records but no IP"),
+            InvalidReason::LookupFailed(e) => write!(f, "lookup failed: {}", e),
+            InvalidReason::ServFail => write!(f, "SERVFAIL"),
+            InvalidReason::Refused => write!(f, "REFUSED"),
+            InvalidReason::TimedOut => write!(f, "timed out"),
+            InvalidReason::Unreachable => write!(f, "unreachable through Tor"),
+        }
+    }
+}
+
+impl ScanStatus {
+    /// Whether the name ends up in the results, which is when sinks are told about it.
+    fn is_finding(&self) -> bool {
+        matches!(self, ScanStatus::Valid | ScanStatus::Dangling(_))
+    }
+
+    /// Short name stored in scan checkpoints. `None` for outcomes that may differ on
+    /// another attempt (timeouts, resolver failures, verifier disagreements, skips),
+    /// which are left out so a resumed scan queries them again.
+    fn checkpoint_label(&self) -> Option<&'static str> {
+        match self {
+            ScanStatus::Valid => Some("valid"),
+            ScanStatus::Dangling(_) => Some("dangling"),
+            ScanStatus::Invalid(InvalidReason::NxDomain) => Some("nxdomain"),
+            ScanStatus::Invalid(InvalidReason::NoData) => Some("no_data"),
+            ScanStatus::Wildcard => Some("wildcard"),
+            _ => None,
+        }
+    }
+
+    /// What a checkpoint needs besides the label to restore this outcome.
+    fn checkpoint_detail(&self, result: &ScanResult) -> Option<String> {
+        match self {
+            ScanStatus::Valid | ScanStatus::Dangling(_) => serde_json::to_string(result).ok(),
+            _ => None,
+        }
+    }
+
+    /// Rebuild a checkpointed outcome. `None` for entries that cannot be restored,
+    /// which are then scanned again.
+    fn from_checkpoint(name: &str, entry: &CheckpointEntry) -> Option<(ScanStatus, ScanResult)> {
+        let status = match entry.status.as_str() {
+            "valid" | "dangling" => {
+                let result: ScanResult = serde_json::from_str(entry.detail.as_deref()?).ok()?;
+                let status = match result.cname.clone() {
+                    Some(target) if entry.status == "dangling" => ScanStatus::Dangling(target),
+                    _ if entry.status == "valid" => ScanStatus::Valid,
+                    _ => return None,
+                };
+                return (result.subdomain == name).then_some((status, result));
+            }
+            "nxdomain" => ScanStatus::Invalid(InvalidReason::NxDomain),
+            "no_data" => ScanStatus::Invalid(InvalidReason::NoData),
+            "wildcard" => ScanStatus::Wildcard,
+            _ => return None,
+        };
+        Some((status, ScanResult { subdomain: name.to_string(), ..Default::default() }))
+    }
+}
+
+/// A subdomain that passed the scan, together with the addresses it resolved to.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScanResult {
+    pub subdomain: String,
+    /// How the name passed the scan; invalid, skipped and wildcard names are never returned.
+    pub status: ResultStatus,
+    pub ips: Vec<IpAddr>,
+    /// Requested record types that returned records (`A`, `MX`, ...).
+    pub record_types: Vec<String>,
+    /// Ports found open by `check_ports`, empty unless a port check was run.
+    pub open_ports: Vec<u16>,
+    /// HTTP(S) answers recorded by `HttpProber::probe_all`, `None` unless `--probe` ran.
+    pub probe: Option<ProbeResult>,
+    /// Page capture written by `Screenshotter::capture_all`, `None` without `--screenshots`.
+    pub screenshot: Option<String>,
+    /// Set by `flag_load_balanced` when the name has more A/AAAA answers than the threshold.
+    pub load_balanced: bool,
+    /// CNAME target, only looked up when CNAME resolution is enabled.
+    pub cname: Option<String>,
+    /// Every CNAME hop followed from the subdomain, starting with `cname`.
+    pub cname_chain: Vec<String>,
+    /// Set when following the chain was cut short.
+    pub cname_issue: Option<CnameIssue>,
+    /// The name has no addresses but CNAMEs to a takeover-prone service.
+    pub dangling: bool,
+    /// Set by `TakeoverChecker::check_all` when the CNAME target looks unclaimed.
+    pub takeover: Option<TakeoverFinding>,
+    /// Lowest TTL (seconds) among the A/AAAA answer records.
+    pub ttl: Option<u32>,
+    /// How long the A/AAAA lookup took, in milliseconds.
+    pub latency_ms: Option<u64>,
+    /// Set by `flag_low_ttl` when `ttl` is below the `--min-ttl` threshold.
+    pub low_ttl: bool,
+    /// Set by `flag_private` when every address is in private or bogon space.
+    pub private: bool,
+    /// Where the candidate name came from.
+    pub source: Source,
+}
+
+/// Outcome of the scan for a returned `ScanResult`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResultStatus {
+    /// The name has records of one of the requested types
+    #[default]
+    Valid,
+    /// No addresses, but a CNAME to a takeover-prone service (`dangling` is set too)
+    Dangling,
+}
+
+impl std::fmt::Display for ResultStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResultStatus::Valid => write!(f, "valid"),
+            ResultStatus::Dangling => write!(f, "dangling"),
+        }
+    }
+}
+
+/// A name to scan, with the phase that proposed it. The name is kept in the ASCII
+/// form that is resolved; Unicode names are punycode-encoded by `Candidate::new`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub name: String,
+    pub source: Source,
+}
+
+impl Candidate {
+    pub fn new(name: impl Into<String>, source: Source) -> Self {
+        let name = name.into();
+        let name = to_ascii_domain(&name).unwrap_or(name);
+        Candidate { name, source }
+    }
+}
+
+impl From<String> for Candidate {
+    fn from(name: String) -> Self {
+        Candidate::new(name, Source::Wordlist)
+    }
+}
+
+/// The discovery phase that produced a candidate name.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    #[default]
+    Wordlist,
+    Wayback,
+    Crtsh,
+    Txt,
+    /// Mutations of subdomains found earlier in the scan (`--permute`)
+    Permutation,
+    /// Proposed by a model trained on subdomains found earlier in the scan (`--smart-gen`)
+    Generated,
+    /// Listed by a nameserver that allowed a zone transfer (`--try-axfr`)
+    Axfr,
+    /// PTR records of addresses found during the scan (`--rdns`)
+    Rdns,
+    /// Names in the HTTPS certificates seen while probing (`--probe`)
+    #[serde(rename = "tls-cert")]
+    TlsCert,
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Wordlist => write!(f, "wordlist"),
+            Source::Wayback => write!(f, "wayback"),
+            Source::Crtsh => write!(f, "crtsh"),
+            Source::Txt => write!(f, "txt"),
+            Source::Permutation => write!(f, "permutation"),
+            Source::Generated => write!(f, "generated"),
+            Source::Axfr => write!(f, "axfr"),
+            Source::Rdns => write!(f, "rdns"),
+            Source::TlsCert => write!(f, "tls-cert"),
+        }
+    }
+}
+
+/// Why a CNAME chain was not followed to its end.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CnameIssue {
+    /// A hop pointed back to a name already in the chain
+    CnameLoop,
+    /// The chain had more hops than the configured maximum depth
+    ChainTooLong,
+}
+
+impl std::fmt::Display for CnameIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CnameIssue::CnameLoop => write!(f, "cname loop"),
+            CnameIssue::ChainTooLong => write!(f, "chain too long"),
+        }
+    }
+}
+
+/// The CNAME hops found for one subdomain.
+#[derive(Debug, Default)]
+struct CnameChain {
+    hops: Vec<String>,
+    issue: Option<CnameIssue>,
+}
+
+/// Follow CNAMEs from `start` using `lookup` for each hop, stopping at loops and
+/// after `max_depth` hops.
+async fn follow_cname_chain<F, Fut>(start: &str, max_depth: usize, mut lookup: F) -> CnameChain
+where
+    F: FnMut(String) -> Fut,
+    Fut: std::future::Future<Output = Option<String>>,
+{
+    let mut chain = CnameChain::default();
+    let mut current = start.to_lowercase();
+    while let Some(next) = lookup(current).await {
+        if next == start.to_lowercase() || chain.hops.contains(&next) {
+            chain.issue = Some(CnameIssue::CnameLoop);
+            break;
+        }
+        if chain.hops.len() == max_depth {
+            chain.issue = Some(CnameIssue::ChainTooLong);
+            break;
+        }
+        chain.hops.push(next.clone());
+        current = next;
+    }
+    chain
+}
+
+impl ScanResult {
+    /// Return the first suffix the CNAME target falls under, if any.
+    pub fn cname_match<'a>(&self, suffixes: &'a [String]) -> Option<&'a str> {
+        let cname = self.cname.as_deref()?.to_lowercase();
+        suffixes
+            .iter()
+            .map(|suffix| suffix.as_str())
+            .find(|suffix| {
+                let suffix = suffix.trim_start_matches("*.").trim_start_matches('.').to_lowercase();
+                cname == suffix || cname.ends_with(&format!(".{}", suffix))
+            })
+    }
+}
+
+/// Counts from a finished scan.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ScanStats {
+    pub total: usize,
+    pub valid: usize,
+    pub invalid: usize,
+    pub skipped: usize,
+    pub discrepancies: usize,
+    /// Resolved, but rejected by the trusted resolvers of `--verify-trusted`
+    pub unverified: usize,
+    pub wildcards: usize,
+    /// CNAMEs to takeover-prone services that no longer resolve
+    pub dangling: usize,
+    /// Invalid because the lookup timed out (included in `invalid`)
+    pub timed_out: usize,
+    /// Invalid because the nameserver answered SERVFAIL (included in `invalid`)
+    pub servfail: usize,
+    /// Invalid because the nameserver refused the query (included in `invalid`)
+    pub refused: usize,
+    /// Invalid because the lookup failed for another reason (included in `invalid`)
+    pub errors: usize,
+    pub elapsed_ms: u64,
+}
+
+impl ScanStats {
+    /// Fold the stats of another scan (e.g. the next domain of a batch) into these.
+    pub fn merge(&mut self, other: &ScanStats) {
+        self.total += other.total;
+        self.valid += other.valid;
+        self.invalid += other.invalid;
+        self.skipped += other.skipped;
+        self.discrepancies += other.discrepancies;
+        self.unverified += other.unverified;
+        self.wildcards += other.wildcards;
+        self.dangling += other.dangling;
+        self.timed_out += other.timed_out;
+        self.servfail += other.servfail;
+        self.refused += other.refused;
+        self.errors += other.errors;
+        self.elapsed_ms += other.elapsed_ms;
+    }
+}
+
+/// Drop results whose addresses all belong to `apex_ips`, returning how many were dropped.
+/// Results without any address (e.g. `.onion` names) are kept.
+pub fn retain_distinct_from(results: &mut Vec<ScanResult>, apex_ips: &[IpAddr]) -> usize {
+    let before = results.len();
+    results.retain(|result| result.ips.is_empty() || result.ips.iter().any(|ip| !apex_ips.contains(ip)));
+    before - results.len()
+}
+
+/// Mark results whose TTL is below `min_ttl` seconds, returning how many were flagged.
+pub fn flag_low_ttl(results: &mut [ScanResult], min_ttl: u32) -> usize {
+    for result in results.iter_mut() {
+        result.low_ttl = result.ttl.is_some_and(|ttl| ttl < min_ttl);
+    }
+    results.iter().filter(|result| result.low_ttl).count()
+}
+
+/// Whether `ip` is outside the public address space: RFC 1918 and unique local
+/// ranges, loopback, link-local, CGNAT, documentation, benchmarking, multicast and
+/// reserved blocks. IPv4-mapped IPv6 addresses are judged by their IPv4 part.
+pub fn is_bogon(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, c, _] = v4.octets();
+            v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                || v4.is_multicast()
+                || a == 0
+                || a >= 240
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 192 && b == 0 && c == 0)
+                || (a == 198 && (b == 18 || b == 19))
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_bogon(&IpAddr::V4(v4));
+            }
+            let first = v6.segments()[0];
+            v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || (first == 0x2001 && v6.segments()[1] == 0x0db8)
+        }
+    }
+}
+
+/// Mark results whose addresses are all private or bogon (`is_bogon`), returning how
+/// many were flagged. Results without addresses are never flagged.
+pub fn flag_private(results: &mut [ScanResult]) -> usize {
+    for result in results.iter_mut() {
+        result.private = !result.ips.is_empty() && result.ips.iter().all(is_bogon);
+    }
+    results.iter().filter(|result| result.private).count()
+}
+
+/// Spread of the address lookup latencies of a set of results.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LatencySummary {
+    pub min_ms: u64,
+    pub avg_ms: u64,
+    pub p95_ms: u64,
+}
+
+impl std::fmt::Display for LatencySummary {
+    /// `min 4ms, avg 18ms, p95 61ms`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "min {}ms, avg {}ms, p95 {}ms", self.min_ms, self.avg_ms, self.p95_ms)
+    }
+}
+
+/// Minimum, mean and 95th percentile (nearest rank) of `latency_ms` across results;
+/// `None` when no result has one.
+pub fn latency_summary(results: &[ScanResult]) -> Option<LatencySummary> {
+    let mut latencies: Vec<u64> = results.iter().filter_map(|result| result.latency_ms).collect();
+    if latencies.is_empty() {
+        return None;
+    }
+    latencies.sort_unstable();
+    let rank = (latencies.len() * 95).div_ceil(100);
+    Some(LatencySummary {
+        min_ms: latencies[0],
+        avg_ms: latencies.iter().sum::<u64>() / latencies.len() as u64,
+        p95_ms: latencies[rank - 1],
+    })
+}
+
+/// Read takeover fingerprints, one suffix per line; blank lines and `#` comments are ignored.
+pub fn parse_fingerprints(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.to_lowercase())
+        .collect()
+}
+
+/// Every IPv4 address of the /24 networks holding `ips`, for `--rdns-expand`. IPv6
+/// addresses are kept as they are. Deduplicated and sorted.
+pub fn expand_to_slash24(ips: &[IpAddr]) -> Vec<IpAddr> {
+    let mut expanded: Vec<IpAddr> = ips
+        .iter()
+        .flat_map(|ip| match ip {
+            IpAddr::V4(v4) => {
+                let [a, b, c, _] = v4.octets();
+                (0..=255).map(|d| IpAddr::from([a, b, c, d])).collect::<Vec<_>>()
+            }
+            IpAddr::V6(_) => vec![*ip],
+        })
+        .collect();
+    expanded.sort();
+    expanded.dedup();
+    expanded
+}
+
+/// Tor hidden service names, which public DNS can never resolve.
+pub fn is_onion(subdomain: &str) -> bool {
+    let name = subdomain.trim_end_matches('.');
+    name.len() > ".onion".len() && name[name.len() - ".onion".len()..].eq_ignore_ascii_case(".onion")
+}
+
+/// Mark results resolving to more than `threshold` addresses as load-balanced.
+pub fn flag_load_balanced(results: &mut [ScanResult], threshold: usize) {
+    for result in results {
+        result.load_balanced = result.ips.len() > threshold;
+    }
+}
+
+/// A random wait between half of `delay` and all of it, so retries of lookups that
+/// failed together do not all hit the resolver at the same moment.
+fn jittered(delay: Duration) -> Duration {
+    if delay.is_zero() {
+        return delay;
+    }
+    rand::thread_rng().gen_range(delay / 2..=delay)
+}
+
+/// Spaces lookups out so that at most `per_second` start each second. Shared by
+/// every shard, so the limit holds for the whole scan.
+struct RateLimiter {
+    period: Duration,
+    next: tokio::sync::Mutex<tokio::time::Instant>,
+}
+
+impl RateLimiter {
+    fn new(per_second: u32) -> Self {
+        RateLimiter {
+            period: Duration::from_secs(1) / per_second.max(1),
+            next: tokio::sync::Mutex::new(tokio::time::Instant::now()),
+        }
+    }
+
+    /// Wait for the next free slot. The lock is held while sleeping, so waiters
+    /// are released one period apart.
+    async fn wait(&self) {
+        let mut next = self.next.lock().await;
+        tokio::time::sleep_until(*next).await;
+        *next = (*next).max(tokio::time::Instant::now()) + self.period;
+    }
+}
+
+/// Resolvers `--verify-trusted` confirms answers with unless others are given.
+pub const DEFAULT_TRUSTED_RESOLVERS: &str = "8.8.8.8,1.1.1.1";
+
+/// The 100 TCP ports most often found open, most common first, as ranked by nmap's
+/// service frequency table. `--top-ports N` checks the first `N`.
+pub const TOP_PORTS: [u16; 100] = [
+    80, 23, 443, 21, 22, 25, 3389, 110, 445, 139, 143, 53, 135, 3306, 8080, 1723, 111, 995, 993, 5900, 1025, 587,
+    8888, 199, 1720, 465, 548, 113, 81, 6001, 10000, 514, 5060, 179, 1026, 2000, 8443, 8000, 32768, 554, 26, 1433,
+    49152, 2001, 515, 8008, 49154, 1027, 5666, 646, 5000, 5631, 631, 49153, 8081, 2049, 88, 79, 5800, 106, 2121,
+    1110, 49155, 6000, 513, 990, 5357, 427, 49156, 543, 544, 5101, 144, 7, 389, 8009, 3128, 444, 9999, 5009, 7070,
+    5190, 3000, 5432, 1900, 3986, 13, 1029, 9, 5051, 6646, 49157, 1028, 873, 1755, 2717, 4899, 9100, 119, 37,
+];
+
+/// The `n` most common ports of `TOP_PORTS`.
+pub fn top_ports(n: usize) -> Vec<u16> {
+    TOP_PORTS[..n.min(TOP_PORTS.len())].to_vec()
+}
+
+/// Candidates scanned per round by `scan_candidates`; bounds how many are held at once.
+const SCAN_CHUNK: usize = 10_000;
+
+/// Count one outcome into `stats`, returning the result if it belongs in the findings.
+fn tally(stats: &mut ScanStats, result: ScanResult, status: ScanStatus) -> Option<ScanResult> {
+    stats.total += 1;
+    match status {
+        ScanStatus::Valid => {
+            stats.valid += 1;
+            Some(result)
+        }
+        ScanStatus::Invalid(reason) => {
+            stats.invalid += 1;
+            match reason {
+                InvalidReason::TimedOut => stats.timed_out += 1,
+                InvalidReason::LookupFailed(_) => stats.errors += 1,
+                InvalidReason::ServFail => stats.servfail += 1,
+                InvalidReason::Refused => stats.refused += 1,
+                _ => {}
+            }
+            None
+        }
+        ScanStatus::Skipped => {
+            stats.skipped += 1;
+            None
+        }
+        ScanStatus::Discrepancy(_) => {
+            stats.discrepancies += 1;
+            None
+        }
+        ScanStatus::Unverified(_) => {
+            stats.unverified += 1;
+            None
+        }
+        ScanStatus::Wildcard => {
+            stats.wildcards += 1;
+            None
+        }
+        ScanStatus::Dangling(_) => {
+            stats.dangling += 1;
+            Some(result)
+        }
+    }
+}
+
+/// Counters updated while a scan runs, readable at any time for a snapshot.
+struct LiveStats {
+    /// Grows chunk by chunk when the candidates are streamed.
+    total: AtomicUsize,
+    processed: AtomicUsize,
+    found: AtomicUsize,
+    started: Instant,
+}
+
+impl LiveStats {
+    fn new(total: usize) -> Self {
+        LiveStats {
+            total: AtomicUsize::new(total),
+            processed: AtomicUsize::new(0),
+            found: AtomicUsize::new(0),
+            started: Instant::now(),
+        }
+    }
+
+    fn snapshot(&self) -> String {
+        let processed = self.processed.load(Ordering::Relaxed);
+        let elapsed = self.started.elapsed();
+        let qps = processed as f64 / elapsed.as_secs_f64().max(f64::EPSILON);
+        format!(
+            "{} Processed {}/{}, {} found, {:.1} queries/s, {:.2?} elapsed",
+            "[stats]".bright_blue(),
+            processed,
+            self.total.load(Ordering::Relaxed),
+            self.found.load(Ordering::Relaxed),
+            qps,
+            elapsed
+        )
+    }
+}
+
+/// Print a stats snapshot whenever SIGQUIT (Ctrl-\) arrives, without stopping the scan.
+#[cfg(unix)]
+fn spawn_stats_listener(stats: Arc<LiveStats>, progress: ProgressBar) -> Option<tokio::task::JoinHandle<()>> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    if progress.is_hidden() {
+        return None;
+    }
+    let mut quit = signal(SignalKind::quit()).ok()?;
+    Some(tokio::spawn(async move {
+        while quit.recv().await.is_some() {
+            progress.println(stats.snapshot());
+        }
+    }))
+}
+
+#[cfg(not(unix))]
+fn spawn_stats_listener(_stats: Arc<LiveStats>, _progress: ProgressBar) -> Option<tokio::task::JoinHandle<()>> {
+    None
+}
+
+/// Parse one nameserver address: `IP`, `IP:PORT` or `[IPv6]:PORT`, port 53 by default.
+fn parse_resolver(value: &str) -> Option<SocketAddr> {
+    value
+        .parse::<SocketAddr>()
+        .ok()
+        .or_else(|| value.parse::<IpAddr>().ok().map(|ip| SocketAddr::new(ip, 53)))
+}
+
+/// Parse `--resolvers` entries. Each entry is a nameserver address (see `parse_resolver`)
+/// or the path of a file listing one per line (blank lines and `#` comments are ignored).
+pub fn parse_resolvers(entries: &[String]) -> Result<Vec<SocketAddr>, ScanError> {
+    let mut resolvers = Vec::new();
+    for entry in entries.iter().map(|entry| entry.trim()).filter(|entry| !entry.is_empty()) {
+        let path = std::path::Path::new(entry);
+        if parse_resolver(entry).is_none() && path.is_file() {
+            let content = std::fs::read_to_string(path)
+                .map_err(|e| ScanError::ConfigError(format!("cannot read resolvers file {}: {}", entry, e)))?;
+            for (number, line) in content.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let addr = parse_resolver(line).ok_or_else(|| {
+                    ScanError::ConfigError(format!("invalid resolver address '{}' ({}:{})", line, entry, number + 1))
+                })?;
+                resolvers.push(addr);
+            }
+        } else {
+            let addr = parse_resolver(entry)
+                .ok_or_else(|| ScanError::ConfigError(format!("invalid resolver address '{}'", entry)))?;
+            resolvers.push(addr);
+        }
+    }
+    Ok(resolvers)
+}
+
+/// Plain UDP/TCP nameserver configuration for each address.
+fn nameserver_group(addrs: &[SocketAddr]) -> NameServerConfigGroup {
+    let mut group = NameServerConfigGroup::new();
+    for addr in addrs {
+        group.merge(NameServerConfigGroup::from_ips_clear(&[addr.ip()], addr.port(), true));
+    }
+    group
+}
+
+/// A resolver that only queries the nameserver at `addr`, over TCP alone with `tcp_only`.
+fn single_server_resolver(addr: SocketAddr, opts: ResolverOpts, tcp_only: bool) -> TokioAsyncResolver {
+    servers_resolver(&[addr], opts, tcp_only)
+}
+
+/// A resolver that only queries the nameservers at `addrs`, over TCP alone with `tcp_only`.
+fn servers_resolver(addrs: &[SocketAddr], opts: ResolverOpts, tcp_only: bool) -> TokioAsyncResolver {
+    let config = ResolverConfig::from_parts(None, vec![], nameserver_group(addrs));
+    TokioAsyncResolver::tokio(if tcp_only { without_udp(&config) } else { config }, opts)
+}
+
+/// `config` with its UDP nameserver entries removed, leaving the TCP ones.
+fn without_udp(config: &ResolverConfig) -> ResolverConfig {
+    let tcp: Vec<_> = config.name_servers().iter().filter(|ns| ns.protocol == Protocol::Tcp).cloned().collect();
+    ResolverConfig::from_parts(config.domain().cloned(), config.search().to_vec(), NameServerConfigGroup::from(tcp))
+}
+
+/// Classify a candidate the scan resolver called NXDOMAIN, given what the
+/// verification resolver made of it. Only agreement keeps it `Invalid`.
+fn verify_nxdomain(verifier: IpAddr, verdict: Result<Vec<IpAddr>, InvalidReason>) -> ScanStatus {
+    match verdict {
+        Err(InvalidReason::NxDomain) => ScanStatus::Invalid(InvalidReason::NxDomain),
+        Ok(ips) if !ips.is_empty() => ScanStatus::Discrepancy(format!(
+            "{} resolves it to {}",
+            verifier,
+            ips.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+        )),
+        Ok(_) => ScanStatus::Discrepancy(format!("{} answered without NXDOMAIN", verifier)),
+        Err(reason) => ScanStatus::Discrepancy(format!("{}: {}", verifier, reason)),
+    }
+}
+
+/// Names whose address lookup `Scanner` remembers for the rest of the run; further
+/// names are looked up without caching, so memory stays bounded on streamed wordlists.
+pub const DNS_CACHE_CAPACITY: usize = 1_000_000;
+
+/// Addresses found by an address lookup.
+#[derive(Debug, Clone)]
+struct AddrLookup {
+    ips: Vec<IpAddr>,
+    /// Lowest TTL among the answer records
+    ttl: Option<u32>,
+    /// How long the answered query took; rate-limit waits and failed attempts excluded
+    latency: Duration,
+}
+
+/// Outcome of an address lookup, or why there are no addresses.
+type AddrAnswer = Result<AddrLookup, InvalidReason>;
+
+/// Name every healthy resolver can resolve, and under which a random label must be
+/// NXDOMAIN, for `Scanner::check_resolvers`.
+pub const HEALTH_CHECK_DOMAIN: &str = "example.com";
+
+/// Lookups per nameserver between two error-rate checks during a scan.
+const HEALTH_WINDOW: usize = 20;
+
+/// Share of failed lookups in a window (timeouts, SERVFAIL, REFUSED, errors) that
+/// gets a nameserver blacklisted.
+const HEALTH_MAX_FAILURE_PERCENT: usize = 50;
+
+/// How long a blacklisted nameserver is skipped before it gets lookups again.
+const BLACKLIST_DURATION: Duration = Duration::from_secs(30);
+
+/// Outcome of the startup check of one nameserver.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResolverHealth {
+    Healthy,
+    /// Failed to resolve the known-good name
+    Dead(String),
+    /// Returned addresses for a name that does not exist
+    Poisoned(Vec<IpAddr>),
+}
+
+impl std::fmt::Display for ResolverHealth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResolverHealth::Healthy => write!(f, "healthy"),
+            ResolverHealth::Dead(reason) => write!(f, "dead ({})", reason),
+            ResolverHealth::Poisoned(ips) => write!(
+                f,
+                "answers nonexistent names with {}",
+                ips.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+            ),
+        }
+    }
+}
+
+/// Lookups and failures of one nameserver in the current window.
+#[derive(Debug, Default)]
+struct HealthWindow {
+    lookups: usize,
+    failures: usize,
+    blacklisted_until: Option<Instant>,
+}
+
+/// Default number of random labels resolved under the target to detect wildcard DNS.
+pub const WILDCARD_PROBES: usize = 3;
+
+/// A label that should not exist under any real zone.
+fn random_label() -> String {
+    const CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+    let mut rng = rand::thread_rng();
+    (0..20).map(|_| CHARSET[rng.gen_range(0..CHARSET.len())] as char).collect()
+}
+
+/// Whether every address in `ips` came from the wildcard set. Wildcards that rotate
+/// through a pool answer each name with a different slice of it, so a subset counts.
+fn matches_wildcard(ips: &[IpAddr], wildcard: &[IpAddr]) -> bool {
+    !ips.is_empty() && ips.iter().all(|ip| wildcard.contains(ip))
+}
+
+/// Deal candidates round-robin into `count` disjoint shards.
+fn shard<T: Clone>(candidates: &[T], count: usize) -> Vec<Vec<T>> {
+    let mut shards = vec![Vec::new(); count];
+    for (index, candidate) in candidates.iter().enumerate() {
+        shards[index % count].push(candidate.clone());
+    }
+    shards
+}
+
+/// Named bundles of scan settings, selected with `--intensity`.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Intensity {
+    /// Few concurrent lookups, long timeouts, a random delay before each lookup and a
+    /// capped query rate
+    Stealthy,
+    /// The default settings
+    Balanced,
+    /// Many concurrent lookups and short timeouts
+    Aggressive,
+}
+
+/// Concrete settings behind an `Intensity`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScanProfile {
+    pub concurrency: usize,
+    /// Timeout for each DNS lookup and connection attempt
+    pub timeout: Duration,
+    /// Upper bound of the random delay added before each lookup
+    pub jitter: Duration,
+    /// Retries after a failed or timed-out DNS query
+    pub retries: usize,
+    /// Lookups started per second, when capped
+    pub rate: Option<u32>,
+}
+
+impl Intensity {
+    pub fn profile(&self) -> ScanProfile {
+        match self {
+            Intensity::Stealthy => ScanProfile {
+                concurrency: 10,
+                timeout: Duration::from_secs(8),
+                jitter: Duration::from_millis(500),
+                retries: 3,
+                rate: Some(10),
+            },
+            Intensity::Balanced => ScanProfile {
+                concurrency: 50,
+                timeout: Duration::from_secs(5),
+                jitter: Duration::ZERO,
+                retries: 2,
+                rate: None,
+            },
+            Intensity::Aggressive => ScanProfile {
+                concurrency: 200,
+                timeout: Duration::from_secs(3),
+                jitter: Duration::ZERO,
+                retries: 1,
+                rate: None,
+            },
+        }
+    }
 }
 
 pub struct Scanner {
     concurrency: usize,
+    timeout: Duration,
+    jitter: Duration,
+    resolver: TokioAsyncResolver,
+    config: ResolverConfig,
+    opts: ResolverOpts,
+    /// One resolver per configured nameserver, used when sharding by resolver or
+    /// rotating through nameservers.
+    shard_resolvers: Vec<TokioAsyncResolver>,
+    /// Address of each of `shard_resolvers`.
+    shard_addrs: Vec<SocketAddr>,
+    shard_by_resolver: bool,
+    round_robin: bool,
+    /// Skip UDP and send every query over TCP (`--tcp-only`).
+    tcp_only: bool,
+    /// Skip nameservers whose failure rate climbs mid-scan, see `with_resolver_blacklist`.
+    blacklisting: bool,
+    /// Failure counts of each of `shard_resolvers`, when blacklisting.
+    health_windows: Vec<Mutex<HealthWindow>>,
+    /// Record types that make a candidate valid when any of them has records.
+    record_types: Vec<RecordType>,
+    resolve_cname: bool,
+    wildcard_filter: bool,
+    wildcard_probes: usize,
+    max_cname_depth: usize,
+    verbosity: u8,
+    /// SOCKS5 proxy used to probe `.onion` names; without one they are skipped.
+    tor_proxy: Option<SocketAddr>,
+    /// CNAME suffixes checked for names without addresses; empty disables the check.
+    takeover_fingerprints: Vec<String>,
+    /// Caps how many DNS lookups start per second, when `--rate` is given.
+    rate_limiter: Option<RateLimiter>,
+    /// Retries after a timed-out or failed address lookup.
+    retries: usize,
+    /// First backoff before a retry, doubled for each further one and jittered. When
+    /// zero the resolver retries immediately on its own instead.
+    retry_delay: Duration,
+    /// Second resolver asked to confirm every NXDOMAIN.
+    nxdomain_verifier: Option<(IpAddr, TokioAsyncResolver)>,
+    /// Resolvers every valid answer is confirmed with (`--verify-trusted`)
+    trusted_verifier: Option<(Vec<SocketAddr>, TokioAsyncResolver)>,
+    /// Receive every valid or dangling result from the scan loop, when streaming.
+    sinks: Mutex<Vec<Box<dyn ResultSink>>>,
+    /// Records every processed candidate, when `--checkpoint` is given.
+    checkpoint: Mutex<Option<ScanCheckpoint>>,
+    /// Address answers by lowercased name, so a name proposed by several sources or
+    /// passes is resolved once. Concurrent lookups of one name share a single query.
+    answer_cache: Mutex<HashMap<String, Arc<tokio::sync::OnceCell<AddrAnswer>>>>,
+    /// Wildcard baselines of zones below the scanned domain, by name.
+    subzone_wildcards: Mutex<HashMap<String, Arc<tokio::sync::OnceCell<Vec<IpAddr>>>>>,
+    /// Turn counter of `next_resolver`.
+    rotation: AtomicUsize,
+    /// Keep progress bars and log lines off the terminal; errors still go to stderr.
+    quiet: bool,
 }
 
 impl Scanner {
     pub async fn new(concurrency: usize) -> Result<Self, ScanError> {
-        Ok(Scanner {
+        let (config, opts) = read_system_conf().map_err(|e| ScanError::ConfigError(e.to_string()))?;
+        Ok(Self::from_config(concurrency, config, opts))
+    }
+
+    /// Resolve through these nameservers instead of the system configuration. Queries
+    /// are spread over them by trust-dns, which prefers the fastest-answering servers.
+    pub async fn with_resolvers(resolvers: &[SocketAddr], concurrency: usize) -> Result<Self, ScanError> {
+        if resolvers.is_empty() {
+            return Err(ScanError::ConfigError("no resolvers given".to_string()));
+        }
+        let config = ResolverConfig::from_parts(None, vec![], nameserver_group(resolvers));
+        Ok(Self::from_config(concurrency, config, ResolverOpts::default()))
+    }
+
+    fn from_config(concurrency: usize, config: ResolverConfig, mut opts: ResolverOpts) -> Self {
+        // Query A and AAAA together, so dual-stack names report both families
+        opts.ip_strategy = LookupIpStrategy::Ipv4AndIpv6;
+        let mut scanner = Scanner {
             concurrency,
-        })
+            timeout: opts.timeout,
+            jitter: Duration::ZERO,
+            resolver: TokioAsyncResolver::tokio(config.clone(), opts),
+            config,
+            opts,
+            shard_resolvers: Vec::new(),
+            shard_addrs: Vec::new(),
+            shard_by_resolver: false,
+            round_robin: false,
+            tcp_only: false,
+            blacklisting: false,
+            health_windows: Vec::new(),
+            record_types: vec![RecordType::A, RecordType::AAAA],
+            resolve_cname: false,
+            wildcard_filter: true,
+            wildcard_probes: WILDCARD_PROBES,
+            max_cname_depth: 10,
+            verbosity: 0,
+            tor_proxy: None,
+            takeover_fingerprints: Vec::new(),
+            rate_limiter: None,
+            retries: opts.attempts,
+            retry_delay: Duration::ZERO,
+            nxdomain_verifier: None,
+            trusted_verifier: None,
+            sinks: Mutex::new(Vec::new()),
+            checkpoint: Mutex::new(None),
+            answer_cache: Mutex::new(HashMap::new()),
+            subzone_wildcards: Mutex::new(HashMap::new()),
+            rotation: AtomicUsize::new(0),
+            quiet: true,
+        };
+        scanner.rebuild_resolvers();
+        scanner
     }
 
-    pub async fn scan_domains(&self, subdomains: Vec<String>) -> Result<Vec<String>, ScanError> {
-        if subdomains.is_empty() {
-            println!("{} {}", "[!]".yellow(), "No subdomains to scan");
-            return Err(ScanError::EmptyInput);
+    /// Recreate every resolver from `config` and `opts`, after the options changed.
+    fn rebuild_resolvers(&mut self) {
+        let mut nameservers: Vec<SocketAddr> = Vec::new();
+        for nameserver in self.config.name_servers() {
+            if !nameservers.contains(&nameserver.socket_addr) {
+                nameservers.push(nameserver.socket_addr);
+            }
+        }
+        let config = if self.tcp_only { without_udp(&self.config) } else { self.config.clone() };
+        self.resolver = TokioAsyncResolver::tokio(config, self.opts);
+        self.shard_resolvers = nameservers
+            .iter()
+            .map(|addr| single_server_resolver(*addr, self.opts, self.tcp_only))
+            .collect();
+        self.health_windows = nameservers.iter().map(|_| Mutex::new(HealthWindow::default())).collect();
+        self.shard_addrs = nameservers;
+        if let Some((ip, _)) = self.nxdomain_verifier.take() {
+            self.nxdomain_verifier = Some((ip, single_server_resolver(SocketAddr::new(ip, 53), self.opts, self.tcp_only)));
         }
+        if let Some((addrs, _)) = self.trusted_verifier.take() {
+            let resolver = servers_resolver(&addrs, self.opts, self.tcp_only);
+            self.trusted_verifier = Some((addrs, resolver));
+        }
+    }
 
-        let start_time = Instant::now();
-        let total_domains = subdomains.len();
+    /// Per-query timeout, and the maximum random delay inserted before each lookup.
+    pub fn with_timing(mut self, timeout: Duration, jitter: Duration) -> Self {
+        self.timeout = timeout;
+        self.jitter = jitter;
+        self.opts.timeout = timeout;
+        self.rebuild_resolvers();
+        self
+    }
 
-        println!("{}", "[*] Initializing scan...".blue());
-        println!("{} {}", "[*]".blue(), format!("Found {} subdomains to scan", total_domains));
-        println!("{} {}", "[*]".blue(), format!("Using {} concurrent connections", self.concurrency));
+    /// Retry failed or timed-out DNS queries this many times before giving up.
+    pub fn with_retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self.apply_retry_policy();
+        self
+    }
 
-        let progress = self.create_progress_bar(total_domains as u64);
-        let results = self.check_subdomains(&subdomains, &progress).await;
-        progress.finish_with_message("scan completed");
+    /// Wait this long before the first retry and twice as long before each next one.
+    /// NXDOMAIN and NODATA answers are never retried.
+    pub fn with_retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self.apply_retry_policy();
+        self
+    }
 
-        let mut valid_count = 0;
-        let mut invalid_count = 0;
+    /// With a backoff or a rate limit the scanner retries itself, so the resolver must not as well.
+    fn apply_retry_policy(&mut self) {
+        self.opts.attempts = if self.scanner_retries() { 0 } else { self.retries };
+        self.rebuild_resolvers();
+    }
 
-        let valid_subdomains: Vec<String> = results
-            .into_iter()
-            .filter_map(|(subdomain, status)| {
-                match status {
-                    ScanStatus::Valid => {
-                        valid_count += 1;
-                        Some(subdomain)
-                    }
-                    ScanStatus::Invalid => {
-                        invalid_count += 1;
-                        None
-                    }
-                }
-            })
-            .collect();
+    /// Whether retries go through `lookup_addrs`, which can back off and take a rate
+    /// limiter slot before each one, rather than through the resolver.
+    fn scanner_retries(&self) -> bool {
+        !self.retry_delay.is_zero() || self.rate_limiter.is_some()
+    }
 
-        println!("\n{}", "Scan Summary:".bright_blue().bold());
-        println!("{} {:.2?}", "Time elapsed:".blue(), start_time.elapsed());
-        println!("{} {}", "Valid subdomains:".green(), valid_count);
-        println!("{} {}", "Invalid subdomains:".yellow(), invalid_count);
-        println!("{} {}", "Total processed:".blue(), valid_count + invalid_count);
+    /// Upper bound for a whole lookup: one timeout per attempt.
+    fn lookup_deadline(&self) -> Duration {
+        self.timeout * (self.opts.attempts as u32 + 1)
+    }
 
-        Ok(valid_subdomains)
+    /// Count a candidate as valid when any of these record types has records.
+    /// A and AAAA go through the address lookup as before; the other types are
+    /// queried for every name that exists, so each result lists all the types found.
+    pub fn with_record_types(mut self, types: Vec<RecordType>) -> Self {
+        let mut unique = Vec::new();
+        for record_type in types {
+            if !unique.contains(&record_type) {
+                unique.push(record_type);
+            }
+        }
+        if !unique.is_empty() {
+            self.record_types = unique;
+        }
+        let (v4, v6) = (self.record_types.contains(&RecordType::A), self.record_types.contains(&RecordType::AAAA));
+        self.opts.ip_strategy = match (v4, v6) {
+            (true, false) => LookupIpStrategy::Ipv4Only,
+            (false, true) => LookupIpStrategy::Ipv6Only,
+            _ => LookupIpStrategy::Ipv4AndIpv6,
+        };
+        self.rebuild_resolvers();
+        self
     }
 
-    async fn check_subdomains(&self, subdomains: &[String], progress: &ProgressBar) -> Vec<(String, ScanStatus)> {
-        stream::iter(subdomains.to_vec())
-            .map(|subdomain| {
-                let progress = progress.clone();
-                async move {
-                    let endpoint = format!("{}:80", subdomain);
-                    let status = match tokio::time::timeout(
-                        Duration::from_secs(5),
-                        TcpStream::connect(&endpoint)
-                    ).await {
-                        Ok(Ok(_)) => ScanStatus::Valid,
-                        Ok(Err(e)) => match e.kind() {
-                            std::io::ErrorKind::ConnectionRefused => ScanStatus::Valid, // Host exists but port is closed
-                            _ => ScanStatus::Invalid,
-                        },
-                        Err(_) => ScanStatus::Invalid,
-                    };
-                    
-                    progress.inc(1);
-                    match &status {
-                        ScanStatus::Valid => progress.println(format!("{} {}", "✓".green(), subdomain.green())),
-                        ScanStatus::Invalid => progress.println(format!("{} {}", "✗".yellow(), subdomain.yellow())),
-                    }
-                    (subdomain, status)
-                }
-            })
-            .buffered(self.concurrency)
-            .collect::<Vec<_>>()
-            .await
+    /// Probe random labels under the target before scanning and classify names that
+    /// only resolve to the wildcard addresses as `Wildcard` instead of valid.
+    pub fn with_wildcard_filter(mut self, enabled: bool) -> Self {
+        self.wildcard_filter = enabled;
+        self
     }
 
-    fn create_progress_bar(&self, total: u64) -> ProgressBar {
-        let progress = ProgressBar::new(total);
-        progress.set_style(
-            ProgressStyle::default_bar()
-                .template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} ({eta}) {msg}")
-                .unwrap()
-                .progress_chars("#>-"),
-        );
-        progress.set_message("Scanning subdomains...");
-        progress
+    /// Number of random labels resolved to build the wildcard address set.
+    pub fn with_wildcard_probes(mut self, probes: usize) -> Self {
+        self.wildcard_probes = probes;
+        self
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Also look up the CNAME target of every subdomain that passes the scan.
+    pub fn with_cname_lookup(mut self, enabled: bool) -> Self {
+        self.resolve_cname = enabled;
+        self
+    }
 
-    #[tokio::test]
-    async fn test_scanner() {
-        let scanner = Scanner::new(10).await.expect("Failed to create scanner");
-        
-        // Test empty subdomains case
-        let empty_result = scanner.scan_domains(vec![]).await;
-        assert!(matches!(empty_result, Err(ScanError::EmptyInput)));
+    /// Stop following a CNAME chain after this many hops.
+    pub fn with_max_cname_depth(mut self, depth: usize) -> Self {
+        self.max_cname_depth = depth;
+        self
+    }
 
-        // Test with some domains
-        let test_subdomains = vec![
-            "www.example.com".to_string(),
-            "mail.example.com".to_string(),
-            "test.example.com".to_string()
-        ];
-        assert_eq!(test_subdomains.len(), 3);
+    /// Keep progress bars, log lines and the scan summary off the terminal. Quiet by
+    /// default, so library users only get the returned results; errors go to stderr
+    /// either way.
+    pub fn with_quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
     }
-}
\ No newline at end of file
+
+    /// At level 3 and above, print why each candidate was classified invalid.
+    pub fn with_verbosity(mut self, verbosity: u8) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// Split candidates into disjoint shards, one per nameserver, each scanned
+    /// concurrently with its own `concurrency`-wide pipeline.
+    pub fn with_resolver_shards(mut self, enabled: bool) -> Self {
+        self.shard_by_resolver = enabled;
+        self
+    }
+
+    /// Send each candidate's lookups to the next nameserver in turn, instead of letting
+    /// the resolver favour the fastest one. All nameservers share one pipeline.
+    pub fn with_round_robin(mut self, enabled: bool) -> Self {
+        self.round_robin = enabled;
+        self
+    }
+
+    /// Track the failure rate of each nameserver during scans and skip one for
+    /// `BLACKLIST_DURATION` when more than half of a window of lookups failed. Lookups
+    /// then go to each nameserver in turn, as with `with_round_robin`.
+    pub fn with_resolver_blacklist(mut self, enabled: bool) -> Self {
+        self.blacklisting = enabled;
+        self
+    }
+
+    /// Resolve `known_good` through each nameserver, then a random name under it that
+    /// must be NXDOMAIN. Nameservers that fail the first query are dead, those that
+    /// answer the second with addresses are poisoned; both are dropped from the scanner.
+    /// Fails when no nameserver passes.
+    pub async fn check_resolvers(&mut self, known_good: &str) -> Result<Vec<(SocketAddr, ResolverHealth)>, ScanError> {
+        let bogus = format!("{}.{}", random_label(), known_good);
+        let scanner = &*self;
+        let checks = scanner.shard_addrs.iter().zip(&scanner.shard_resolvers).map(|(addr, resolver)| {
+            let bogus = &bogus;
+            async move {
+                let health = match scanner.lookup_addrs_once(resolver, known_good).await {
+                    Ok(lookup) if !lookup.ips.is_empty() => match scanner.lookup_addrs_once(resolver, bogus).await {
+                        Ok(lookup) if !lookup.ips.is_empty() => ResolverHealth::Poisoned(lookup.ips),
+                        Ok(_) | Err(InvalidReason::NxDomain | InvalidReason::NoData) => ResolverHealth::Healthy,
+                        Err(reason) => ResolverHealth::Dead(reason.to_string()),
+                    },
+                    Ok(_) => ResolverHealth::Dead(format!("no addresses for {}", known_good)),
+                    Err(reason) => ResolverHealth::Dead(reason.to_string()),
+                };
+                (*addr, health)
+            }
+        });
+        let report = futures::future::join_all(checks).await;
+
+        let healthy: Vec<SocketAddr> = report
+            .iter()
+            .filter(|(_, health)| *health == ResolverHealth::Healthy)
+            .map(|(addr, _)| *addr)
+            .collect();
+        if healthy.is_empty() {
+            return Err(ScanError::ConfigError("no resolver passed the health check".to_string()));
+        }
+        if healthy.len() < report.len() {
+            self.config = ResolverConfig::from_parts(
+                self.config.domain().cloned(),
+                self.config.search().to_vec(),
+                nameserver_group(&healthy),
+            );
+            self.rebuild_resolvers();
+        }
+        Ok(report)
+    }
+
+    /// Query nameservers over TCP only. By default queries go over UDP, and only
+    /// truncated answers and unreachable servers are retried over TCP.
+    pub fn with_tcp_only(mut self, enabled: bool) -> Self {
+        self.tcp_only = enabled;
+        self.rebuild_resolvers();
+        self
+    }
+
+    /// Probe `.onion` candidates through this Tor SOCKS5 proxy instead of skipping them.
+    pub fn with_tor_proxy(mut self, proxy: Option<SocketAddr>) -> Self {
+        self.tor_proxy = proxy;
+        self
+    }
+
+    /// Look up the CNAME of names that have no addresses and classify them as
+    /// dangling when it points under one of `fingerprints`.
+    pub fn with_takeover_check(mut self, fingerprints: Vec<String>) -> Self {
+        self.takeover_fingerprints = fingerprints;
+        self
+    }
+
+    /// Start at most `per_second` DNS lookups per second, on top of the concurrency
+    /// cap. Every lookup takes a slot: retries, extra record types, CNAME hops and
+    /// wildcard probes alike. `None` leaves lookups unthrottled.
+    pub fn with_rate(mut self, per_second: Option<u32>) -> Self {
+        self.rate_limiter = per_second.map(RateLimiter::new);
+        self.apply_retry_policy();
+        self
+    }
+
+    /// Re-query every NXDOMAIN against the nameserver at `resolver` before trusting it.
+    pub fn with_nxdomain_verifier(mut self, resolver: Option<IpAddr>) -> Self {
+        self.nxdomain_verifier =
+            resolver.map(|ip| (ip, single_server_resolver(SocketAddr::new(ip, 53), self.opts, self.tcp_only)));
+        self
+    }
+
+    /// Re-query every name that resolved through these nameservers (port 53 unless
+    /// given) before reporting it, like a second puredns pass: names they do not resolve
+    /// are dropped as `unverified`, and the addresses they return replace the first
+    /// answer's. Names found only through other record types are re-queried for those.
+    /// An empty list turns verification off.
+    pub fn with_trusted_verifier(mut self, resolvers: &[SocketAddr]) -> Self {
+        self.trusted_verifier = (!resolvers.is_empty())
+            .then(|| (resolvers.to_vec(), servers_resolver(resolvers, self.opts, self.tcp_only)));
+        self
+    }
+
+    /// Skip candidates already recorded in `checkpoint` and record every new one,
+    /// so an interrupted scan can be resumed.
+    pub fn with_checkpoint(self, checkpoint: Option<ScanCheckpoint>) -> Self {
+        *self.checkpoint.lock().unwrap() = checkpoint;
+        self
+    }
+
+    /// Remove the checkpoint file once every target has been scanned.
+    pub fn finish_checkpoint(&self) -> Result<(), CheckpointError> {
+        match self.checkpoint.lock().unwrap().take() {
+            Some(checkpoint) => checkpoint.finish(),
+            None => Ok(()),
+        }
+    }
+
+    /// Hand every valid or dangling result to `sinks` as soon as it is found.
+    pub fn with_sinks(self, sinks: Vec<Box<dyn ResultSink>>) -> Self {
+        *self.sinks.lock().unwrap() = sinks;
+        self
+    }
+
+    fn emit_to_sinks(&self, result: &ScanResult, progress: &ProgressBar) {
+        for sink in self.sinks.lock().unwrap().iter_mut() {
+            if let Err(e) = sink.emit(result) {
+                progress.suspend(|| eprintln!("{} {}", "[!]".red(), e));
+            }
+        }
+    }
+
+    /// Send the final version of results already emitted, once their target has been
+    /// filtered and enriched.
+    pub fn update_sinks(&self, results: &[ScanResult]) {
+        for sink in self.sinks.lock().unwrap().iter_mut() {
+            for result in results {
+                if let Err(e) = sink.update(result) {
+                    eprintln!("{} {}", "[!]".red(), e);
+                }
+            }
+        }
+    }
+
+    /// Hand the final stats to every sink and wait for them to flush. Call once, after
+    /// the last target.
+    pub async fn finish_sinks(&self, stats: &ScanStats) {
+        let sinks = std::mem::take(&mut *self.sinks.lock().unwrap());
+        for mut sink in sinks {
+            if let Err(e) = sink.finish(stats).await {
+                eprintln!("{} {}", "[!]".red(), e);
+            }
+        }
+    }
+
+    /// Split off the candidates whose outcome is already in the checkpoint. Entries
+    /// for names that are no longer candidates (e.g. the wordlist changed) are ignored.
+    fn restore_checkpoint(&self, candidates: Vec<Candidate>) -> (Vec<(ScanResult, ScanStatus)>, Vec<Candidate>) {
+        let guard = self.checkpoint.lock().unwrap();
+        let Some(checkpoint) = guard.as_ref() else {
+            return (Vec::new(), candidates);
+        };
+
+        let mut restored = Vec::new();
+        let mut remaining = Vec::new();
+        for candidate in candidates {
+            let outcome = checkpoint
+                .entries()
+                .get(&candidate.name)
+                .and_then(|entry| ScanStatus::from_checkpoint(&candidate.name, entry));
+            match outcome {
+                Some((status, result)) => restored.push((result, status)),
+                None => remaining.push(candidate),
+            }
+        }
+        (restored, remaining)
+    }
+
+    /// How many lookups run at once, for phases that issue their own queries.
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    /// Records of `record_type` at `name`, queried like the scan's own lookups: through
+    /// the configured resolvers in turn, under the rate limit and lookup timeout. Empty
+    /// when the name has none.
+    pub async fn lookup_records(&self, name: &str, record_type: RecordType) -> Result<Vec<RData>, ScanError> {
+        self.throttle().await;
+        match tokio::time::timeout(self.timeout, self.next_resolver().lookup(name, record_type)).await {
+            Ok(Ok(lookup)) => Ok(lookup
+                .record_iter()
+                .filter(|record| record.record_type() == record_type)
+                .filter_map(|record| record.data().cloned())
+                .collect()),
+            Ok(Err(e)) => match e.kind() {
+                ResolveErrorKind::NoRecordsFound { .. } => Ok(Vec::new()),
+                _ => Err(ScanError::LookupFailed(e.to_string())),
+            },
+            Err(_) => Err(ScanError::LookupFailed(format!("{} {} timed out", name, record_type))),
+        }
+    }
+
+    /// DNS record types queried for each candidate: the `--record-types`, plus CNAME
+    /// for valid names when CNAME lookup is enabled.
+    pub fn query_types(&self) -> Vec<RecordType> {
+        let mut types = self.record_types.clone();
+        if self.resolve_cname && !types.contains(&RecordType::CNAME) {
+            types.push(RecordType::CNAME);
+        }
+        types
+    }
+
+    /// Wildcard baseline of the zone directly above `subdomain`, when that is below
+    /// `domain` rather than `domain` itself. A wildcard often exists only on one subzone
+    /// (`*.dev.example.com`), so names found there by wordlists, passive sources or
+    /// `--label-depth` need its baseline. Each parent is probed once per run, the first
+    /// time a valid name under it turns up. `None` with the wildcard filter off.
+    async fn subzone_wildcard(&self, subdomain: &str, domain: &str, progress: &ProgressBar) -> Option<Vec<IpAddr>> {
+        if !self.wildcard_filter {
+            return None;
+        }
+        let parent = subdomain.split_once('.')?.1.trim_end_matches('.').to_lowercase();
+        let domain = domain.trim_end_matches('.').to_lowercase();
+        if parent == domain || !parent.ends_with(&format!(".{}", domain)) {
+            return None;
+        }
+
+        let cell = self.subzone_wildcards.lock().unwrap().entry(parent.clone()).or_default().clone();
+        let baseline = cell
+            .get_or_init(|| async {
+                let baseline = self.detect_wildcard(&parent).await;
+                if !baseline.is_empty() {
+                    let ips: Vec<String> = baseline.iter().map(ToString::to_string).collect();
+                    progress.println(format!(
+                        "{} Wildcard DNS detected: *.{} -> {} (matching names are filtered)",
+                        "[!]".yellow(),
+                        parent,
+                        ips.join(", ")
+                    ));
+                }
+                baseline
+            })
+            .await;
+        Some(baseline.clone())
+    }
+
+    /// Resolve random labels under `domain` and return the union of the addresses they
+    /// resolve to. Empty when the domain has no wildcard record.
+    pub async fn detect_wildcard(&self, domain: &str) -> Vec<IpAddr> {
+        let mut wildcard = Vec::new();
+        for _ in 0..self.wildcard_probes {
+            let probe = format!("{}.{}", random_label(), domain);
+            if let Ok(lookup) = self.lookup_addrs(self.next_resolver(), &probe).await {
+                for ip in lookup.ips {
+                    if !wildcard.contains(&ip) {
+                        wildcard.push(ip);
+                    }
+                }
+            }
+        }
+        wildcard
+    }
+
+    /// Scan candidates under `domain`, which is probed for wildcard DNS first.
+    pub async fn scan_domains(
+        &self,
+        domain: &str,
+        subdomains: Vec<impl Into<Candidate>>,
+    ) -> Result<(Vec<ScanResult>, ScanStats), ScanError> {
+        let total = subdomains.len();
+        self.scan_candidates(domain, subdomains.into_iter().map(Into::into), Some(total)).await
+    }
+
+    /// Scan candidates pulled from an iterator `SCAN_CHUNK` at a time, so a streamed
+    /// wordlist never has to be in memory at once. `total`, when known, sizes the
+    /// progress bar; otherwise it grows with every chunk.
+    pub async fn scan_candidates(
+        &self,
+        domain: &str,
+        candidates: impl Iterator<Item = Candidate>,
+        total: Option<usize>,
+    ) -> Result<(Vec<ScanResult>, ScanStats), ScanError> {
+        let mut candidates = candidates.peekable();
+        if candidates.peek().is_none() {
+            say!(quiet = self.quiet; "{} No subdomains to scan", "[!]".yellow());
+            return Err(ScanError::EmptyInput);
+        }
+
+        let wildcard = if self.wildcard_filter { self.detect_wildcard(domain).await } else { Vec::new() };
+        if !wildcard.is_empty() {
+            let ips: Vec<String> = wildcard.iter().map(ToString::to_string).collect();
+            say!(quiet = self.quiet;
+                "{} Wildcard DNS detected: *.{} -> {} (matching names are filtered)",
+                "[!]".yellow(),
+                domain,
+                ips.join(", ")
+            );
+        }
+
+        let start_time = Instant::now();
+
+        say!(quiet = self.quiet; "{}", "[*] Initializing scan...".blue());
+        match total {
+            Some(total) => say!(quiet = self.quiet; "{} Found {} subdomains to scan", "[*]".blue(), total),
+            None => say!(quiet = self.quiet; "{} Streaming candidates in chunks of {}", "[*]".blue(), SCAN_CHUNK),
+        }
+        say!(quiet = self.quiet; "{} Using {} concurrent connections", "[*]".blue(), self.concurrency);
+
+        let progress = quiet_progress_bar(self.quiet, total.unwrap_or(0) as u64, "Scanning... 0 found");
+        let live = Arc::new(LiveStats::new(total.unwrap_or(0)));
+        let stats_listener = spawn_stats_listener(live.clone(), progress.clone());
+
+        let mut stats = ScanStats::default();
+        let mut valid_subdomains = Vec::new();
+        let mut restored_count = 0;
+        loop {
+            let chunk: Vec<Candidate> = candidates.by_ref().take(SCAN_CHUNK).collect();
+            if chunk.is_empty() {
+                break;
+            }
+            if total.is_none() {
+                progress.inc_length(chunk.len() as u64);
+                live.total.fetch_add(chunk.len(), Ordering::Relaxed);
+            }
+
+            let (restored, chunk) = self.restore_checkpoint(chunk);
+            for (result, _) in restored.iter().filter(|(_, status)| status.is_finding()) {
+                self.emit_to_sinks(result, &progress);
+            }
+            restored_count += restored.len();
+            progress.inc(restored.len() as u64);
+            live.processed.fetch_add(restored.len(), Ordering::Relaxed);
+
+            let scanned = self.perform_scan(&chunk, domain, &wildcard, &progress, &live).await;
+            valid_subdomains.extend(
+                restored
+                    .into_iter()
+                    .chain(scanned)
+                    .filter_map(|(result, status)| tally(&mut stats, result, status)),
+            );
+        }
+
+        if let Some(listener) = stats_listener {
+            listener.abort();
+        }
+        if let Some(checkpoint) = self.checkpoint.lock().unwrap().as_mut() {
+            if let Err(e) = checkpoint.flush() {
+                progress.suspend(|| eprintln!("{} {}", "[!]".red(), e));
+            }
+        }
+        progress.finish_with_message("scan completed");
+        stats.elapsed_ms = start_time.elapsed().as_millis() as u64;
+
+        if restored_count > 0 {
+            say!(quiet = self.quiet; "{} Restored {} candidates from the checkpoint", "[*]".blue(), restored_count);
+        }
+        say!(quiet = self.quiet; "\n{}", "Scan Summary:".bright_blue().bold());
+        say!(quiet = self.quiet; "{} {:.2?}", "Time elapsed:".blue(), start_time.elapsed());
+        say!(quiet = self.quiet; "{} {}", "Valid subdomains:".green(), stats.valid);
+        say!(quiet = self.quiet; "{} {}", "Invalid subdomains:".yellow(), stats.invalid);
+        if let Some(latency) = latency_summary(&valid_subdomains) {
+            say!(quiet = self.quiet; "{} {}", "Lookup latency:".blue(), latency);
+        }
+        if stats.timed_out + stats.servfail + stats.refused + stats.errors > 0 {
+            say!(quiet = self.quiet; "{} {}", format!("  Timed out after {} retries:", self.retries).yellow(), stats.timed_out);
+            say!(quiet = self.quiet; "{} {}", format!("  SERVFAIL after {} retries:", self.retries).yellow(), stats.servfail);
+            say!(quiet = self.quiet; "{} {}", format!("  REFUSED after {} retries:", self.retries).yellow(), stats.refused);
+            say!(quiet = self.quiet; "{} {}", format!("  Errored after {} retries:", self.retries).yellow(), stats.errors);
+        }
+        if stats.skipped > 0 {
+            say!(quiet = self.quiet; "{} {}", "Skipped subdomains:".dimmed(), stats.skipped);
+        }
+        if self.nxdomain_verifier.is_some() {
+            say!(quiet = self.quiet; "{} {}", "NXDOMAIN discrepancies:".magenta(), stats.discrepancies);
+        }
+        if self.trusted_verifier.is_some() {
+            say!(quiet = self.quiet; "{} {}", "Rejected by trusted resolvers:".magenta(), stats.unverified);
+        }
+        if !wildcard.is_empty() {
+            say!(quiet = self.quiet; "{} {}", "Wildcard matches:".yellow(), stats.wildcards);
+        }
+        if !self.takeover_fingerprints.is_empty() {
+            say!(quiet = self.quiet; "{} {}", "Dangling CNAMEs:".red(), stats.dangling);
+        }
+        say!(quiet = self.quiet; "{} {}", "Total processed:".blue(), stats.total);
+
+        Ok((valid_subdomains, stats))
+    }
+
+    async fn perform_scan(
+        &self,
+        subdomains: &[Candidate],
+        domain: &str,
+        wildcard: &[IpAddr],
+        progress: &ProgressBar,
+        stats: &LiveStats,
+    ) -> Vec<(ScanResult, ScanStatus)> {
+        if self.shard_resolvers.len() < 2 || !(self.shard_by_resolver || self.round_robin || self.blacklisting) {
+            let resolvers = std::slice::from_ref(&self.resolver);
+            return self.scan_shard(subdomains.to_vec(), resolvers, domain, wildcard, progress, stats).await;
+        }
+        if !self.shard_by_resolver {
+            return self.scan_shard(subdomains.to_vec(), &self.shard_resolvers, domain, wildcard, progress, stats).await;
+        }
+
+        say!(quiet = self.quiet; 
+            "{} Sharding {} candidates across {} resolvers",
+            "[*]".blue(),
+            subdomains.len(),
+            self.shard_resolvers.len()
+        );
+        let shards = shard(subdomains, self.shard_resolvers.len());
+        let scans = shards
+            .into_iter()
+            .zip(&self.shard_resolvers)
+            .map(|(shard, resolver)| {
+                self.scan_shard(shard, std::slice::from_ref(resolver), domain, wildcard, progress, stats)
+            });
+        futures::future::join_all(scans).await.into_iter().flatten().collect()
+    }
+
+    /// Scan `subdomains` in one `concurrency`-wide pipeline, sending the lookups of
+    /// each candidate to the next of `resolvers` in turn. When blacklisting with several
+    /// `resolvers` (then always `shard_resolvers`), blacklisted ones are passed over.
+    /// Valid names are compared with `wildcard`, the baseline of `domain`, or with
+    /// that of their own parent zone when they sit deeper (see `subzone_wildcard`).
+    async fn scan_shard(
+        &self,
+        subdomains: Vec<Candidate>,
+        resolvers: &[TokioAsyncResolver],
+        domain: &str,
+        wildcard: &[IpAddr],
+        progress: &ProgressBar,
+        stats: &LiveStats,
+    ) -> Vec<(ScanResult, ScanStatus)> {
+        stream::iter(subdomains.into_iter().enumerate())
+            .map(|(index, Candidate { name: subdomain, source })| {
+                let progress = progress.clone();
+                let tracked = self.blacklisting && resolvers.len() > 1;
+                async move {
+                    let slot = if tracked { self.pick_resolver(index) } else { index % resolvers.len() };
+                    let resolver = &resolvers[slot];
+                    if !self.jitter.is_zero() {
+                        let delay = rand::thread_rng().gen_range(Duration::ZERO..=self.jitter);
+                        tokio::time::sleep(delay).await;
+                    }
+                    let (mut status, mut result) = self.check_subdomain(resolver, &subdomain).await;
+                    result.source = source;
+                    if tracked {
+                        self.record_health(slot, &status, &progress);
+                    }
+                    if status == ScanStatus::Valid {
+                        let subzone = self.subzone_wildcard(&subdomain, domain, &progress).await;
+                        if matches_wildcard(&result.ips, subzone.as_deref().unwrap_or(wildcard)) {
+                            status = ScanStatus::Wildcard;
+                        }
+                    }
+
+                    progress.inc(1);
+                    stats.processed.fetch_add(1, Ordering::Relaxed);
+                    if let (Some(checkpoint), Some(label)) =
+                        (self.checkpoint.lock().unwrap().as_mut(), status.checkpoint_label())
+                    {
+                        let detail = status.checkpoint_detail(&result);
+                        if let Err(e) = checkpoint.record(&subdomain, label, detail.as_deref()) {
+                            progress.suspend(|| eprintln!("{} {}", "[!]".red(), e));
+                        }
+                    }
+                    if status.is_finding() {
+                        self.emit_to_sinks(&result, &progress);
+                    }
+                    match &status {
+                        ScanStatus::Valid => {
+                            let count = stats.found.fetch_add(1, Ordering::Relaxed) + 1;
+                            progress.set_message(format!("Scanning... {} found", count));
+                            progress.println(format!("{} {}", "✓".green(), to_unicode_domain(&subdomain).green()))
+                        }
+                        ScanStatus::Invalid(reason) if self.verbosity >= 3 => progress.println(format!(
+                            "{} {} {}",
+                            "✗".yellow(),
+                            subdomain.yellow(),
+                            format!("({})", reason).dimmed()
+                        )),
+                        ScanStatus::Invalid(_) => progress.println(format!("{} {}", "✗".yellow(), subdomain.yellow())),
+                        ScanStatus::Skipped if self.verbosity >= 3 => progress.println(format!(
+                            "{} {} {}",
+                            "-".dimmed(),
+                            subdomain.dimmed(),
+                            "(skipped)".dimmed()
+                        )),
+                        ScanStatus::Skipped => {}
+                        ScanStatus::Wildcard if self.verbosity >= 3 => progress.println(format!(
+                            "{} {} {}",
+                            "~".dimmed(),
+                            subdomain.dimmed(),
+                            "(wildcard)".dimmed()
+                        )),
+                        ScanStatus::Wildcard => {}
+                        ScanStatus::Discrepancy(detail) => progress.println(format!(
+                            "{} {} {}",
+                            "≠".magenta(),
+                            subdomain.magenta(),
+                            format!("(NXDOMAIN, but {})", detail).dimmed()
+                        )),
+                        ScanStatus::Unverified(detail) => progress.println(format!(
+                            "{} {} {}",
+                            "≠".magenta(),
+                            subdomain.magenta(),
+                            format!("(resolved, but trusted resolvers: {})", detail).dimmed()
+                        )),
+                        ScanStatus::Dangling(target) => progress.println(format!(
+                            "{} {} {}",
+                            "⚠".red(),
+                            subdomain.red(),
+                            format!("(dangling CNAME -> {})", target).dimmed()
+                        )),
+                    }
+                    (result, status)
+                }
+            })
+            .buffered(self.concurrency)
+            .collect::<Vec<_>>()
+            .await
+    }
+
+    async fn check_subdomain(
+        &self,
+        resolver: &TokioAsyncResolver,
+        subdomain: &str,
+    ) -> (ScanStatus, ScanResult) {
+        let mut result = ScanResult { subdomain: subdomain.to_string(), ..Default::default() };
+        if is_onion(subdomain) {
+            return (self.check_onion(subdomain).await, result);
+        }
+
+        let queries_addresses = self.record_types.iter().any(|t| matches!(t, RecordType::A | RecordType::AAAA));
+        let lookup = if queries_addresses {
+            self.cached_lookup_addrs(resolver, subdomain).await
+        } else {
+            Err(InvalidReason::NoData)
+        };
+
+        let lookup = match lookup {
+            Ok(lookup) if !lookup.ips.is_empty() => {
+                result.latency_ms = Some(lookup.latency.as_millis() as u64);
+                match &self.trusted_verifier {
+                    Some((_, trusted)) => match self.lookup_addrs(trusted, subdomain).await {
+                        Ok(confirmed) if !confirmed.ips.is_empty() => confirmed,
+                        Ok(_) => return (ScanStatus::Unverified(InvalidReason::NoAddresses.to_string()), result),
+                        Err(reason) => return (ScanStatus::Unverified(reason.to_string()), result),
+                    },
+                    None => lookup,
+                }
+            }
+            Err(InvalidReason::NxDomain) => {
+                if let Some(status) = self.check_dangling(resolver, subdomain, &mut result).await {
+                    return (status, result);
+                }
+                let status = match &self.nxdomain_verifier {
+                    Some((ip, verifier)) => {
+                        let verdict = self.lookup_addrs(verifier, subdomain).await.map(|lookup| lookup.ips);
+                        verify_nxdomain(*ip, verdict)
+                    }
+                    None => ScanStatus::Invalid(InvalidReason::NxDomain),
+                };
+                return (status, result);
+            }
+            other => {
+                let reason = match other {
+                    Ok(_) => InvalidReason::NoAddresses,
+                    Err(reason) => reason,
+                };
+                if matches!(reason, InvalidReason::NoData | InvalidReason::NoAddresses) {
+                    if let Some(status) = self.check_dangling(resolver, subdomain, &mut result).await {
+                        return (status, result);
+                    }
+                }
+                let status = match self.lookup_other_types(resolver, subdomain).await {
+                    Ok(matched) if !matched.is_empty() => {
+                        let matched = match &self.trusted_verifier {
+                            Some((_, trusted)) => match self.lookup_other_types(trusted, subdomain).await {
+                                Ok(confirmed) if !confirmed.is_empty() => confirmed,
+                                Ok(_) => return (ScanStatus::Unverified(InvalidReason::NoData.to_string()), result),
+                                Err(reason) => return (ScanStatus::Unverified(reason.to_string()), result),
+                            },
+                            None => matched,
+                        };
+                        result.record_types = matched.iter().map(ToString::to_string).collect();
+                        ScanStatus::Valid
+                    }
+                    Err(InvalidReason::NxDomain) if !queries_addresses => ScanStatus::Invalid(InvalidReason::NxDomain),
+                    _ => ScanStatus::Invalid(reason),
+                };
+                return (status, result);
+            }
+        };
+
+        // Any answer makes the name valid; whether something listens on it is for
+        // `--check-ports` and `--probe` to find out.
+        result.ttl = lookup.ttl;
+        for ip in lookup.ips {
+            if !result.ips.contains(&ip) {
+                result.ips.push(ip);
+            }
+        }
+        let other_types = self.lookup_other_types(resolver, subdomain).await.unwrap_or_default();
+        result.record_types = self
+            .record_types
+            .iter()
+            .filter(|record_type| match record_type {
+                RecordType::A => result.ips.iter().any(IpAddr::is_ipv4),
+                RecordType::AAAA => result.ips.iter().any(IpAddr::is_ipv6),
+                other => other_types.contains(other),
+            })
+            .map(ToString::to_string)
+            .collect();
+
+        if self.resolve_cname {
+            let chain = follow_cname_chain(subdomain, self.max_cname_depth, |name| async move {
+                self.lookup_cname(resolver, &name).await
+            })
+            .await;
+            result.cname = chain.hops.first().cloned();
+            result.cname_chain = chain.hops;
+            result.cname_issue = chain.issue;
+        }
+        (ScanStatus::Valid, result)
+    }
+
+    /// The requested non-address record types that have records for `subdomain`.
+    /// Fails with `NxDomain` only when no type matched and a lookup reported NXDOMAIN.
+    async fn lookup_other_types(&self, resolver: &TokioAsyncResolver, subdomain: &str) -> Result<Vec<RecordType>, InvalidReason> {
+        let mut matched = Vec::new();
+        let mut nxdomain = false;
+        for record_type in &self.record_types {
+            if matches!(record_type, RecordType::A | RecordType::AAAA) {
+                continue;
+            }
+            self.throttle().await;
+            match tokio::time::timeout(self.timeout, resolver.lookup(subdomain, *record_type)).await {
+                Ok(Ok(lookup)) if lookup.record_iter().next().is_some() => matched.push(*record_type),
+                Ok(Err(e)) => {
+                    if let ResolveErrorKind::NoRecordsFound { response_code: ResponseCode::NXDomain, .. } = e.kind() {
+                        nxdomain = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+        if matched.is_empty() && nxdomain {
+            Err(InvalidReason::NxDomain)
+        } else {
+            Ok(matched)
+        }
+    }
+
+    /// Addresses of `name` from the scan resolver, empty if it does not resolve.
+    pub async fn resolve(&self, name: &str) -> Vec<IpAddr> {
+        self.cached_lookup_addrs(self.next_resolver(), name).await.map(|lookup| lookup.ips).unwrap_or_default()
+    }
+
+    /// `lookup_addrs` through the answer cache. Answers (addresses, NXDOMAIN, NODATA)
+    /// are kept for the rest of the run; failures are not, so a later candidate of the
+    /// same name tries again.
+    async fn cached_lookup_addrs(&self, resolver: &TokioAsyncResolver, subdomain: &str) -> AddrAnswer {
+        let cell = {
+            let mut cache = self.answer_cache.lock().unwrap();
+            let key = subdomain.to_lowercase();
+            match cache.get(&key) {
+                Some(cell) => Some(cell.clone()),
+                None if cache.len() < DNS_CACHE_CAPACITY => Some(cache.entry(key).or_default().clone()),
+                None => None,
+            }
+        };
+        let Some(cell) = cell else {
+            return self.lookup_addrs(resolver, subdomain).await;
+        };
+        let answer = cell
+            .get_or_try_init(|| async {
+                match self.lookup_addrs(resolver, subdomain).await {
+                    Err(
+                        reason @ (InvalidReason::TimedOut
+                        | InvalidReason::ServFail
+                        | InvalidReason::Refused
+                        | InvalidReason::LookupFailed(_)),
+                    ) => Err(reason),
+                    answer => Ok(answer),
+                }
+            })
+            .await;
+        match answer {
+            Ok(answer) => answer.clone(),
+            Err(reason) => Err(reason),
+        }
+    }
+
+    /// Addresses of `subdomain` and the lowest TTL among the answer records.
+    /// `lookup_addrs_once`, retried with exponential backoff when a retry delay is set.
+    /// Only timeouts and resolver failures are retried; NXDOMAIN and NODATA are answers.
+    async fn lookup_addrs(&self, resolver: &TokioAsyncResolver, subdomain: &str) -> AddrAnswer {
+        let mut attempt = 0;
+        loop {
+            match self.lookup_addrs_once(resolver, subdomain).await {
+                Err(InvalidReason::TimedOut | InvalidReason::ServFail | InvalidReason::Refused | InvalidReason::LookupFailed(_))
+                    if self.scanner_retries() && attempt < self.retries =>
+                {
+                    tokio::time::sleep(jittered(self.retry_delay.saturating_mul(1 << attempt.min(16)))).await;
+                    attempt += 1;
+                }
+                outcome => return outcome,
+            }
+        }
+    }
+
+    async fn lookup_addrs_once(&self, resolver: &TokioAsyncResolver, subdomain: &str) -> AddrAnswer {
+        self.throttle().await;
+        let started = Instant::now();
+        match tokio::time::timeout(self.lookup_deadline(), resolver.lookup_ip(subdomain)).await {
+            Ok(Ok(lookup)) => {
+                let ttl = lookup.as_lookup().record_iter().map(|record| record.ttl()).min();
+                // Sorted (IPv4 before IPv6) so output does not depend on answer order
+                let mut ips: Vec<IpAddr> = lookup.iter().collect();
+                ips.sort();
+                ips.dedup();
+                Ok(AddrLookup { ips, ttl, latency: started.elapsed() })
+            }
+            Ok(Err(e)) => Err(match e.kind() {
+                ResolveErrorKind::NoRecordsFound { response_code, .. } if *response_code == ResponseCode::NXDomain => {
+                    InvalidReason::NxDomain
+                }
+                // A server failure is no answer about the name, so it is retried like a timeout
+                ResolveErrorKind::NoRecordsFound { response_code: ResponseCode::ServFail, .. } => InvalidReason::ServFail,
+                ResolveErrorKind::NoRecordsFound { response_code: ResponseCode::Refused, .. } => InvalidReason::Refused,
+                ResolveErrorKind::NoRecordsFound { .. } => InvalidReason::NoData,
+                ResolveErrorKind::Timeout => InvalidReason::TimedOut,
+                _ => InvalidReason::LookupFailed(e.to_string()),
+            }),
+            Err(_) => Err(InvalidReason::TimedOut),
+        }
+    }
+
+    /// The slot of `shard_resolvers` for the `index`th candidate: the next in turn, or
+    /// the one after it that is not blacklisted. When all are, the turn is kept.
+    fn pick_resolver(&self, index: usize) -> usize {
+        let count = self.health_windows.len();
+        let now = Instant::now();
+        (0..count)
+            .map(|offset| (index + offset) % count)
+            .find(|slot| {
+                let mut window = self.health_windows[*slot].lock().unwrap();
+                match window.blacklisted_until {
+                    Some(until) if until > now => false,
+                    Some(_) => {
+                        window.blacklisted_until = None;
+                        true
+                    }
+                    None => true,
+                }
+            })
+            .unwrap_or(index % count)
+    }
+
+    /// The resolver for a lookup outside the per-candidate flow (wildcard probes, reverse
+    /// lookups, `resolve`): the next of the pool in turn whenever candidates rotate through
+    /// it, otherwise the primary resolver.
+    fn next_resolver(&self) -> &TokioAsyncResolver {
+        if self.shard_resolvers.len() < 2 || !(self.shard_by_resolver || self.round_robin || self.blacklisting) {
+            return &self.resolver;
+        }
+        let turn = self.rotation.fetch_add(1, Ordering::Relaxed);
+        &self.shard_resolvers[self.pick_resolver(turn)]
+    }
+
+    /// Count a lookup outcome against its nameserver, blacklisting it when too many
+    /// lookups of the window failed.
+    fn record_health(&self, slot: usize, status: &ScanStatus, progress: &ProgressBar) {
+        let failed = matches!(
+            status,
+            ScanStatus::Invalid(
+                InvalidReason::TimedOut | InvalidReason::ServFail | InvalidReason::Refused | InvalidReason::LookupFailed(_)
+            )
+        );
+        let mut window = self.health_windows[slot].lock().unwrap();
+        window.lookups += 1;
+        window.failures += usize::from(failed);
+        if window.lookups < HEALTH_WINDOW {
+            return;
+        }
+        if window.failures * 100 > window.lookups * HEALTH_MAX_FAILURE_PERCENT {
+            window.blacklisted_until = Some(Instant::now() + BLACKLIST_DURATION);
+            progress.println(format!(
+                "{} Blacklisting {} for {}s: {} of its last {} lookups failed",
+                "[!]".yellow(),
+                self.shard_addrs[slot],
+                BLACKLIST_DURATION.as_secs(),
+                window.failures,
+                window.lookups
+            ));
+        }
+        window.lookups = 0;
+        window.failures = 0;
+    }
+
+    /// Wait for a rate limiter slot before a DNS lookup or port connect, when `--rate` is set.
+    async fn throttle(&self) {
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.wait().await;
+        }
+    }
+
+    /// Hidden services have no addresses to report; they are valid if the proxy
+    /// can open a connection to port 80.
+    async fn check_onion(&self, subdomain: &str) -> ScanStatus {
+        let Some(proxy) = self.tor_proxy else {
+            return ScanStatus::Skipped;
+        };
+        match tokio::time::timeout(
+            self.timeout,
+            tokio_socks::tcp::Socks5Stream::connect(proxy, (subdomain, 80))
+        ).await {
+            Ok(Ok(_)) => ScanStatus::Valid,
+            Ok(Err(e)) => ScanStatus::Invalid(InvalidReason::LookupFailed(e.to_string())),
+            Err(_) => ScanStatus::Invalid(InvalidReason::Unreachable),
+        }
+    }
+
+    /// For a name without addresses: `Dangling` if its CNAME points under a takeover
+    /// fingerprint, recording the target in `result`.
+    async fn check_dangling(&self, resolver: &TokioAsyncResolver, subdomain: &str, result: &mut ScanResult) -> Option<ScanStatus> {
+        if self.takeover_fingerprints.is_empty() {
+            return None;
+        }
+        let target = self.lookup_cname(resolver, subdomain).await?;
+        result.cname = Some(target.clone());
+        result.cname_match(&self.takeover_fingerprints)?;
+        result.cname_chain = vec![target.clone()];
+        result.dangling = true;
+        result.status = ResultStatus::Dangling;
+        Some(ScanStatus::Dangling(target))
+    }
+
+    async fn lookup_cname(&self, resolver: &TokioAsyncResolver, subdomain: &str) -> Option<String> {
+        self.throttle().await;
+        let lookup = tokio::time::timeout(self.timeout, resolver.lookup(subdomain, RecordType::CNAME))
+            .await
+            .ok()?
+            .ok()?;
+        lookup.record_iter().find_map(|record| match record.data() {
+            Some(RData::CNAME(cname)) => Some(cname.0.to_utf8().trim_end_matches('.').to_lowercase()),
+            _ => None,
+        })
+    }
+
+    /// Hostnames the PTR records of `ip` point to, lowercased; empty when it has none.
+    pub async fn reverse_lookup(&self, ip: IpAddr) -> Vec<String> {
+        self.throttle().await;
+        match tokio::time::timeout(self.timeout, self.next_resolver().reverse_lookup(ip)).await {
+            Ok(Ok(lookup)) => lookup.iter().map(|name| name.0.to_utf8().trim_end_matches('.').to_lowercase()).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Reverse-resolve every address and keep the hostnames under `domain` (or the
+    /// apex itself), deduplicated and sorted.
+    pub async fn reverse_sweep(&self, ips: &[IpAddr], domain: &str) -> Vec<String> {
+        let domain = domain.trim_end_matches('.').to_lowercase();
+        let suffix = format!(".{}", domain);
+        say!(quiet = self.quiet; "{} Reverse-resolving {} addresses", "[*]".blue(), ips.len());
+        let progress = quiet_progress_bar(self.quiet, ips.len() as u64, "Reverse DNS...");
+
+        let names: Vec<Vec<String>> = stream::iter(ips)
+            .map(|ip| {
+                let progress = progress.clone();
+                async move {
+                    let names = self.reverse_lookup(*ip).await;
+                    progress.inc(1);
+                    names
+                }
+            })
+            .buffer_unordered(self.concurrency)
+            .collect()
+            .await;
+        progress.finish_with_message("reverse DNS completed");
+
+        let mut names: Vec<String> = names
+            .into_iter()
+            .flatten()
+            .filter(|name| *name == domain || name.ends_with(&suffix))
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// TCP-connect to each port on every resolved IP and record which ones accept
+    /// connections. A port counts as open for a subdomain if any of its IPs answer.
+    /// Connects use the lookup timeout and share the `--rate` limit with DNS lookups.
+    pub async fn check_ports(&self, results: &mut [ScanResult], ports: &[u16]) {
+        let probes: Vec<(usize, SocketAddr)> = results
+            .iter()
+            .enumerate()
+            .flat_map(|(index, result)| {
+                result.ips.iter().flat_map(move |ip| {
+                    ports.iter().map(move |port| (index, SocketAddr::new(*ip, *port)))
+                })
+            })
+            .collect();
+
+        say!(quiet = self.quiet; "{} Checking {} ports across {} subdomains", "[*]".blue(), ports.len(), results.len());
+        let progress = quiet_progress_bar(self.quiet, probes.len() as u64, "Checking ports...");
+
+        let open: Vec<(usize, u16)> = stream::iter(probes)
+            .map(|(index, addr)| {
+                let progress = progress.clone();
+                async move {
+                    self.throttle().await;
+                    let is_open = matches!(tokio::time::timeout(self.timeout, TcpStream::connect(addr)).await, Ok(Ok(_)));
+                    progress.inc(1);
+                    (index, addr.port(), is_open)
+                }
+            })
+            .buffer_unordered(self.concurrency)
+            .filter_map(|(index, port, is_open)| async move { is_open.then_some((index, port)) })
+            .collect()
+            .await;
+        progress.finish_with_message("port check completed");
+
+        for (index, port) in open {
+            let open_ports = &mut results[index].open_ports;
+            if !open_ports.contains(&port) {
+                open_ports.push(port);
+            }
+        }
+        for result in results.iter_mut() {
+            result.open_ports.sort_unstable();
+        }
+    }
+}
+
+/// A configurable local nameserver for tests.
+#[cfg(test)]
+pub(crate) mod fake_dns {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use trust_dns_resolver::proto::op::{Message, MessageType, ResponseCode};
+    use trust_dns_resolver::proto::rr::{rdata, RData, Record, RecordType};
+    use trust_dns_resolver::proto::serialize::binary::BinEncodable;
+
+    /// One question put to a fake nameserver.
+    pub struct Question {
+        /// The queried name as sent, with its trailing dot
+        pub name: String,
+        pub record_type: RecordType,
+        /// Asked over TCP rather than UDP
+        pub tcp: bool,
+    }
+
+    /// Response code and answer records for a question, or `None` to drop the query.
+    pub type Reply = Option<(ResponseCode, Vec<RData>)>;
+
+    type Answer = Arc<Mutex<dyn FnMut(&Question) -> Reply + Send>>;
+
+    /// A NOERROR reply carrying `data`.
+    pub fn records(data: Vec<RData>) -> Reply {
+        Some((ResponseCode::NoError, data))
+    }
+
+    /// A reply with `code` and no records.
+    pub fn code(code: ResponseCode) -> Reply {
+        Some((code, Vec::new()))
+    }
+
+    pub fn a(ip: [u8; 4]) -> RData {
+        RData::A(rdata::A(ip.into()))
+    }
+
+    #[derive(Default)]
+    pub struct Options {
+        /// Wait this long before each answer; queries waiting overlap.
+        pub delay: Duration,
+        /// Also answer over TCP, on the same port.
+        pub tcp: bool,
+        /// Send UDP answers without records and with the truncation bit set.
+        pub truncate_udp: bool,
+    }
+
+    /// Serve DNS on a local UDP port, answering each query's first question with `answer`.
+    pub async fn serve(answer: impl FnMut(&Question) -> Reply + Send + 'static) -> u16 {
+        serve_with(Options::default(), answer).await
+    }
+
+    pub async fn serve_with(options: Options, answer: impl FnMut(&Question) -> Reply + Send + 'static) -> u16 {
+        let answer: Answer = Arc::new(Mutex::new(answer));
+        let (socket, listener) = if options.tcp {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            (tokio::net::UdpSocket::bind(("127.0.0.1", port)).await.unwrap(), Some(listener))
+        } else {
+            (tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap(), None)
+        };
+        let socket = Arc::new(socket);
+        let port = socket.local_addr().unwrap().port();
+
+        let udp_answer = answer.clone();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            while let Ok((len, peer)) = socket.recv_from(&mut buf).await {
+                let Some(bytes) = respond(&buf[..len], false, options.truncate_udp, &udp_answer) else { continue };
+                let socket = socket.clone();
+                tokio::spawn(async move {
+                    tokio::time::sleep(options.delay).await;
+                    let _ = socket.send_to(&bytes, peer).await;
+                });
+            }
+        });
+        if let Some(listener) = listener {
+            tokio::spawn(async move {
+                while let Ok((mut stream, _)) = listener.accept().await {
+                    let answer = answer.clone();
+                    tokio::spawn(async move {
+                        let mut len = [0u8; 2];
+                        while stream.read_exact(&mut len).await.is_ok() {
+                            let mut buf = vec![0u8; u16::from_be_bytes(len) as usize];
+                            if stream.read_exact(&mut buf).await.is_err() {
+                                break;
+                            }
+                            let Some(bytes) = respond(&buf, true, false, &answer) else { continue };
+                            tokio::time::sleep(options.delay).await;
+                            let _ = stream.write_all(&(bytes.len() as u16).to_be_bytes()).await;
+                            let _ = stream.write_all(&bytes).await;
+                        }
+                    });
+                }
+            });
+        }
+        port
+    }
+
+    fn respond(query: &[u8], tcp: bool, truncate: bool, answer: &Answer) -> Option<Vec<u8>> {
+        let query = Message::from_vec(query).ok()?;
+        let question = query.queries().first()?;
+        let asked = Question { name: question.name().to_utf8(), record_type: question.query_type(), tcp };
+        let (code, data) = (answer.lock().unwrap())(&asked)?;
+
+        let mut response = Message::new();
+        response
+            .set_id(query.id())
+            .set_message_type(MessageType::Response)
+            .set_recursion_available(true)
+            .set_response_code(code)
+            .set_truncated(truncate);
+        response.add_query(question.clone());
+        if !truncate {
+            for data in data {
+                response.add_answer(Record::from_rdata(question.name().clone(), 60, data));
+            }
+        }
+        response.to_bytes().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::fake_dns::{self, a, code, records};
+    use trust_dns_resolver::proto::rr::{rdata, Name};
+
+    #[tokio::test]
+    async fn test_scanner() {
+        let scanner = Scanner::new(10).await.expect("Failed to create scanner");
+        
+        // Test empty subdomains case
+        let empty_result = scanner.scan_domains("example.com", Vec::<String>::new()).await;
+        assert!(matches!(empty_result, Err(ScanError::EmptyInput)));
+
+        // Test with some domains
+        let test_subdomains = [
+            "www.example.com".to_string(),
+            "mail.example.com".to_string(),
+            "test.example.com".to_string()
+        ];
+        assert_eq!(test_subdomains.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_check_ports() {
+        let scanner = Scanner::new(10).await.expect("Failed to create scanner");
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open_port = listener.local_addr().unwrap().port();
+        let closed_port = {
+            let probe = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            probe.local_addr().unwrap().port()
+        };
+
+        let mut results = vec![ScanResult {
+            subdomain: "localhost".to_string(),
+            ips: vec!["127.0.0.1".parse().unwrap()],
+            ..Default::default()
+        }];
+        scanner.check_ports(&mut results, &[closed_port, open_port]).await;
+        assert_eq!(results[0].open_ports, vec![open_port]);
+    }
+
+    #[tokio::test]
+    async fn test_top_ports_are_paced() {
+        let unanswered = || vec![ScanResult { ips: vec!["192.0.2.1".parse().unwrap()], ..Default::default() }];
+
+        // A hundred connects to an address that never answers take one short timeout
+        let scanner = Scanner::new(100).await.unwrap().with_timing(Duration::from_millis(200), Duration::ZERO);
+        let started = Instant::now();
+        scanner.check_ports(&mut unanswered(), &top_ports(100)).await;
+        assert!(started.elapsed() < Duration::from_secs(2), "took {:?}", started.elapsed());
+
+        // ... and are spread out under --rate
+        let scanner = scanner.with_rate(Some(20));
+        let started = Instant::now();
+        scanner.check_ports(&mut unanswered(), &top_ports(11)).await;
+        assert!(started.elapsed() >= Duration::from_millis(500), "took {:?}", started.elapsed());
+    }
+
+    #[test]
+    fn test_flag_load_balanced() {
+        let ips = |count: u8| (1..=count).map(|i| IpAddr::from([10, 0, 0, i])).collect::<Vec<_>>();
+        let mut results = vec![
+            ScanResult { subdomain: "single.example.com".to_string(), ips: ips(1), ..Default::default() },
+            ScanResult { subdomain: "pair.example.com".to_string(), ips: ips(2), ..Default::default() },
+            ScanResult { subdomain: "pool.example.com".to_string(), ips: ips(6), ..Default::default() },
+        ];
+        flag_load_balanced(&mut results, 2);
+        let flagged: Vec<bool> = results.iter().map(|r| r.load_balanced).collect();
+        assert_eq!(flagged, vec![false, false, true]);
+    }
+
+    #[test]
+    fn test_cname_match() {
+        let suffixes = vec!["github.io".to_string(), "*.herokuapp.com".to_string()];
+        let result = |cname: Option<&str>| ScanResult {
+            subdomain: "docs.example.com".to_string(),
+            cname: cname.map(String::from),
+            ..Default::default()
+        };
+
+        assert_eq!(result(Some("example.github.io")).cname_match(&suffixes), Some("github.io"));
+        assert_eq!(result(Some("app.HerokuApp.com")).cname_match(&suffixes), Some("*.herokuapp.com"));
+        assert_eq!(result(Some("notgithub.io")).cname_match(&suffixes), None);
+        assert_eq!(result(None).cname_match(&suffixes), None);
+    }
+
+    #[test]
+    fn test_shard() {
+        let subdomains: Vec<String> = (0..5).map(|i| format!("host{}.example.com", i)).collect();
+        let shards = shard(&subdomains, 2);
+        assert_eq!(shards[0], vec!["host0.example.com", "host2.example.com", "host4.example.com"]);
+        assert_eq!(shards[1], vec!["host1.example.com", "host3.example.com"]);
+    }
+
+    #[test]
+    fn test_live_stats_snapshot() {
+        let stats = LiveStats::new(10);
+        stats.processed.fetch_add(4, Ordering::Relaxed);
+        stats.found.fetch_add(1, Ordering::Relaxed);
+        let snapshot = stats.snapshot();
+        assert!(snapshot.contains("Processed 4/10, 1 found"));
+    }
+
+    #[tokio::test]
+    async fn test_onion_candidates_are_skipped() {
+        assert!(is_onion("duckduckgogg42xjoc72x3sjasowoarfbgcmvfimaftt6twagswzczad.onion"));
+        assert!(is_onion("Shop.Example.ONION."));
+        assert!(!is_onion("onion"));
+        assert!(!is_onion("onion.example.com"));
+
+        let scanner = Scanner::new(10).await.expect("Failed to create scanner");
+        let (results, stats) = scanner
+            .scan_domains("onion", vec!["hidden.onion".to_string(), "other.onion".to_string()])
+            .await
+            .unwrap();
+        assert!(results.is_empty());
+        assert_eq!((stats.total, stats.skipped, stats.invalid), (2, 2, 0));
+    }
+
+    #[test]
+    fn test_verify_nxdomain() {
+        let verifier: IpAddr = "1.1.1.1".parse().unwrap();
+        assert_eq!(
+            verify_nxdomain(verifier, Err(InvalidReason::NxDomain)),
+            ScanStatus::Invalid(InvalidReason::NxDomain)
+        );
+        assert_eq!(
+            verify_nxdomain(verifier, Ok(vec!["10.0.0.1".parse().unwrap()])),
+            ScanStatus::Discrepancy("1.1.1.1 resolves it to 10.0.0.1".to_string())
+        );
+        assert!(matches!(verify_nxdomain(verifier, Err(InvalidReason::NoData)), ScanStatus::Discrepancy(_)));
+    }
+
+    #[tokio::test]
+    async fn test_follow_cname_chain() {
+        let zone: std::collections::HashMap<&str, &str> = [
+            ("www.example.com", "edge.example.net"),
+            ("edge.example.net", "origin.example.org"),
+            ("loop.example.com", "loop.example.com"),
+            ("a.example.com", "b.example.com"),
+            ("b.example.com", "a.example.com"),
+        ]
+        .into_iter()
+        .collect();
+        let lookup = |name: String| {
+            let next = zone.get(name.as_str()).map(|target| target.to_string());
+            async move { next }
+        };
+
+        let chain = follow_cname_chain("www.example.com", 10, lookup).await;
+        assert_eq!(chain.hops, vec!["edge.example.net", "origin.example.org"]);
+        assert_eq!(chain.issue, None);
+
+        let chain = follow_cname_chain("loop.example.com", 10, lookup).await;
+        assert!(chain.hops.is_empty());
+        assert_eq!(chain.issue, Some(CnameIssue::CnameLoop));
+
+        let chain = follow_cname_chain("a.example.com", 10, lookup).await;
+        assert_eq!(chain.hops, vec!["b.example.com"]);
+        assert_eq!(chain.issue, Some(CnameIssue::CnameLoop));
+
+        let chain = follow_cname_chain("www.example.com", 1, lookup).await;
+        assert_eq!(chain.hops, vec!["edge.example.net"]);
+        assert_eq!(chain.issue, Some(CnameIssue::ChainTooLong));
+    }
+
+    #[test]
+    fn test_retain_distinct_from() {
+        let apex: Vec<IpAddr> = vec!["10.0.0.1".parse().unwrap()];
+        let result = |name: &str, ips: &[&str]| ScanResult {
+            subdomain: name.to_string(),
+            ips: ips.iter().map(|ip| ip.parse().unwrap()).collect(),
+            ..Default::default()
+        };
+        let mut results = vec![
+            result("www.example.com", &["10.0.0.1"]),
+            result("mixed.example.com", &["10.0.0.1", "10.0.0.2"]),
+            result("vpn.example.com", &["192.0.2.7"]),
+            result("hidden.onion", &[]),
+        ];
+        assert_eq!(retain_distinct_from(&mut results, &apex), 1);
+        let names: Vec<&str> = results.iter().map(|r| r.subdomain.as_str()).collect();
+        assert_eq!(names, vec!["mixed.example.com", "vpn.example.com", "hidden.onion"]);
+    }
+
+    #[test]
+    fn test_flag_private() {
+        let bogons = [
+            "10.1.2.3", "172.16.0.1", "192.168.1.1", "127.0.0.1", "169.254.1.1", "100.64.0.1", "198.18.0.1",
+            "0.1.2.3", "240.0.0.1", "fd00::1", "fe80::1", "::1", "2001:db8::1", "::ffff:10.0.0.1",
+        ];
+        for bogon in bogons {
+            assert!(is_bogon(&bogon.parse().unwrap()), "{} is a bogon", bogon);
+        }
+        for public in ["8.8.8.8", "172.32.0.1", "100.128.0.1", "2606:4700::1111", "::ffff:1.1.1.1"] {
+            assert!(!is_bogon(&public.parse().unwrap()), "{} is public", public);
+        }
+
+        let result = |ips: &[&str]| ScanResult { ips: ips.iter().map(|ip| ip.parse().unwrap()).collect(), ..Default::default() };
+        let mut results = vec![result(&["10.0.0.1", "fd00::1"]), result(&["10.0.0.1", "8.8.8.8"]), result(&[])];
+        assert_eq!(flag_private(&mut results), 1);
+        let flagged: Vec<bool> = results.iter().map(|r| r.private).collect();
+        assert_eq!(flagged, vec![true, false, false]);
+    }
+
+    #[test]
+    fn test_latency_summary() {
+        let result = |latency_ms| ScanResult { latency_ms, ..Default::default() };
+        let mut results: Vec<ScanResult> = (1..=20).map(|ms| result(Some(ms * 10))).collect();
+        results.push(result(None));
+        assert_eq!(latency_summary(&results), Some(LatencySummary { min_ms: 10, avg_ms: 105, p95_ms: 190 }));
+        assert_eq!(latency_summary(&results).unwrap().to_string(), "min 10ms, avg 105ms, p95 190ms");
+        assert_eq!(latency_summary(&[result(Some(7))]), Some(LatencySummary { min_ms: 7, avg_ms: 7, p95_ms: 7 }));
+        assert_eq!(latency_summary(&[result(None)]), None);
+    }
+
+    #[test]
+    fn test_flag_low_ttl() {
+        let mut results: Vec<ScanResult> = [Some(30), Some(300), Some(3600), None]
+            .into_iter()
+            .map(|ttl| ScanResult { subdomain: "www.example.com".to_string(), ttl, ..Default::default() })
+            .collect();
+        assert_eq!(flag_low_ttl(&mut results, 300), 1);
+        let flagged: Vec<bool> = results.iter().map(|r| r.low_ttl).collect();
+        assert_eq!(flagged, vec![true, false, false, false]);
+    }
+
+    #[tokio::test]
+    async fn test_lookups_run_concurrently() {
+        // A local nameserver that answers NXDOMAIN to everything after 200ms, so a scan
+        // waiting on each lookup in turn would take a minute for 300 names
+        let options = fake_dns::Options { delay: Duration::from_millis(200), ..Default::default() };
+        let port = fake_dns::serve_with(options, |_| code(ResponseCode::NXDomain)).await;
+        let group = NameServerConfigGroup::from_ips_clear(&["127.0.0.1".parse().unwrap()], port, true);
+        let resolver = TokioAsyncResolver::tokio(ResolverConfig::from_parts(None, vec![], group), ResolverOpts::default());
+
+        let candidates: Vec<Candidate> = (0..300).map(|i| Candidate::from(format!("nx-{}.example.com", i))).collect();
+        let scanner = Scanner::new(candidates.len()).await.unwrap();
+        let progress = ProgressBar::hidden();
+        let stats = LiveStats::new(candidates.len());
+
+        let started = Instant::now();
+        let results = scanner.scan_shard(candidates, &[resolver], "example.com", &[], &progress, &stats).await;
+        assert_eq!(results.len(), 300);
+        // NXDOMAIN keeps its own reason rather than counting as a failed lookup
+        assert!(results.iter().all(|(_, status)| *status == ScanStatus::Invalid(InvalidReason::NxDomain)));
+        assert!(started.elapsed() < Duration::from_secs(5), "300 lookups took {:?}", started.elapsed());
+    }
+
+    #[tokio::test]
+    async fn test_record_types() {
+        let scanner = Scanner::new(10).await.expect("Failed to create scanner");
+        assert_eq!(scanner.query_types(), vec![RecordType::A, RecordType::AAAA]);
+
+        let scanner = scanner
+            .with_record_types(vec![RecordType::A, RecordType::MX, RecordType::A])
+            .with_cname_lookup(true);
+        assert_eq!(scanner.query_types(), vec![RecordType::A, RecordType::MX, RecordType::CNAME]);
+
+        let scanner = scanner.with_record_types(Vec::new());
+        assert_eq!(scanner.query_types(), vec![RecordType::A, RecordType::MX, RecordType::CNAME]);
+    }
+
+    /// Serve DNS where `www.example.test` has an A record, `smtp.example.test` A and MX
+    /// records and `mail.example.test` only MX and TXT records. Other names are NXDOMAIN.
+    async fn mail_nameserver() -> u16 {
+        fake_dns::serve(|question| match (question.name.as_str(), question.record_type) {
+            ("www.example.test." | "smtp.example.test.", RecordType::A) => records(vec![a([127, 0, 0, 2])]),
+            ("mail.example.test." | "smtp.example.test.", RecordType::MX) => {
+                records(vec![RData::MX(rdata::MX::new(10, Name::from_ascii("mx.example.net.").unwrap()))])
+            }
+            ("mail.example.test.", RecordType::TXT) => records(vec![RData::TXT(rdata::TXT::new(vec!["v=spf1 -all".to_string()]))]),
+            ("www.example.test." | "mail.example.test." | "smtp.example.test.", _) => records(Vec::new()),
+            _ => code(ResponseCode::NXDomain),
+        })
+        .await
+    }
+
+    /// Answers PTR queries for 192.0.2.1 (two names, one outside example.test) and
+    /// 192.0.2.2; every other query gets NXDOMAIN.
+    async fn ptr_nameserver() -> u16 {
+        fake_dns::serve(|question| {
+            let targets: &[&str] = match (question.name.as_str(), question.record_type) {
+                ("1.2.0.192.in-addr.arpa.", RecordType::PTR) => &["Mail.example.test.", "host.example.net."],
+                ("2.2.0.192.in-addr.arpa.", RecordType::PTR) => &["vpn.example.test."],
+                _ => return code(ResponseCode::NXDomain),
+            };
+            records(targets.iter().map(|target| RData::PTR(rdata::PTR(Name::from_ascii(target).unwrap()))).collect())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_reverse_sweep() {
+        let port = ptr_nameserver().await;
+        let resolvers = parse_resolvers(&[format!("127.0.0.1:{}", port)]).unwrap();
+        let scanner = Scanner::with_resolvers(&resolvers, 10).await.unwrap();
+
+        let ip: IpAddr = "192.0.2.1".parse().unwrap();
+        assert_eq!(scanner.reverse_lookup(ip).await, vec!["mail.example.test", "host.example.net"]);
+        let ips = ["192.0.2.1", "192.0.2.2", "192.0.2.3"].map(|ip| ip.parse().unwrap());
+        assert_eq!(scanner.reverse_sweep(&ips, "example.test").await, vec!["mail.example.test", "vpn.example.test"]);
+    }
+
+    #[test]
+    fn test_expand_to_slash24() {
+        let ips: Vec<IpAddr> = ["192.0.2.7", "192.0.2.200", "::1"].iter().map(|ip| ip.parse().unwrap()).collect();
+        let expanded = expand_to_slash24(&ips);
+        assert_eq!(expanded.len(), 257);
+        assert_eq!(expanded[0], "192.0.2.0".parse::<IpAddr>().unwrap());
+        assert_eq!(expanded[255], "192.0.2.255".parse::<IpAddr>().unwrap());
+        assert_eq!(expanded[256], "::1".parse::<IpAddr>().unwrap());
+    }
+
+    /// Answers A queries for example.com, and every A query when `poisoned`; other
+    /// names get NXDOMAIN.
+    async fn health_nameserver(poisoned: bool) -> u16 {
+        fake_dns::serve(move |question| {
+            if !poisoned && question.name != "example.com." {
+                code(ResponseCode::NXDomain)
+            } else if question.record_type == RecordType::A {
+                records(vec![a([10, 0, 0, 9])])
+            } else {
+                records(Vec::new())
+            }
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_check_resolvers() {
+        let good = format!("127.0.0.1:{}", health_nameserver(false).await);
+        let poisoned = format!("127.0.0.1:{}", health_nameserver(true).await);
+        // Bound but never answering
+        let silent = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let silent = silent.local_addr().unwrap().to_string();
+
+        let resolvers = parse_resolvers(&[good.clone(), poisoned.clone(), silent.clone()]).unwrap();
+        let mut scanner = Scanner::with_resolvers(&resolvers, 10)
+            .await
+            .unwrap()
+            .with_timing(Duration::from_millis(300), Duration::ZERO)
+            .with_retries(0);
+        let report = scanner.check_resolvers(HEALTH_CHECK_DOMAIN).await.unwrap();
+        assert_eq!(report[0], (resolvers[0], ResolverHealth::Healthy));
+        assert_eq!(report[1], (resolvers[1], ResolverHealth::Poisoned(vec!["10.0.0.9".parse().unwrap()])));
+        assert!(matches!(report[2].1, ResolverHealth::Dead(_)));
+        assert_eq!(scanner.shard_addrs, vec![resolvers[0]]);
+
+        // Nothing left after dropping every failing resolver
+        let resolvers = parse_resolvers(&[poisoned, silent]).unwrap();
+        let mut scanner = Scanner::with_resolvers(&resolvers, 10)
+            .await
+            .unwrap()
+            .with_timing(Duration::from_millis(300), Duration::ZERO)
+            .with_retries(0);
+        assert!(matches!(scanner.check_resolvers(HEALTH_CHECK_DOMAIN).await, Err(ScanError::ConfigError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_resolver_blacklist() {
+        let resolvers = parse_resolvers(&["127.0.0.1:5301".to_string(), "127.0.0.1:5302".to_string()]).unwrap();
+        let scanner = Scanner::with_resolvers(&resolvers, 10).await.unwrap().with_resolver_blacklist(true);
+        let progress = ProgressBar::hidden();
+        let timed_out = || ScanStatus::Invalid(InvalidReason::TimedOut);
+
+        // Half a window of failures is tolerated
+        for i in 0..HEALTH_WINDOW {
+            let status = if i % 2 == 0 { timed_out() } else { ScanStatus::Invalid(InvalidReason::NxDomain) };
+            scanner.record_health(0, &status, &progress);
+        }
+        assert_eq!((scanner.pick_resolver(0), scanner.pick_resolver(1)), (0, 1));
+
+        for _ in 0..HEALTH_WINDOW {
+            scanner.record_health(0, &timed_out(), &progress);
+        }
+        assert_eq!((scanner.pick_resolver(0), scanner.pick_resolver(1)), (1, 1));
+
+        // With every resolver blacklisted, candidates keep their turn
+        for _ in 0..HEALTH_WINDOW {
+            scanner.record_health(1, &timed_out(), &progress);
+        }
+        assert_eq!((scanner.pick_resolver(0), scanner.pick_resolver(1)), (0, 1));
+    }
+
+    #[tokio::test]
+    async fn test_matched_record_types() {
+        let port = mail_nameserver().await;
+        let resolvers = parse_resolvers(&[format!("127.0.0.1:{}", port)]).unwrap();
+        let scanner = Scanner::with_resolvers(&resolvers, 10)
+            .await
+            .unwrap()
+            .with_record_types(vec![RecordType::A, RecordType::AAAA, RecordType::MX, RecordType::TXT])
+            .with_wildcard_filter(false);
+
+        let candidates = ["www", "mail", "smtp", "gone"].map(|label| format!("{}.example.test", label)).to_vec();
+        let (mut results, _) = scanner.scan_domains("example.test", candidates).await.unwrap();
+        results.sort_by(|a, b| a.subdomain.cmp(&b.subdomain));
+        let matched: Vec<(&str, Vec<String>)> =
+            results.iter().map(|r| (r.subdomain.as_str(), r.record_types.clone())).collect();
+        assert_eq!(
+            matched,
+            vec![
+                ("mail.example.test", vec!["MX".to_string(), "TXT".to_string()]),
+                ("smtp.example.test", vec!["A".to_string(), "MX".to_string()]),
+                ("www.example.test", vec!["A".to_string()]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_covers_every_lookup() {
+        let port = mail_nameserver().await;
+        let resolvers = parse_resolvers(&[format!("127.0.0.1:{}", port)]).unwrap();
+        let scanner = Scanner::with_resolvers(&resolvers, 10)
+            .await
+            .unwrap()
+            .with_record_types(vec![RecordType::A, RecordType::MX, RecordType::TXT])
+            .with_rate(Some(10))
+            .with_wildcard_filter(false);
+
+        // The address lookup, then MX and TXT: three slots 100ms apart
+        let started = Instant::now();
+        let (results, _) = scanner.scan_domains("example.test", vec!["mail.example.test".to_string()]).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(started.elapsed() >= Duration::from_millis(200), "took {:?}", started.elapsed());
+    }
+
+    #[tokio::test]
+    async fn test_timeouts_overlap_and_progress_ticks() {
+        // A nameserver that never answers, so every lookup runs into the timeout
+        let silent = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let group = NameServerConfigGroup::from_ips_clear(&["127.0.0.1".parse().unwrap()], silent.local_addr().unwrap().port(), true);
+        let resolver = TokioAsyncResolver::tokio(ResolverConfig::from_parts(None, vec![], group), ResolverOpts::default());
+
+        let timeout = Duration::from_millis(300);
+        let scanner = Scanner::new(100).await.unwrap().with_timing(timeout, Duration::ZERO).with_retries(0);
+        let candidates: Vec<Candidate> = (0..200).map(|i| Candidate::from(format!("host{}.example.com", i))).collect();
+        let progress = ProgressBar::hidden();
+        let stats = LiveStats::new(candidates.len());
+
+        // Run one at a time, 200 lookups would take 200 timeouts; 100 in flight take about 2
+        let started = Instant::now();
+        let results = scanner.scan_shard(candidates, &[resolver], "example.com", &[], &progress, &stats).await;
+        let elapsed = started.elapsed();
+
+        assert_eq!(results.len(), 200);
+        assert!(results.iter().all(|(_, status)| *status == ScanStatus::Invalid(InvalidReason::TimedOut)));
+        assert!(elapsed < timeout * 10, "200 lookups took {:?}", elapsed);
+        assert_eq!(progress.position(), 200);
+        assert_eq!(stats.processed.load(Ordering::Relaxed), 200);
+    }
+
+    #[test]
+    fn test_matches_wildcard() {
+        let ip = |s: &str| -> IpAddr { s.parse().unwrap() };
+        let wildcard = vec![ip("10.0.0.1"), ip("10.0.0.2")];
+        assert!(matches_wildcard(&[ip("10.0.0.2"), ip("10.0.0.1")], &wildcard));
+        assert!(matches_wildcard(&[ip("10.0.0.1")], &wildcard));
+        assert!(!matches_wildcard(&[ip("10.0.0.1"), ip("10.0.0.3")], &wildcard));
+        assert!(!matches_wildcard(&[ip("10.0.0.1")], &[]));
+        assert!(!matches_wildcard(&[], &wildcard));
+
+        let label = random_label();
+        assert_eq!(label.len(), 20);
+        assert!(label.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()));
+    }
+
+    /// Serve DNS answering A queries for `real.example.test` with 127.0.0.3 and every
+    /// other name with the wildcard address 127.0.0.2. Names starting with `dual.`
+    /// also get AAAA answers (::1 and ::2).
+    async fn wildcard_nameserver() -> u16 {
+        fake_dns::serve(|question| match question.record_type {
+            RecordType::A if question.name.starts_with("real.") => records(vec![a([127, 0, 0, 3])]),
+            RecordType::A => records(vec![a([127, 0, 0, 2])]),
+            RecordType::AAAA if question.name.starts_with("dual.") => records(
+                [std::net::Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 2), std::net::Ipv6Addr::LOCALHOST]
+                    .map(|ip| RData::AAAA(rdata::AAAA(ip)))
+                    .to_vec(),
+            ),
+            _ => records(Vec::new()),
+        })
+        .await
+    }
+
+    /// Serve DNS on one port over both transports: UDP answers are truncated and empty,
+    /// TCP answers carry an A record of 127.0.0.7. Counts the UDP queries.
+    async fn truncating_nameserver() -> (u16, Arc<AtomicUsize>) {
+        let udp_queries = Arc::new(AtomicUsize::new(0));
+        let counter = udp_queries.clone();
+        let options = fake_dns::Options { tcp: true, truncate_udp: true, ..Default::default() };
+        let port = fake_dns::serve_with(options, move |question| {
+            if !question.tcp {
+                counter.fetch_add(1, Ordering::Relaxed);
+            }
+            match question.record_type {
+                RecordType::A => records(vec![a([127, 0, 0, 7])]),
+                _ => records(Vec::new()),
+            }
+        })
+        .await;
+        (port, udp_queries)
+    }
+
+    #[tokio::test]
+    async fn test_tcp_fallback() {
+        let (port, udp_queries) = truncating_nameserver().await;
+        let resolvers = parse_resolvers(&[format!("127.0.0.1:{}", port)]).unwrap();
+        let seven: Vec<IpAddr> = vec!["127.0.0.7".parse().unwrap()];
+
+        // A truncated UDP answer is repeated over TCP
+        let scanner = Scanner::with_resolvers(&resolvers, 10).await.unwrap();
+        assert_eq!(scanner.resolve("big.example.test").await, seven);
+        assert!(udp_queries.load(Ordering::Relaxed) > 0);
+
+        let before = udp_queries.load(Ordering::Relaxed);
+        let scanner = Scanner::with_resolvers(&resolvers, 10)
+            .await
+            .unwrap()
+            .with_tcp_only(true)
+            .with_wildcard_filter(false);
+        assert_eq!(scanner.resolve("other.example.test").await, seven);
+        let (results, _) = scanner.scan_domains("example.test", vec!["www.example.test".to_string()]).await.unwrap();
+        assert_eq!(results[0].ips, seven);
+        assert_eq!(udp_queries.load(Ordering::Relaxed), before);
+    }
+
+    /// Serve example.test with a wildcard only on its `dev` subzone: api and real.dev
+    /// have their own addresses, other names under dev get 127.0.0.4, the rest NXDOMAIN.
+    async fn subzone_wildcard_nameserver() -> u16 {
+        fake_dns::serve(|question| {
+            let ip = match question.name.as_str() {
+                "api.example.test." => Some([127, 0, 0, 5]),
+                "real.dev.example.test." => Some([127, 0, 0, 6]),
+                "dev.example.test." => None,
+                name if name.ends_with(".dev.example.test.") => Some([127, 0, 0, 4]),
+                _ => return code(ResponseCode::NXDomain),
+            };
+            match (ip, question.record_type) {
+                (Some(ip), RecordType::A) => records(vec![a(ip)]),
+                _ => records(Vec::new()),
+            }
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_subzone_wildcard() {
+        let port = subzone_wildcard_nameserver().await;
+        let resolvers = parse_resolvers(&[format!("127.0.0.1:{}", port)]).unwrap();
+        let scanner = Scanner::with_resolvers(&resolvers, 10).await.unwrap();
+
+        // The apex has no wildcard, only dev does
+        assert!(scanner.detect_wildcard("example.test").await.is_empty());
+        let candidates = ["api", "real.dev", "junk.dev", "a.b.dev"].map(|name| format!("{}.example.test", name)).to_vec();
+        let (results, stats) = scanner.scan_domains("example.test", candidates).await.unwrap();
+        let mut found: Vec<&str> = results.iter().map(|r| r.subdomain.as_str()).collect();
+        found.sort();
+        assert_eq!(found, vec!["api.example.test", "real.dev.example.test"]);
+        assert_eq!(stats.wildcards, 2);
+
+        let scanner = scanner.with_wildcard_filter(false);
+        let (results, _) = scanner.scan_domains("example.test", vec!["junk.dev.example.test".to_string()]).await.unwrap();
+        assert_eq!(results.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_trusted_verifier() {
+        // The scan resolver answers everything; the trusted one only knows api
+        let scan = wildcard_nameserver().await;
+        let trusted = subzone_wildcard_nameserver().await;
+        let resolvers = parse_resolvers(&[format!("127.0.0.1:{}", scan)]).unwrap();
+        let trusted = parse_resolvers(&[format!("127.0.0.1:{}", trusted)]).unwrap();
+        let scanner = Scanner::with_resolvers(&resolvers, 10)
+            .await
+            .unwrap()
+            .with_wildcard_filter(false)
+            .with_trusted_verifier(&trusted);
+
+        let candidates = vec!["api.example.test".to_string(), "ghost.example.test".to_string()];
+        let (results, stats) = scanner.scan_domains("example.test", candidates).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].subdomain, "api.example.test");
+        assert_eq!(results[0].ips, vec!["127.0.0.5".parse::<IpAddr>().unwrap()]);
+        assert_eq!((stats.valid, stats.unverified), (1, 1));
+
+        let scanner = scanner.with_trusted_verifier(&[]);
+        let (results, _) = scanner.scan_domains("example.test", vec!["ghost.example.test".to_string()]).await.unwrap();
+        assert_eq!(results.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_trusted_verifier_other_types() {
+        // mail.example.test only has MX and TXT records, which the trusted resolver lacks
+        let scan = parse_resolvers(&[format!("127.0.0.1:{}", mail_nameserver().await)]).unwrap();
+        let trusted = parse_resolvers(&[format!("127.0.0.1:{}", health_nameserver(false).await)]).unwrap();
+        let scanner = Scanner::with_resolvers(&scan, 10)
+            .await
+            .unwrap()
+            .with_record_types(vec![RecordType::A, RecordType::MX])
+            .with_wildcard_filter(false)
+            .with_trusted_verifier(&trusted);
+        let (results, stats) = scanner.scan_domains("example.test", vec!["mail.example.test".to_string()]).await.unwrap();
+        assert!(results.is_empty());
+        assert_eq!((stats.valid, stats.unverified), (0, 1));
+
+        let scanner = scanner.with_trusted_verifier(&scan);
+        let (results, _) = scanner.scan_domains("example.test", vec!["mail.example.test".to_string()]).await.unwrap();
+        assert_eq!(results[0].record_types, vec!["MX".to_string()]);
+    }
+
+    /// Serve DNS answering every A query with 127.0.0.2 after a 300ms delay, each
+    /// query in its own task so slow answers overlap.
+    async fn slow_nameserver() -> u16 {
+        let options = fake_dns::Options { delay: Duration::from_millis(300), ..Default::default() };
+        fake_dns::serve_with(options, |question| match question.record_type {
+            RecordType::A => records(vec![a([127, 0, 0, 2])]),
+            _ => records(Vec::new()),
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_slow_answers_overlap() {
+        let port = slow_nameserver().await;
+        let resolvers = parse_resolvers(&[format!("127.0.0.1:{}", port)]).unwrap();
+        let scanner = Scanner::with_resolvers(&resolvers, 20)
+            .await
+            .unwrap()
+            .with_timing(Duration::from_secs(2), Duration::ZERO)
+            .with_wildcard_filter(false);
+        let candidates: Vec<String> = (0..20).map(|i| format!("host{}.example.test", i)).collect();
+
+        // One at a time, 20 lookups of 300ms each would take at least 6s
+        let started = Instant::now();
+        let (results, _) = scanner.scan_domains("example.test", candidates).await.unwrap();
+        assert_eq!(results.len(), 20);
+        assert!(started.elapsed() < Duration::from_secs(3), "took {:?}", started.elapsed());
+    }
+
+    /// Serve DNS where `takeover.example.test` is a CNAME to the deleted `gone.github.io`
+    /// and `docs.example.test` a CNAME to the unlisted `gone.example.net`: A queries get the
+    /// CNAME with NXDOMAIN, CNAME queries the record itself. Every other name is NXDOMAIN.
+    async fn dangling_nameserver() -> u16 {
+        fake_dns::serve(|question| {
+            let target = match question.name.as_str() {
+                "takeover.example.test." => "gone.github.io.",
+                "docs.example.test." => "gone.example.net.",
+                _ => return code(ResponseCode::NXDomain),
+            };
+            let cname = vec![RData::CNAME(rdata::CNAME(Name::from_ascii(target).unwrap()))];
+            match question.record_type {
+                RecordType::CNAME => records(cname),
+                _ => Some((ResponseCode::NXDomain, cname)),
+            }
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_dangling_cname() {
+        let port = dangling_nameserver().await;
+        let resolvers = parse_resolvers(&[format!("127.0.0.1:{}", port)]).unwrap();
+        let candidates = vec![
+            "takeover.example.test".to_string(),
+            "docs.example.test".to_string(),
+            "missing.example.test".to_string(),
+        ];
+
+        let scanner = Scanner::with_resolvers(&resolvers, 10).await.unwrap().with_wildcard_filter(false);
+        let scanner = scanner.with_takeover_check(crate::takeover::cname_suffixes());
+        let (results, stats) = scanner.scan_domains("example.test", candidates.clone()).await.unwrap();
+        assert_eq!((stats.dangling, stats.invalid, stats.valid), (1, 2, 0));
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].subdomain, "takeover.example.test");
+        assert_eq!(results[0].cname.as_deref(), Some("gone.github.io"));
+        assert!(results[0].dangling);
+        assert_eq!(results[0].status, ResultStatus::Dangling);
+
+        // Without the check these are plain NXDOMAINs
+        let scanner = scanner.with_takeover_check(Vec::new());
+        let (results, stats) = scanner.scan_domains("example.test", candidates).await.unwrap();
+        assert!(results.is_empty());
+        assert_eq!((stats.dangling, stats.invalid), (0, 3));
+    }
+
+    #[test]
+    fn test_parse_fingerprints() {
+        assert_eq!(parse_fingerprints("# mine\nGitHub.io\n\n  example.net \n"), vec!["github.io", "example.net"]);
+    }
+
+    #[tokio::test]
+    async fn test_wildcard_names_are_filtered() {
+        let port = wildcard_nameserver().await;
+        let group = NameServerConfigGroup::from_ips_clear(&["127.0.0.1".parse().unwrap()], port, true);
+        let mut scanner = Scanner::new(10).await.unwrap().with_wildcard_probes(2);
+        scanner.resolver = TokioAsyncResolver::tokio(ResolverConfig::from_parts(None, vec![], group), ResolverOpts::default());
+
+        let wildcard = scanner.detect_wildcard("example.test").await;
+        assert_eq!(wildcard, vec!["127.0.0.2".parse::<IpAddr>().unwrap()]);
+
+        let candidates = vec!["www.example.test".to_string(), "real.example.test".to_string(), "admin.example.test".to_string()];
+        let (results, stats) = scanner.scan_domains("example.test", candidates.clone()).await.unwrap();
+        let names: Vec<&str> = results.iter().map(|r| r.subdomain.as_str()).collect();
+        assert_eq!(names, vec!["real.example.test"]);
+        assert_eq!((stats.valid, stats.wildcards), (1, 2));
+
+        let scanner = scanner.with_wildcard_filter(false);
+        let (results, _) = scanner.scan_domains("example.test", candidates).await.unwrap();
+        assert_eq!(results.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_parse_resolvers() {
+        let entries = |list: &[&str]| list.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            parse_resolvers(&entries(&["8.8.8.8", " 2606:4700:4700::1111 ", "127.0.0.1:5353", "[::1]:5353"])).unwrap(),
+            vec![
+                "8.8.8.8:53".parse::<SocketAddr>().unwrap(),
+                "[2606:4700:4700::1111]:53".parse().unwrap(),
+                "127.0.0.1:5353".parse().unwrap(),
+                "[::1]:5353".parse().unwrap(),
+            ]
+        );
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"# public\n1.1.1.1\n\n9.9.9.9\n").unwrap();
+        let path = file.path().to_str().unwrap().to_string();
+        assert_eq!(parse_resolvers(&entries(&[&path])).unwrap().len(), 2);
+
+        let error = parse_resolvers(&entries(&["8.8.8.8", "8.8.8.888"])).unwrap_err();
+        assert_eq!(error.to_string(), "Resolver configuration error: invalid resolver address '8.8.8.888'");
+        assert!(parse_resolvers(&entries(&["1.1.1.1:99999"])).is_err());
+        assert!(parse_resolvers(&entries(&["dns.google"])).is_err());
+
+        std::io::Write::write_all(&mut file, b"not-an-ip\n").unwrap();
+        let error = parse_resolvers(&entries(&[&path])).unwrap_err();
+        assert!(error.to_string().contains(&format!("'not-an-ip' ({}:5)", path)));
+
+        let resolvers = parse_resolvers(&entries(&["127.0.0.1", "127.0.0.1:5353", "127.0.0.2"])).unwrap();
+        let scanner = Scanner::with_resolvers(&resolvers, 10).await.unwrap();
+        assert_eq!(scanner.shard_resolvers.len(), 3);
+        assert!(Scanner::with_resolvers(&[], 10).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_custom_resolver_scans() {
+        let port = wildcard_nameserver().await;
+        let resolvers = parse_resolvers(&[format!("127.0.0.1:{}", port)]).unwrap();
+        let scanner = Scanner::with_resolvers(&resolvers, 10)
+            .await
+            .unwrap()
+            .with_wildcard_filter(false);
+        let (results, _) = scanner.scan_domains("example.test", vec!["real.example.test".to_string()]).await.unwrap();
+        assert_eq!(results[0].ips, vec!["127.0.0.3".parse::<IpAddr>().unwrap()]);
+        assert_eq!(results[0].source, Source::Wordlist);
+
+        // Both families, in a fixed order regardless of how the answers arrived
+        let (status, result) = scanner.check_subdomain(&scanner.resolver, "dual.example.test").await;
+        let expected: Vec<IpAddr> = ["127.0.0.2", "::1", "::2"].iter().map(|ip| ip.parse().unwrap()).collect();
+        assert_eq!((status, result.ips), (ScanStatus::Valid, expected));
+
+        let candidates = vec![Candidate::new("real.example.test", Source::Wayback)];
+        let (results, _) = scanner.scan_domains("example.test", candidates).await.unwrap();
+        assert_eq!(results[0].source, Source::Wayback);
+    }
+
+    #[tokio::test]
+    async fn test_valid_without_listener() {
+        // 192.0.2.1 (TEST-NET-1) has nothing listening on it, or is not even routed
+        let port = fake_dns::serve(|question| match question.record_type {
+            RecordType::A => records(vec![a([192, 0, 2, 1])]),
+            _ => records(Vec::new()),
+        })
+        .await;
+        let resolvers = parse_resolvers(&[format!("127.0.0.1:{}", port)]).unwrap();
+        let scanner = Scanner::with_resolvers(&resolvers, 10)
+            .await
+            .unwrap()
+            .with_timing(Duration::from_millis(500), Duration::ZERO)
+            .with_wildcard_filter(false);
+
+        let (results, stats) = scanner.scan_domains("example.test", vec!["fw.example.test".to_string()]).await.unwrap();
+        assert_eq!(stats.valid, 1);
+        assert_eq!(results[0].ips, vec!["192.0.2.1".parse::<IpAddr>().unwrap()]);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit() {
+        let port = wildcard_nameserver().await;
+        let resolvers = parse_resolvers(&[format!("127.0.0.1:{}", port)]).unwrap();
+        let scanner = Scanner::with_resolvers(&resolvers, 50)
+            .await
+            .unwrap()
+            .with_wildcard_filter(false)
+            .with_rate(Some(20));
+        let candidates: Vec<String> = (0..11).map(|i| format!("host{}.example.test", i)).collect();
+
+        // The first lookup starts at once, the other ten 50ms apart
+        let started = Instant::now();
+        let (results, _) = scanner.scan_domains("example.test", candidates).await.unwrap();
+        assert_eq!(results.len(), 11);
+        assert!(started.elapsed() >= Duration::from_millis(500), "took {:?}", started.elapsed());
+    }
+
+    /// Serve DNS answering every A query with 127.0.0.2, recording each queried name.
+    async fn recording_nameserver() -> (u16, Arc<Mutex<Vec<String>>>) {
+        let queried = Arc::new(Mutex::new(Vec::new()));
+        let log = queried.clone();
+        let port = fake_dns::serve(move |question| match question.record_type {
+            RecordType::A => {
+                log.lock().unwrap().push(question.name.trim_end_matches('.').to_string());
+                records(vec![a([127, 0, 0, 2])])
+            }
+            _ => records(Vec::new()),
+        })
+        .await;
+        (port, queried)
+    }
+
+    #[tokio::test]
+    async fn test_answer_cache() {
+        let (first, first_queried) = recording_nameserver().await;
+        let (second, second_queried) = recording_nameserver().await;
+        let resolvers = parse_resolvers(&[format!("127.0.0.1:{}", first), format!("127.0.0.1:{}", second)]).unwrap();
+        let scanner = Scanner::with_resolvers(&resolvers, 10)
+            .await
+            .unwrap()
+            .with_round_robin(true)
+            .with_wildcard_filter(false);
+
+        // Found by several sources in one pass, then proposed again by a later pass
+        let candidates = vec![
+            Candidate::new("www.example.test", Source::Wordlist),
+            Candidate::new("www.example.test", Source::Wayback),
+            Candidate::new("WWW.example.test", Source::Crtsh),
+            Candidate::new("api.example.test", Source::Wordlist),
+        ];
+        let (results, _) = scanner.scan_domains("example.test", candidates).await.unwrap();
+        assert_eq!(results.len(), 4);
+        scanner.scan_domains("example.test", vec!["www.example.test".to_string()]).await.unwrap();
+        assert_eq!(scanner.resolve("api.example.test").await, vec!["127.0.0.2".parse::<IpAddr>().unwrap()]);
+
+        let mut queried: Vec<String> = first_queried.lock().unwrap().clone();
+        queried.extend(second_queried.lock().unwrap().iter().cloned());
+        let mut queried: Vec<String> = queried.iter().map(|name| name.to_lowercase()).collect();
+        queried.sort();
+        assert_eq!(queried, vec!["api.example.test", "www.example.test"]);
+    }
+
+    #[tokio::test]
+    async fn test_round_robin() {
+        let (first, first_queried) = recording_nameserver().await;
+        let (second, second_queried) = recording_nameserver().await;
+        let resolvers = parse_resolvers(&[format!("127.0.0.1:{}", first), format!("127.0.0.1:{}", second)]).unwrap();
+        let scanner = Scanner::with_resolvers(&resolvers, 10)
+            .await
+            .unwrap()
+            .with_round_robin(true)
+            .with_wildcard_filter(false);
+
+        let candidates: Vec<String> = (0..10).map(|i| format!("host{}.example.test", i)).collect();
+        let (results, _) = scanner.scan_domains("example.test", candidates).await.unwrap();
+        assert_eq!(results.len(), 10);
+        assert_eq!((first_queried.lock().unwrap().len(), second_queried.lock().unwrap().len()), (5, 5));
+        assert!(first_queried.lock().unwrap().contains(&"host0.example.test".to_string()));
+        assert!(second_queried.lock().unwrap().contains(&"host1.example.test".to_string()));
+
+        // Wildcard probes take turns through the pool as well
+        first_queried.lock().unwrap().clear();
+        second_queried.lock().unwrap().clear();
+        let scanner = scanner.with_wildcard_probes(4);
+        assert_eq!(scanner.detect_wildcard("example.test").await, vec!["127.0.0.2".parse::<IpAddr>().unwrap()]);
+        assert_eq!((first_queried.lock().unwrap().len(), second_queried.lock().unwrap().len()), (2, 2));
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_resume() {
+        let (port, queried) = recording_nameserver().await;
+        let resolvers = parse_resolvers(&[format!("127.0.0.1:{}", port)]).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scan.checkpoint");
+        let scanner = || async {
+            Scanner::with_resolvers(&resolvers, 10)
+                .await
+                .unwrap()
+                .with_wildcard_filter(false)
+                .with_checkpoint(Some(ScanCheckpoint::open(&path, 0).unwrap()))
+        };
+
+        // An interrupted run that only got through two names
+        let first = scanner().await;
+        let names = |labels: &[&str]| labels.iter().map(|l| format!("{}.example.test", l)).collect::<Vec<_>>();
+        first.scan_domains("example.test", names(&["a", "b"])).await.unwrap();
+        drop(first);
+        queried.lock().unwrap().clear();
+
+        // The wordlist has since lost `a` and gained `c`
+        let resumed = scanner().await;
+        let (results, stats) = resumed.scan_domains("example.test", names(&["b", "c"])).await.unwrap();
+        assert_eq!(*queried.lock().unwrap(), vec!["c.example.test"]);
+        let found: Vec<&str> = results.iter().map(|r| r.subdomain.as_str()).collect();
+        assert_eq!(found, vec!["b.example.test", "c.example.test"]);
+        assert_eq!(results[0].ips, vec!["127.0.0.2".parse::<IpAddr>().unwrap()]);
+        assert_eq!((stats.valid, stats.total), (2, 2));
+
+        resumed.finish_checkpoint().unwrap();
+        assert!(!path.exists());
+    }
+
+    /// Serve DNS that drops the first query of every name and type, or answers it with
+    /// SERVFAIL when `servfail` is set, then answers: `www.*` has an A record,
+    /// everything else is NXDOMAIN. Logs every A query.
+    async fn flaky_nameserver(servfail: bool) -> (u16, Arc<Mutex<Vec<String>>>) {
+        let queried = Arc::new(Mutex::new(Vec::new()));
+        let log = queried.clone();
+        let mut seen = std::collections::HashSet::new();
+        let port = fake_dns::serve(move |question| {
+            if question.record_type == RecordType::A {
+                log.lock().unwrap().push(question.name.trim_end_matches('.').to_string());
+            }
+            if seen.insert((question.name.clone(), question.record_type)) {
+                return servfail.then(|| (ResponseCode::ServFail, Vec::new()));
+            }
+            match question.record_type {
+                _ if !question.name.starts_with("www.") => code(ResponseCode::NXDomain),
+                RecordType::A => records(vec![a([127, 0, 0, 2])]),
+                _ => records(Vec::new()),
+            }
+        })
+        .await;
+        (port, queried)
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_backoff() {
+        let (port, queried) = flaky_nameserver(false).await;
+        let resolvers = parse_resolvers(&[format!("127.0.0.1:{}", port)]).unwrap();
+        let scanner = Scanner::with_resolvers(&resolvers, 10)
+            .await
+            .unwrap()
+            .with_timing(Duration::from_millis(200), Duration::ZERO)
+            .with_retries(2)
+            .with_retry_delay(Duration::from_millis(20))
+            .with_wildcard_filter(false);
+        // The scanner retries, so each resolver lookup is a single attempt
+        assert_eq!(scanner.lookup_deadline(), Duration::from_millis(200));
+
+        let candidates = vec!["www.example.test".to_string(), "gone.example.test".to_string()];
+        let (results, stats) = scanner.scan_domains("example.test", candidates).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].subdomain, "www.example.test");
+        assert_eq!((stats.valid, stats.invalid, stats.timed_out), (1, 1, 0));
+
+        // The dropped first query is retried; the NXDOMAIN answer to the second is not
+        let gone = queried.lock().unwrap().iter().filter(|name| name.starts_with("gone.")).count();
+        assert_eq!(gone, 2);
+    }
+
+    #[tokio::test]
+    async fn test_retry_servfail() {
+        let (port, queried) = flaky_nameserver(true).await;
+        let resolvers = parse_resolvers(&[format!("127.0.0.1:{}", port)]).unwrap();
+        let scanner = Scanner::with_resolvers(&resolvers, 10)
+            .await
+            .unwrap()
+            .with_retries(1)
+            .with_retry_delay(Duration::from_millis(20))
+            .with_wildcard_filter(false);
+
+        // SERVFAIL says nothing about the name, so it is retried rather than taken as NODATA
+        let (results, stats) = scanner.scan_domains("example.test", vec!["www.example.test".to_string()]).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!((stats.valid, stats.errors), (1, 0));
+        assert_eq!(queried.lock().unwrap().len(), 2);
+
+        let failing = Scanner::with_resolvers(&resolvers, 10)
+            .await
+            .unwrap()
+            .with_retries(0)
+            .with_retry_delay(Duration::from_millis(20))
+            .with_wildcard_filter(false);
+        let (_, stats) = failing.scan_domains("example.test", vec!["www.other.test".to_string()]).await.unwrap();
+        assert_eq!((stats.invalid, stats.servfail, stats.errors), (1, 1, 0));
+    }
+
+    #[test]
+    fn test_failure_reasons_checkpoint() {
+        for reason in [InvalidReason::ServFail, InvalidReason::Refused, InvalidReason::TimedOut] {
+            assert_eq!(ScanStatus::Invalid(reason).checkpoint_label(), None);
+        }
+        for status in [ScanStatus::Invalid(InvalidReason::NxDomain), ScanStatus::Wildcard] {
+            let entry = CheckpointEntry { status: status.checkpoint_label().unwrap().to_string(), detail: None };
+            let (restored, _) = ScanStatus::from_checkpoint("www.example.test", &entry).unwrap();
+            assert_eq!(restored, status);
+        }
+        // Entries written before transient outcomes were left out are scanned again.
+        let entry = CheckpointEntry { status: "timed_out".to_string(), detail: None };
+        assert!(ScanStatus::from_checkpoint("www.example.test", &entry).is_none());
+        assert_eq!(InvalidReason::ServFail.to_string(), "SERVFAIL");
+        assert_eq!(InvalidReason::Refused.to_string(), "REFUSED");
+    }
+
+    #[test]
+    fn test_jittered() {
+        assert_eq!(jittered(Duration::ZERO), Duration::ZERO);
+        for _ in 0..100 {
+            let delay = jittered(Duration::from_millis(100));
+            assert!((Duration::from_millis(50)..=Duration::from_millis(100)).contains(&delay));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_covers_retries() {
+        let (port, queried) = flaky_nameserver(false).await;
+        let resolvers = parse_resolvers(&[format!("127.0.0.1:{}", port)]).unwrap();
+        let scanner = Scanner::with_resolvers(&resolvers, 10)
+            .await
+            .unwrap()
+            .with_timing(Duration::from_millis(200), Duration::ZERO)
+            .with_retries(1)
+            .with_rate(Some(2))
+            .with_wildcard_filter(false);
+
+        // Both first queries are dropped, so four lookups share the 500ms slots
+        let started = Instant::now();
+        let candidates = vec!["www.example.test".to_string(), "gone.example.test".to_string()];
+        let (results, _) = scanner.scan_domains("example.test", candidates).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(queried.lock().unwrap().len(), 4);
+        assert!(started.elapsed() >= Duration::from_millis(1400), "took {:?}", started.elapsed());
+    }
+
+    #[tokio::test]
+    async fn test_blackhole_resolver_times_out() {
+        let blackhole = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let scanner = Scanner::with_resolvers(&[blackhole.local_addr().unwrap()], 10)
+            .await
+            .unwrap()
+            .with_timing(Duration::from_millis(200), Duration::ZERO)
+            .with_retries(1)
+            .with_wildcard_filter(false);
+        assert_eq!(scanner.lookup_deadline(), Duration::from_millis(400));
+
+        let started = Instant::now();
+        let (results, stats) = scanner.scan_domains("example.com", vec!["www.example.com".to_string()]).await.unwrap();
+        assert!(results.is_empty());
+        assert_eq!((stats.invalid, stats.timed_out, stats.errors), (1, 1, 0));
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,242 @@
+use crate::console::progress_bar;
+use crate::scanner::ScanResult;
+use colored::*;
+use futures::stream::{self, StreamExt};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::process::Command;
+
+/// Browser executables looked up on `PATH` when none is given.
+pub const BROWSER_NAMES: &[&str] = &["chromium", "chromium-browser", "google-chrome", "google-chrome-stable", "chrome"];
+
+/// Bound for one page load and capture, browser startup included.
+pub const DEFAULT_SCREENSHOT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Viewport of every capture.
+const WINDOW_SIZE: &str = "1280,800";
+
+/// Browsers started at once; each is a full Chrome process, so this stays well below
+/// the DNS concurrency.
+const MAX_BROWSERS: usize = 4;
+
+#[derive(Debug)]
+pub enum ScreenshotError {
+    BrowserNotFound,
+    IoError(PathBuf, String),
+    CaptureFailed(String, String),
+}
+
+impl std::fmt::Display for ScreenshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScreenshotError::BrowserNotFound => {
+                write!(f, "No Chrome or Chromium found on PATH ({}), see --browser", BROWSER_NAMES.join(", "))
+            }
+            ScreenshotError::IoError(path, e) => write!(f, "Cannot write screenshots to {}: {}", path.display(), e),
+            ScreenshotError::CaptureFailed(url, e) => write!(f, "No screenshot of {}: {}", url, e),
+        }
+    }
+}
+
+impl Error for ScreenshotError {}
+
+/// Captures live web pages with a headless Chrome, one browser process per page.
+pub struct Screenshotter {
+    browser: PathBuf,
+    dir: PathBuf,
+    timeout: Duration,
+    concurrency: usize,
+}
+
+impl Screenshotter {
+    /// Save captures under `dir`, created if missing, using `browser` or the first of
+    /// `BROWSER_NAMES` found on `PATH`.
+    pub fn new(dir: &Path, browser: Option<PathBuf>, concurrency: usize) -> Result<Self, ScreenshotError> {
+        let browser = match browser {
+            Some(browser) => browser,
+            None => find_browser(&std::env::var_os("PATH").unwrap_or_default()).ok_or(ScreenshotError::BrowserNotFound)?,
+        };
+        std::fs::create_dir_all(dir).map_err(|e| ScreenshotError::IoError(dir.to_path_buf(), e.to_string()))?;
+        Ok(Screenshotter {
+            browser,
+            dir: dir.to_path_buf(),
+            timeout: DEFAULT_SCREENSHOT_TIMEOUT,
+            concurrency: concurrency.clamp(1, MAX_BROWSERS),
+        })
+    }
+
+    /// Capture `url` into `path`. Each browser runs without the Chrome sandbox, which
+    /// needs privileges containers and root sessions usually lack, and gets a fresh
+    /// profile directory so concurrent captures do not fight over one profile lock.
+    pub async fn capture(&self, url: &str, path: &Path) -> Result<(), ScreenshotError> {
+        let failed = |e: String| ScreenshotError::CaptureFailed(url.to_string(), e);
+        let profile = std::env::temp_dir().join(format!("subtahu-chrome-{:016x}", rand::random::<u64>()));
+        std::fs::create_dir(&profile).map_err(|e| ScreenshotError::IoError(profile.clone(), e.to_string()))?;
+
+        let mut user_data_dir = std::ffi::OsString::from("--user-data-dir=");
+        user_data_dir.push(&profile);
+        let mut screenshot = std::ffi::OsString::from("--screenshot=");
+        screenshot.push(path);
+        let mut command = Command::new(&self.browser);
+        command
+            .args(["--headless", "--no-sandbox", "--disable-gpu", "--hide-scrollbars", "--ignore-certificate-errors"])
+            .arg(format!("--window-size={}", WINDOW_SIZE))
+            .arg(user_data_dir)
+            .arg(screenshot)
+            .arg(url)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true);
+        let outcome = tokio::time::timeout(self.timeout, command.output()).await;
+        let _ = std::fs::remove_dir_all(&profile);
+
+        let output = match outcome {
+            Ok(Ok(output)) => output,
+            Ok(Err(e)) => return Err(failed(format!("cannot run {}: {}", self.browser.display(), e))),
+            Err(_) => return Err(failed(format!("timed out after {}s", self.timeout.as_secs()))),
+        };
+        if output.status.success() && path.is_file() {
+            return Ok(());
+        }
+        // Chrome logs a lot; its last line is usually the reason
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().map(str::trim).rev().find(|line| !line.is_empty()).unwrap_or("no output");
+        if output.status.success() {
+            Err(failed(format!("browser wrote no file ({})", reason)))
+        } else {
+            Err(failed(format!("browser {} ({})", output.status, reason)))
+        }
+    }
+
+    /// Capture every result that answered `--probe`, preferring its HTTPS page, and
+    /// store the file path in its `screenshot` field. Files are named after the
+    /// subdomain (`www.example.com.png`).
+    pub async fn capture_all(&self, results: &mut [ScanResult]) {
+        let targets: Vec<(usize, String)> = results
+            .iter()
+            .enumerate()
+            .filter_map(|(index, result)| {
+                let probe = result.probe.as_ref()?;
+                let response = probe.https.as_ref().or(probe.http.as_ref())?;
+                Some((index, response.final_url.clone()))
+            })
+            .collect();
+        if targets.is_empty() {
+            return;
+        }
+        say!("{} Capturing {} live pages into {}", "[*]".blue(), targets.len(), self.dir.display());
+        let progress = progress_bar(targets.len() as u64, "Capturing screenshots...");
+
+        let captured: Vec<(usize, PathBuf)> = stream::iter(targets)
+            .map(|(index, url)| {
+                let progress = progress.clone();
+                let path = self.dir.join(screenshot_file_name(&results[index].subdomain));
+                async move {
+                    let captured = self.capture(&url, &path).await;
+                    progress.inc(1);
+                    match captured {
+                        Ok(()) => Some((index, path)),
+                        Err(e) => {
+                            progress.suspend(|| eprintln!("{} {}", "[!]".red(), e));
+                            None
+                        }
+                    }
+                }
+            })
+            .buffer_unordered(self.concurrency)
+            .filter_map(|captured| async move { captured })
+            .collect()
+            .await;
+        progress.finish_with_message("screenshots completed");
+
+        say!("{} {} screenshots saved", "[+]".green(), captured.len());
+        for (index, path) in captured {
+            results[index].screenshot = Some(path.display().to_string());
+        }
+    }
+}
+
+/// The first of `BROWSER_NAMES` that is a file in one of the `path` directories.
+pub fn find_browser(path: &std::ffi::OsStr) -> Option<PathBuf> {
+    let dirs: Vec<PathBuf> = std::env::split_paths(path).collect();
+    BROWSER_NAMES.iter().find_map(|name| dirs.iter().map(|dir| dir.join(name)).find(|candidate| candidate.is_file()))
+}
+
+/// `<subdomain>.png`, with anything but letters, digits, `.`, `-` and `_` replaced,
+/// so Unicode names or stray characters never leave the directory.
+pub fn screenshot_file_name(subdomain: &str) -> String {
+    let name: String = subdomain
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') { c } else { '_' })
+        .collect();
+    format!("{}.png", name.trim_start_matches('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::probe::{HttpResponse, ProbeResult};
+
+    #[test]
+    fn test_screenshot_file_name() {
+        assert_eq!(screenshot_file_name("www.example.com"), "www.example.com.png");
+        assert_eq!(screenshot_file_name("../café.example.com"), "_caf_.example.com.png");
+    }
+
+    /// A stand-in browser that writes its `--screenshot=` argument's file, provided it
+    /// was started without the sandbox and with an existing profile directory. URLs
+    /// containing `broken` fail with a message on stderr.
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_capture_all() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let browser = dir.path().join("chromium");
+        std::fs::write(
+            &browser,
+            concat!(
+                "#!/bin/sh\n",
+                "for arg; do case \"$arg\" in\n",
+                "  --no-sandbox) unsandboxed=1;;\n",
+                "  --user-data-dir=*) profile=\"${arg#--user-data-dir=}\";;\n",
+                "  --screenshot=*) out=\"${arg#--screenshot=}\";;\n",
+                "  *broken*) echo 'ERROR: net::ERR_NAME_NOT_RESOLVED' >&2; exit 1;;\n",
+                "esac; done\n",
+                "[ -n \"$unsandboxed\" ] && [ -d \"$profile\" ] || exit 2\n",
+                "echo png > \"$out\"\n",
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&browser, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(find_browser(dir.path().as_os_str()), Some(browser.clone()));
+        assert_eq!(find_browser(std::ffi::OsStr::new("")), None);
+
+        let live = |subdomain: &str, url: &str| ScanResult {
+            subdomain: subdomain.to_string(),
+            probe: Some(ProbeResult {
+                https: Some(HttpResponse { status: 200, final_url: url.to_string(), title: None, content_length: None }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut results = vec![
+            live("www.example.com", "https://www.example.com/"),
+            live("broken.example.com", "https://broken.example.com/"),
+            ScanResult { subdomain: "mail.example.com".to_string(), ..Default::default() },
+        ];
+        let shots = dir.path().join("shots");
+        let screenshotter = Screenshotter::new(&shots, Some(browser), 4).unwrap();
+        screenshotter.capture_all(&mut results).await;
+
+        let expected = shots.join("www.example.com.png");
+        assert_eq!(results[0].screenshot, Some(expected.display().to_string()));
+        assert!(expected.is_file());
+        assert_eq!((&results[1].screenshot, &results[2].screenshot), (&None, &None));
+
+        let error = screenshotter.capture("https://broken.example.com/", &shots.join("broken.png")).await.unwrap_err();
+        assert!(error.to_string().contains("ERR_NAME_NOT_RESOLVED"), "{}", error);
+    }
+}
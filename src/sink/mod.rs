@@ -0,0 +1,282 @@
+use crate::scanner::{ScanResult, ScanStats};
+use futures::future::BoxFuture;
+use serde::Serialize;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+
+#[derive(Debug)]
+pub enum SinkError {
+    IoError(PathBuf, String),
+    WebhookError(String),
+}
+
+impl std::fmt::Display for SinkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SinkError::IoError(path, e) => write!(f, "Sink {}: {}", path.display(), e),
+            SinkError::WebhookError(e) => write!(f, "Webhook error: {}", e),
+        }
+    }
+}
+
+impl Error for SinkError {}
+
+/// A destination that receives findings while a scan is still running.
+///
+/// `emit` is called from the scan loop once per valid or dangling subdomain, as soon as
+/// it has been resolved. Fields filled in later (ports, probe results, takeover checks,
+/// flags) follow in one `update` per kept finding, once its target's pipeline has run.
+/// `finish` is called once after every target has been scanned, with the combined
+/// stats.
+pub trait ResultSink: Send {
+    fn emit(&mut self, result: &ScanResult) -> Result<(), SinkError>;
+
+    /// The final version of a finding passed to `emit` earlier. Ignored by default.
+    fn update(&mut self, _result: &ScanResult) -> Result<(), SinkError> {
+        Ok(())
+    }
+
+    fn finish<'a>(&'a mut self, stats: &'a ScanStats) -> BoxFuture<'a, Result<(), SinkError>>;
+}
+
+/// What a streamed record says about its finding.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Event {
+    /// Just resolved, with only the fields the scan itself fills in
+    Found,
+    /// The same finding after filtering and enrichment
+    Enriched,
+}
+
+/// A finding tagged with its event: `{"event": "found", "subdomain": ..., ...}`.
+#[derive(Serialize)]
+struct Record<'a> {
+    event: Event,
+    #[serde(flatten)]
+    result: &'a ScanResult,
+}
+
+/// Passes on only the findings `keep` accepts, so names a filter drops from the report
+/// (`--exclude-private`, `--drop-low-ttl`, ...) are never streamed.
+pub struct FilteredSink {
+    inner: Box<dyn ResultSink>,
+    keep: Arc<dyn Fn(&ScanResult) -> bool + Send + Sync>,
+}
+
+impl FilteredSink {
+    pub fn new(inner: Box<dyn ResultSink>, keep: Arc<dyn Fn(&ScanResult) -> bool + Send + Sync>) -> Self {
+        FilteredSink { inner, keep }
+    }
+}
+
+impl ResultSink for FilteredSink {
+    fn emit(&mut self, result: &ScanResult) -> Result<(), SinkError> {
+        match (self.keep)(result) {
+            true => self.inner.emit(result),
+            false => Ok(()),
+        }
+    }
+
+    fn update(&mut self, result: &ScanResult) -> Result<(), SinkError> {
+        match (self.keep)(result) {
+            true => self.inner.update(result),
+            false => Ok(()),
+        }
+    }
+
+    fn finish<'a>(&'a mut self, stats: &'a ScanStats) -> BoxFuture<'a, Result<(), SinkError>> {
+        self.inner.finish(stats)
+    }
+}
+
+/// Writes one JSON object per event to a file (newline-delimited JSON), tagged with
+/// `"event": "found"` or `"event": "enriched"`.
+pub struct NdjsonSink {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl NdjsonSink {
+    pub fn create(path: &Path) -> Result<Self, SinkError> {
+        let file = File::create(path).map_err(|e| SinkError::IoError(path.to_path_buf(), e.to_string()))?;
+        Ok(NdjsonSink {
+            path: path.to_path_buf(),
+            writer: BufWriter::new(file),
+        })
+    }
+
+    fn io_error(&self, e: impl ToString) -> SinkError {
+        SinkError::IoError(self.path.clone(), e.to_string())
+    }
+
+    fn write(&mut self, event: Event, result: &ScanResult) -> Result<(), SinkError> {
+        let line = serde_json::to_string(&Record { event, result }).map_err(|e| self.io_error(e))?;
+        writeln!(self.writer, "{}", line).map_err(|e| self.io_error(e))?;
+        // Flush per line so the file can be tailed while the scan runs
+        self.writer.flush().map_err(|e| self.io_error(e))
+    }
+}
+
+impl ResultSink for NdjsonSink {
+    fn emit(&mut self, result: &ScanResult) -> Result<(), SinkError> {
+        self.write(Event::Found, result)
+    }
+
+    fn update(&mut self, result: &ScanResult) -> Result<(), SinkError> {
+        self.write(Event::Enriched, result)
+    }
+
+    fn finish<'a>(&'a mut self, _stats: &'a ScanStats) -> BoxFuture<'a, Result<(), SinkError>> {
+        Box::pin(async move { self.writer.flush().map_err(|e| self.io_error(e)) })
+    }
+}
+
+/// Prints the bare subdomain of every finding to stdout, for piping into other tools.
+pub struct StdoutSink;
+
+impl ResultSink for StdoutSink {
+    fn emit(&mut self, result: &ScanResult) -> Result<(), SinkError> {
+        let mut stdout = std::io::stdout().lock();
+        writeln!(stdout, "{}", result.subdomain).map_err(|e| SinkError::IoError(PathBuf::from("<stdout>"), e.to_string()))
+    }
+
+    fn finish<'a>(&'a mut self, _stats: &'a ScanStats) -> BoxFuture<'a, Result<(), SinkError>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// POSTs events to a webhook in batches of `batch_size` as `{"events": [...]}`, each
+/// tagged like an NDJSON line. The final request also carries the scan `stats`.
+pub struct WebhookSink {
+    client: reqwest::Client,
+    url: String,
+    batch_size: usize,
+    pending: Vec<serde_json::Value>,
+    in_flight: Vec<JoinHandle<Result<(), SinkError>>>,
+}
+
+impl WebhookSink {
+    pub fn new(url: &str, batch_size: usize) -> Self {
+        WebhookSink {
+            client: reqwest::Client::new(),
+            url: url.to_string(),
+            batch_size: batch_size.max(1),
+            pending: Vec::new(),
+            in_flight: Vec::new(),
+        }
+    }
+
+    fn post(&self, body: serde_json::Value) -> JoinHandle<Result<(), SinkError>> {
+        let request = self.client.post(&self.url).json(&body);
+        tokio::spawn(async move {
+            let response = request.send().await.map_err(|e| SinkError::WebhookError(e.to_string()))?;
+            if !response.status().is_success() {
+                return Err(SinkError::WebhookError(format!("HTTP {}", response.status())));
+            }
+            Ok(())
+        })
+    }
+
+    fn push(&mut self, event: Event, result: &ScanResult) -> Result<(), SinkError> {
+        let record = serde_json::to_value(Record { event, result }).map_err(|e| SinkError::WebhookError(e.to_string()))?;
+        self.pending.push(record);
+        if self.pending.len() >= self.batch_size {
+            let batch = std::mem::take(&mut self.pending);
+            let handle = self.post(serde_json::json!({ "events": batch }));
+            self.in_flight.push(handle);
+        }
+        Ok(())
+    }
+}
+
+impl ResultSink for WebhookSink {
+    fn emit(&mut self, result: &ScanResult) -> Result<(), SinkError> {
+        self.push(Event::Found, result)
+    }
+
+    fn update(&mut self, result: &ScanResult) -> Result<(), SinkError> {
+        self.push(Event::Enriched, result)
+    }
+
+    fn finish<'a>(&'a mut self, stats: &'a ScanStats) -> BoxFuture<'a, Result<(), SinkError>> {
+        Box::pin(async move {
+            let batch = std::mem::take(&mut self.pending);
+            let last = self.post(serde_json::json!({ "events": batch, "stats": stats }));
+            self.in_flight.push(last);
+
+            let mut first_error = None;
+            for handle in self.in_flight.drain(..) {
+                let outcome = handle.await.map_err(|e| SinkError::WebhookError(e.to_string())).and_then(|r| r);
+                if let (Err(e), None) = (outcome, &first_error) {
+                    first_error = Some(e);
+                }
+            }
+            first_error.map_or(Ok(()), Err)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_ndjson_sink() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("findings.ndjson");
+        let mut sink = NdjsonSink::create(&path).unwrap();
+
+        for name in ["www.example.com", "api.example.com"] {
+            let result = ScanResult {
+                subdomain: name.to_string(),
+                ips: vec!["10.0.0.1".parse().unwrap()],
+                ..Default::default()
+            };
+            sink.emit(&result).unwrap();
+        }
+        let enriched = ScanResult {
+            subdomain: "www.example.com".to_string(),
+            open_ports: vec![443],
+            ..Default::default()
+        };
+        sink.update(&enriched).unwrap();
+        sink.finish(&ScanStats::default()).await.unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = content.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1]["event"], "found");
+        assert_eq!(lines[1]["subdomain"], "api.example.com");
+        assert_eq!(lines[1]["ips"][0], "10.0.0.1");
+        assert_eq!(lines[2]["event"], "enriched");
+        assert_eq!(lines[2]["subdomain"], "www.example.com");
+        assert_eq!(lines[2]["open_ports"][0], 443);
+    }
+
+    #[test]
+    fn test_filtered_sink() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("findings.ndjson");
+        let inner = Box::new(NdjsonSink::create(&path).unwrap());
+        let mut sink = FilteredSink::new(inner, Arc::new(|result: &ScanResult| result.subdomain.starts_with("www.")));
+
+        for name in ["www.example.com", "api.example.com"] {
+            let result = ScanResult {
+                subdomain: name.to_string(),
+                ..Default::default()
+            };
+            sink.emit(&result).unwrap();
+            sink.update(&result).unwrap();
+        }
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 2);
+        assert!(content.lines().all(|line| line.contains("www.example.com")));
+    }
+}
@@ -0,0 +1,337 @@
+use crate::console::progress_bar;
+use crate::scanner::ScanResult;
+use colored::*;
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::time::Duration;
+
+/// Timeout for the request fetching a page to compare against fingerprints.
+pub const DEFAULT_TAKEOVER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How much of a response body is searched for a fingerprint.
+const BODY_SCAN_BYTES: usize = 64 * 1024;
+
+#[derive(Debug)]
+pub enum TakeoverError {
+    ClientError(String),
+}
+
+impl std::fmt::Display for TakeoverError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TakeoverError::ClientError(e) => write!(f, "Failed to build HTTP client: {}", e),
+        }
+    }
+}
+
+impl Error for TakeoverError {}
+
+/// How easily a finding can be turned into a takeover.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Low => write!(f, "low"),
+            Severity::Medium => write!(f, "medium"),
+            Severity::High => write!(f, "high"),
+        }
+    }
+}
+
+/// A service where a CNAME left pointing at a deleted resource can be claimed by
+/// someone else.
+pub struct Fingerprint {
+    pub service: &'static str,
+    /// CNAME target suffixes belonging to the service
+    pub cnames: &'static [&'static str],
+    /// Text the service serves for a resource that does not exist; `None` when the
+    /// unclaimed resource only shows as a CNAME target that no longer resolves
+    pub body: Option<&'static str>,
+    pub severity: Severity,
+}
+
+/// Built-in fingerprints, after the public can-i-take-over-xyz list.
+pub const FINGERPRINTS: &[Fingerprint] = &[
+    Fingerprint {
+        service: "GitHub Pages",
+        cnames: &["github.io"],
+        body: Some("There isn't a GitHub Pages site here."),
+        severity: Severity::High,
+    },
+    Fingerprint {
+        service: "Heroku",
+        cnames: &["herokuapp.com", "herokudns.com"],
+        body: Some("No such app"),
+        severity: Severity::High,
+    },
+    Fingerprint {
+        service: "AWS S3",
+        cnames: &["s3.amazonaws.com"],
+        body: Some("NoSuchBucket"),
+        severity: Severity::High,
+    },
+    Fingerprint {
+        service: "AWS CloudFront",
+        cnames: &["cloudfront.net"],
+        body: Some("The request could not be satisfied"),
+        severity: Severity::Low,
+    },
+    Fingerprint {
+        service: "Azure",
+        cnames: &["azurewebsites.net", "cloudapp.net", "trafficmanager.net", "blob.core.windows.net"],
+        body: None,
+        severity: Severity::High,
+    },
+    Fingerprint {
+        service: "Pantheon",
+        cnames: &["pantheonsite.io"],
+        body: Some("404 error unknown site!"),
+        severity: Severity::High,
+    },
+    Fingerprint {
+        service: "Ghost",
+        cnames: &["ghost.io"],
+        body: Some("Failed to resolve DNS path for this host"),
+        severity: Severity::Medium,
+    },
+    Fingerprint {
+        service: "Shopify",
+        cnames: &["myshopify.com"],
+        body: Some("Sorry, this shop is currently unavailable."),
+        severity: Severity::Medium,
+    },
+    Fingerprint {
+        service: "Surge.sh",
+        cnames: &["surge.sh"],
+        body: Some("project not found"),
+        severity: Severity::High,
+    },
+    Fingerprint {
+        service: "Bitbucket",
+        cnames: &["bitbucket.io"],
+        body: Some("Repository not found"),
+        severity: Severity::High,
+    },
+    Fingerprint {
+        service: "Netlify",
+        cnames: &["netlify.app"],
+        body: Some("Not Found - Request ID"),
+        severity: Severity::Medium,
+    },
+    Fingerprint {
+        service: "ReadMe.io",
+        cnames: &["readme.io"],
+        body: Some("Project doesnt exist... yet!"),
+        severity: Severity::High,
+    },
+    Fingerprint {
+        service: "Unbounce",
+        cnames: &["unbouncepages.com"],
+        body: Some("The requested URL was not found on this server."),
+        severity: Severity::Medium,
+    },
+];
+
+/// Every CNAME suffix of the built-in fingerprints, for the scanner's dangling check.
+pub fn cname_suffixes() -> Vec<String> {
+    FINGERPRINTS
+        .iter()
+        .flat_map(|fingerprint| fingerprint.cnames.iter().map(|suffix| suffix.to_string()))
+        .collect()
+}
+
+/// The fingerprint whose CNAME suffixes `target` falls under.
+pub fn fingerprint_for(target: &str) -> Option<&'static Fingerprint> {
+    let target = target.trim_end_matches('.').to_lowercase();
+    FINGERPRINTS.iter().find(|fingerprint| {
+        fingerprint
+            .cnames
+            .iter()
+            .any(|suffix| target == *suffix || target.ends_with(&format!(".{}", suffix)))
+    })
+}
+
+/// A subdomain whose CNAME points at an unclaimed resource of `service`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TakeoverFinding {
+    pub service: String,
+    pub severity: Severity,
+    /// Why the resource is considered unclaimed
+    pub evidence: String,
+}
+
+impl std::fmt::Display for TakeoverFinding {
+    /// `GitHub Pages (high): acme.github.io serves the unclaimed-resource page`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}): {}", self.service, self.severity, self.evidence)
+    }
+}
+
+pub struct TakeoverChecker {
+    client: reqwest::Client,
+    concurrency: usize,
+}
+
+impl TakeoverChecker {
+    /// Checks run `concurrency` subdomains at a time.
+    pub fn new(concurrency: usize) -> Result<Self, TakeoverError> {
+        let client = reqwest::Client::builder()
+            .timeout(DEFAULT_TAKEOVER_TIMEOUT)
+            .danger_accept_invalid_certs(true)
+            .build()
+            .map_err(|e| TakeoverError::ClientError(e.to_string()))?;
+        Ok(TakeoverChecker { client, concurrency: concurrency.max(1) })
+    }
+
+    /// Check one result. A CNAME to a fingerprinted service is a finding when the
+    /// target no longer resolves (`dangling`) or when the subdomain serves the
+    /// service's page for a resource that does not exist.
+    pub async fn check(&self, result: &ScanResult) -> Option<TakeoverFinding> {
+        let (target, fingerprint) = result
+            .cname_chain
+            .iter()
+            .find_map(|target| fingerprint_for(target).map(|fingerprint| (target, fingerprint)))?;
+
+        let evidence = if result.dangling {
+            format!("CNAME target {} does not resolve", target)
+        } else {
+            let body = fingerprint.body?;
+            if !self.serves(&result.subdomain, body).await {
+                return None;
+            }
+            format!("{} serves the unclaimed-resource page", target)
+        };
+        Some(TakeoverFinding {
+            service: fingerprint.service.to_string(),
+            severity: fingerprint.severity,
+            evidence,
+        })
+    }
+
+    /// Check every result and store findings in its `takeover` field.
+    pub async fn check_all(&self, results: &mut [ScanResult]) {
+        say!("{} Checking {} subdomains for takeover fingerprints", "[*]".blue(), results.len());
+        let progress = progress_bar(results.len() as u64, "Checking takeovers...");
+
+        let findings: Vec<(usize, Option<TakeoverFinding>)> = stream::iter(results.iter().enumerate())
+            .map(|(index, result)| {
+                let progress = progress.clone();
+                async move {
+                    let finding = self.check(result).await;
+                    progress.inc(1);
+                    (index, finding)
+                }
+            })
+            .buffer_unordered(self.concurrency)
+            .collect()
+            .await;
+        progress.finish_with_message("takeover check completed");
+
+        let mut count = 0;
+        for (index, finding) in findings {
+            count += usize::from(finding.is_some());
+            results[index].takeover = finding;
+        }
+        if count > 0 {
+            say!("{} {} subdomains look open to takeover", "[!]".red(), count);
+        }
+    }
+
+    /// Whether `host` answers on either HTTP or HTTPS with a body containing `fingerprint`.
+    async fn serves(&self, host: &str, fingerprint: &str) -> bool {
+        for scheme in ["http", "https"] {
+            let Ok(mut response) = self.client.get(format!("{}://{}/", scheme, host)).send().await else {
+                continue;
+            };
+            let mut body = Vec::new();
+            while let Ok(Some(chunk)) = response.chunk().await {
+                body.extend_from_slice(&chunk);
+                if body.len() >= BODY_SCAN_BYTES {
+                    break;
+                }
+            }
+            // Providers often redirect or serve a placeholder on one scheme only
+            if String::from_utf8_lossy(&body).contains(fingerprint) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// HTTP server answering every request with `body`.
+    async fn http_server(body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let host = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = vec![0; 1024];
+                let _ = stream.read(&mut request).await;
+                let response = format!(
+                    "HTTP/1.1 404 Not Found\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        host
+    }
+
+    fn result(subdomain: &str, cname: &str, dangling: bool) -> ScanResult {
+        ScanResult {
+            subdomain: subdomain.to_string(),
+            cname: Some(cname.to_string()),
+            cname_chain: vec![cname.to_string()],
+            dangling,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_fingerprint_for() {
+        assert_eq!(fingerprint_for("acme.github.io").map(|f| f.service), Some("GitHub Pages"));
+        assert_eq!(fingerprint_for("Shop.MyShopify.com.").map(|f| f.service), Some("Shopify"));
+        assert!(fingerprint_for("notgithub.io").is_none());
+        assert!(cname_suffixes().contains(&"blob.core.windows.net".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_check() {
+        let checker = TakeoverChecker::new(4).unwrap();
+        let unclaimed = http_server("<html><p>There isn't a GitHub Pages site here.</p></html>").await;
+        let claimed = http_server("<html>Welcome to acme</html>").await;
+
+        let finding = checker.check(&result(&unclaimed, "acme.github.io", false)).await.unwrap();
+        assert_eq!((finding.service.as_str(), finding.severity), ("GitHub Pages", Severity::High));
+        assert_eq!(finding.to_string(), "GitHub Pages (high): acme.github.io serves the unclaimed-resource page");
+
+        assert!(checker.check(&result(&claimed, "acme.github.io", false)).await.is_none());
+        // Not a fingerprinted service
+        assert!(checker.check(&result(&unclaimed, "edge.example.net", false)).await.is_none());
+
+        // A dangling CNAME needs no HTTP check, even for services without a body fingerprint
+        let finding = checker.check(&result("gone.example.com", "gone.azurewebsites.net", true)).await.unwrap();
+        assert_eq!(finding.service, "Azure");
+        assert_eq!(finding.evidence, "CNAME target gone.azurewebsites.net does not resolve");
+
+        let mut results = vec![result(&unclaimed, "acme.github.io", false), result(&claimed, "acme.github.io", false)];
+        checker.check_all(&mut results).await;
+        assert!(results[0].takeover.is_some());
+        assert!(results[1].takeover.is_none());
+    }
+}
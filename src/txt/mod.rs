@@ -0,0 +1,243 @@
+use colored::*;
+use futures::stream::{self, StreamExt};
+use serde::Serialize;
+use std::collections::HashSet;
+use trust_dns_resolver::proto::rr::{RData, RecordType};
+use crate::scanner::Scanner;
+
+/// A candidate subdomain found inside a TXT record, along with where it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TxtCandidate {
+    pub subdomain: String,
+    pub host: String,
+    pub record: String,
+}
+
+/// DKIM selectors commonly published by mail providers and MTAs.
+pub const COMMON_DKIM_SELECTORS: &[&str] = &[
+    "default", "google", "selector1", "selector2", "k1", "k2", "dkim", "mail", "s1", "s2", "smtp", "mandrill",
+];
+
+/// Email-security records published at a domain's apex.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EmailPosture {
+    pub domain: String,
+    pub spf: Option<String>,
+    /// The SPF `all` mechanism, e.g. `-all` (hard fail) or `~all` (soft fail)
+    pub spf_policy: Option<String>,
+    pub dmarc: Option<String>,
+    /// The DMARC `p=` tag: `none`, `quarantine` or `reject`
+    pub dmarc_policy: Option<String>,
+    /// Selectors from `COMMON_DKIM_SELECTORS` with a DKIM key record
+    pub dkim_selectors: Vec<String>,
+}
+
+pub struct TxtParser<'a> {
+    scanner: &'a Scanner,
+}
+
+impl<'a> TxtParser<'a> {
+    /// Query TXT records through `scanner`, so they share its resolvers, rate limit,
+    /// timeout and concurrency.
+    pub fn new(scanner: &'a Scanner) -> Self {
+        TxtParser { scanner }
+    }
+
+    /// Look up TXT records for every host and collect the in-scope hostnames they reference.
+    pub async fn fetch_candidates(&self, base_domain: &str, hosts: &[String]) -> Vec<TxtCandidate> {
+        self.info(&format!("Fetching TXT records for {} hosts", hosts.len()));
+
+        let records: Vec<(String, Vec<String>)> = stream::iter(hosts.to_vec())
+            .map(|host| async move {
+                let records = self.txt_records(&host).await;
+                (host, records)
+            })
+            .buffered(self.scanner.concurrency())
+            .collect()
+            .await;
+
+        let known: HashSet<&str> = hosts.iter().map(String::as_str).collect();
+        let mut seen = HashSet::new();
+        let mut candidates = Vec::new();
+        let mut record_count = 0;
+
+        for (host, host_records) in &records {
+            for record in host_records {
+                record_count += 1;
+                for subdomain in extract_hostnames(record, base_domain) {
+                    if known.contains(subdomain.as_str()) || !seen.insert(subdomain.clone()) {
+                        continue;
+                    }
+                    candidates.push(TxtCandidate {
+                        subdomain,
+                        host: host.clone(),
+                        record: record.clone(),
+                    });
+                }
+            }
+        }
+
+        self.success(&format!(
+            "Parsed {} TXT records, found {} new candidates",
+            record_count,
+            candidates.len()
+        ));
+        candidates
+    }
+
+    /// Look up the apex SPF record, `_dmarc` and the common DKIM selectors.
+    pub async fn email_posture(&self, domain: &str) -> EmailPosture {
+        self.info(&format!("Checking SPF, DMARC and {} DKIM selectors", COMMON_DKIM_SELECTORS.len()));
+
+        let spf = self.txt_records(domain).await.into_iter().find(|r| r.to_lowercase().starts_with("v=spf1"));
+        let dmarc = self
+            .txt_records(&format!("_dmarc.{}", domain))
+            .await
+            .into_iter()
+            .find(|r| r.to_lowercase().starts_with("v=dmarc1"));
+
+        let dkim_selectors = stream::iter(COMMON_DKIM_SELECTORS.iter())
+            .map(|selector| async move {
+                let records = self.txt_records(&format!("{}._domainkey.{}", selector, domain)).await;
+                let found = records.iter().any(|r| r.contains("p=") || r.to_lowercase().starts_with("v=dkim1"));
+                found.then(|| selector.to_string())
+            })
+            .buffered(self.scanner.concurrency())
+            .filter_map(|selector| async move { selector })
+            .collect()
+            .await;
+
+        EmailPosture {
+            domain: domain.to_string(),
+            spf_policy: spf.as_deref().and_then(spf_policy),
+            dmarc_policy: dmarc.as_deref().and_then(dmarc_policy),
+            spf,
+            dmarc,
+            dkim_selectors,
+        }
+    }
+
+    async fn txt_records(&self, host: &str) -> Vec<String> {
+        let records = self.scanner.lookup_records(host, RecordType::TXT).await.unwrap_or_default();
+        records
+            .into_iter()
+            .filter_map(|data| match data {
+                RData::TXT(txt) => Some(txt.to_string()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn info(&self, message: &str) {
+        say!("{} {}", "[*]".blue(), message);
+    }
+
+    fn success(&self, message: &str) {
+        say!("{} {}", "[+]".green(), message);
+    }
+}
+
+/// The qualified `all` mechanism of an SPF record (`-all`, `~all`, `?all` or `+all`).
+pub fn spf_policy(record: &str) -> Option<String> {
+    record.split_whitespace().find_map(|term| match term.to_lowercase().as_str() {
+        "all" | "+all" => Some("+all".to_string()),
+        term @ ("-all" | "~all" | "?all") => Some(term.to_string()),
+        _ => None,
+    })
+}
+
+/// The `p=` tag of a DMARC record.
+pub fn dmarc_policy(record: &str) -> Option<String> {
+    record.split(';').find_map(|tag| {
+        let (name, value) = tag.split_once('=')?;
+        (name.trim().eq_ignore_ascii_case("p")).then(|| value.trim().to_lowercase())
+    })
+}
+
+/// Extract hostnames under `base_domain` referenced by a TXT record (SPF `include:`,
+/// `redirect=`, `a:`, `mx:`, verification strings and so on).
+pub fn extract_hostnames(record: &str, base_domain: &str) -> Vec<String> {
+    let suffix = format!(".{}", base_domain.to_lowercase());
+    let mut hostnames = Vec::new();
+
+    for token in record.split(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')) {
+        let hostname = token.trim_end_matches('.').to_lowercase();
+        if hostname.len() <= suffix.len() || !hostname.ends_with(&suffix) {
+            continue;
+        }
+        let valid = hostname.split('.').all(|label| {
+            !label.is_empty() && !label.starts_with('-') && !label.ends_with('-')
+        });
+        if valid && !hostnames.contains(&hostname) {
+            hostnames.push(hostname);
+        }
+    }
+    hostnames
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_hostnames() {
+        let spf = "v=spf1 include:_spf.example.com include:_spf.google.com a:mail.Example.com -all";
+        assert_eq!(
+            extract_hostnames(spf, "example.com"),
+            vec!["_spf.example.com".to_string(), "mail.example.com".to_string()]
+        );
+
+        let redirect = "v=spf1 redirect=spf.corp.example.com";
+        assert_eq!(
+            extract_hostnames(redirect, "example.com"),
+            vec!["spf.corp.example.com".to_string()]
+        );
+
+        // Look-alike domains and the apex itself are not candidates
+        assert!(extract_hostnames("include:mail.notexample.com", "example.com").is_empty());
+        assert!(extract_hostnames("google-site-verification=abc123", "example.com").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_candidates_through_scan_resolvers() {
+        use crate::scanner::fake_dns::{self, code, records};
+        use trust_dns_resolver::proto::op::ResponseCode;
+        use trust_dns_resolver::proto::rr::rdata;
+
+        let port = fake_dns::serve(|question| match (question.name.as_str(), question.record_type) {
+            ("example.test.", RecordType::TXT) => {
+                records(vec![RData::TXT(rdata::TXT::new(vec!["v=spf1 include:_spf.example.test -all".to_string()]))])
+            }
+            ("example.test." | "www.example.test.", _) => records(Vec::new()),
+            _ => code(ResponseCode::NXDomain),
+        })
+        .await;
+        let resolvers = crate::scanner::parse_resolvers(&[format!("127.0.0.1:{}", port)]).unwrap();
+        let scanner = Scanner::with_resolvers(&resolvers, 10).await.unwrap();
+        let parser = TxtParser::new(&scanner);
+
+        let hosts = vec!["example.test".to_string(), "www.example.test".to_string()];
+        let candidates = parser.fetch_candidates("example.test", &hosts).await;
+        assert_eq!(
+            candidates,
+            vec![TxtCandidate {
+                subdomain: "_spf.example.test".to_string(),
+                host: "example.test".to_string(),
+                record: "v=spf1 include:_spf.example.test -all".to_string(),
+            }]
+        );
+        assert_eq!(parser.email_posture("example.test").await.spf_policy.as_deref(), Some("-all"));
+    }
+
+    #[test]
+    fn test_email_policies() {
+        assert_eq!(spf_policy("v=spf1 include:_spf.google.com -all"), Some("-all".to_string()));
+        assert_eq!(spf_policy("v=spf1 mx ~ALL"), Some("~all".to_string()));
+        assert_eq!(spf_policy("v=spf1 a all"), Some("+all".to_string()));
+        assert_eq!(spf_policy("v=spf1 redirect=_spf.example.com"), None);
+
+        assert_eq!(dmarc_policy("v=DMARC1; p=reject; rua=mailto:d@example.com"), Some("reject".to_string()));
+        assert_eq!(dmarc_policy("v=DMARC1;p=None"), Some("none".to_string()));
+        assert_eq!(dmarc_policy("v=DMARC1; sp=reject"), None);
+    }
+}
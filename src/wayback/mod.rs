@@ -1,15 +1,21 @@
 use colored::*;
 use std::collections::HashSet;
-use regex::Regex;
 use std::error::Error;
+use std::time::Duration;
+use crate::utils::harvest_hostnames;
 
 #[derive(Debug)]
 pub enum WaybackError {
     NetworkError(String),
     EmptyResponse,
-    InvalidResponse(String),
-    RegexError(String),
     HttpError(String),
+    /// The whole phase exceeded `--wayback-timeout`
+    Timeout(Duration),
+    /// A single CDX request exceeded the request timeout
+    RequestTimeout(Duration),
+    UnexpectedFormat { content_type: String, preview: String },
+    /// A `from`/`to` bound that is not an 8 to 14 digit CDX timestamp
+    InvalidTimestamp(String),
 }
 
 impl std::fmt::Display for WaybackError {
@@ -17,141 +23,413 @@ impl std::fmt::Display for WaybackError {
         match self {
             WaybackError::NetworkError(e) => write!(f, "Network error: {}", e),
             WaybackError::EmptyResponse => write!(f, "Wayback Machine returned no data"),
-            WaybackError::InvalidResponse(e) => write!(f, "Invalid response format: {}", e),
-            WaybackError::RegexError(e) => write!(f, "Regex error: {}", e),
             WaybackError::HttpError(e) => write!(f, "HTTP error: {}", e),
+            WaybackError::Timeout(d) => write!(f, "Wayback phase timed out after {:?}", d),
+            WaybackError::RequestTimeout(d) => write!(f, "Wayback request timed out after {:?}", d),
+            WaybackError::UnexpectedFormat { content_type, preview } => write!(
+                f,
+                "Wayback API returned an unexpected format (content-type: {}), body starts with: {:?}",
+                content_type, preview
+            ),
+            WaybackError::InvalidTimestamp(value) => write!(
+                f,
+                "Invalid Wayback timestamp {:?}, expected YYYYMMDD optionally followed by hhmmss",
+                value
+            ),
         }
     }
 }
 
 impl Error for WaybackError {}
 
+/// How much of an unexpected response body is quoted in errors.
+const PREVIEW_LEN: usize = 120;
+
+const CDX_URL: &str = "http://web.archive.org/cdx/search/cdx";
+
+/// Rows requested per CDX page.
+const PAGE_SIZE: usize = 5000;
+
+/// Default bound for a single CDX request.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// One page of CDX results and the key to request the next one.
+#[derive(Debug, PartialEq)]
+struct CdxPage {
+    urls: Vec<String>,
+    resume_key: Option<String>,
+}
+
 pub struct WaybackMachine {
     client: reqwest::Client,
+    /// `CDX_URL`, replaced by a local server in tests
+    cdx_url: String,
+    phase_timeout: Option<Duration>,
+    request_timeout: Duration,
+    row_limit: Option<usize>,
+    from: Option<String>,
+    to: Option<String>,
+    /// Keep log lines off the terminal.
+    quiet: bool,
 }
 
 impl WaybackMachine {
-    pub fn new() -> Self {
+    /// `phase_timeout` bounds the whole Wayback phase (fetching and extraction),
+    /// independently of how long any single request takes.
+    pub fn new(phase_timeout: Option<Duration>) -> Self {
         WaybackMachine {
-            client: reqwest::Client::new(),
+            client: build_client(DEFAULT_REQUEST_TIMEOUT),
+            cdx_url: CDX_URL.to_string(),
+            phase_timeout,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            row_limit: None,
+            from: None,
+            to: None,
+            quiet: true,
         }
     }
 
+    /// Log each step of the phase. Quiet by default; errors are returned either way.
+    pub fn with_quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Bound each CDX page request.
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.client = build_client(timeout);
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Stop paging once this many CDX rows have been fetched.
+    pub fn with_row_limit(mut self, limit: Option<usize>) -> Self {
+        self.row_limit = limit;
+        self
+    }
+
+    /// Only consider captures between these CDX timestamps (inclusive), either bound
+    /// optional. Malformed bounds are rejected before any request is built.
+    pub fn with_time_range(mut self, from: Option<&str>, to: Option<&str>) -> Result<Self, WaybackError> {
+        self.from = from.map(parse_timestamp).transpose()?;
+        self.to = to.map(parse_timestamp).transpose()?;
+        Ok(self)
+    }
+
     pub async fn fetch_subdomains(&self, domain: &str) -> Result<Vec<String>, WaybackError> {
+        let Some(limit) = self.phase_timeout else {
+            return self.fetch_all(domain).await;
+        };
+
+        match tokio::time::timeout(limit, self.fetch_all(domain)).await {
+            Ok(result) => result,
+            Err(_) => Err(WaybackError::Timeout(limit)),
+        }
+    }
+
+    async fn fetch_all(&self, domain: &str) -> Result<Vec<String>, WaybackError> {
         self.info("Initializing Wayback Machine scan...");
-        
-        let url = format!(
-            "http://web.archive.org/cdx/search/cdx?url=*.{}&output=json&fl=original&collapse=urlkey",
-            domain
-        );
-
-        let domain_regex = Regex::new(r"url=\*\.([^&]+)").unwrap();
-        let domain = domain_regex.captures(&url)
-            .and_then(|cap| cap.get(1))
-            .map(|m| m.as_str())
-            .unwrap_or("unknown");
         self.info(&format!("Searching for subdomains of: {}", domain));
 
-        let response = self.client.get(&url)
+        let mut subdomains = HashSet::new();
+        let mut rows = 0;
+        let mut resume_key: Option<String> = None;
+        loop {
+            let page_size = match self.row_limit {
+                Some(limit) => PAGE_SIZE.min(limit - rows),
+                None => PAGE_SIZE,
+            };
+            let page = self.fetch_page(domain, page_size, resume_key.as_deref()).await?;
+            rows += page.urls.len();
+            self.extract_subdomains(domain, &page.urls, &mut subdomains);
+
+            let limit_reached = self.row_limit.is_some_and(|limit| rows >= limit);
+            match page.resume_key {
+                Some(key) if !limit_reached && !page.urls.is_empty() => {
+                    self.info(&format!("Fetched {} URLs so far, requesting next page", rows));
+                    resume_key = Some(key);
+                }
+                _ => break,
+            }
+        }
+
+        if rows == 0 {
+            self.warn("Wayback Machine returned empty response");
+            return Err(WaybackError::EmptyResponse);
+        }
+
+        self.success(&format!("Retrieved {} URLs from Wayback Machine", rows));
+        self.success(&format!("Found {} unique subdomains", subdomains.len()));
+        Ok(subdomains.into_iter().collect())
+    }
+
+    async fn fetch_page(&self, domain: &str, page_size: usize, resume_key: Option<&str>) -> Result<CdxPage, WaybackError> {
+        let response = self.page_request(domain, page_size, resume_key)
             .send()
             .await
-            .map_err(|e| WaybackError::NetworkError(e.to_string()))?;
+            .map_err(|e| self.request_error(e))?;
 
         if !response.status().is_success() {
             let error_msg = format!("HTTP {} {}",
                 response.status().as_str(),
                 response.status().canonical_reason().unwrap_or("Unknown error")
             );
-            self.error(&error_msg);
             return Err(WaybackError::HttpError(error_msg));
         }
 
-        let urls: Vec<Vec<String>> = response.json()
+        let content_type = response.headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("unknown")
+            .to_string();
+        let body = response.text()
             .await
-            .map_err(|e| WaybackError::InvalidResponse(e.to_string()))?;
+            .map_err(|e| self.request_error(e))?;
 
-        if urls.is_empty() {
-            self.warn("Wayback Machine returned empty response");
-            return Err(WaybackError::EmptyResponse);
-        }
+        parse_page(&content_type, &body)
+    }
 
-        self.success(&format!("Retrieved {} URLs from Wayback Machine", urls.len()));
-        
-        let urls: Vec<String> = urls.into_iter()
-            .skip(1) // Skip header row
-            .map(|row| row[0].clone())
-            .collect();
+    fn page_request(&self, domain: &str, page_size: usize, resume_key: Option<&str>) -> reqwest::RequestBuilder {
+        let mut query = vec![
+            ("url", format!("*.{}", domain)),
+            ("output", "json".to_string()),
+            ("fl", "original".to_string()),
+            ("collapse", "urlkey".to_string()),
+            ("showResumeKey", "true".to_string()),
+            ("limit", page_size.to_string()),
+        ];
+        if let Some(from) = &self.from {
+            query.push(("from", from.clone()));
+        }
+        if let Some(to) = &self.to {
+            query.push(("to", to.clone()));
+        }
+        if let Some(key) = resume_key {
+            query.push(("resumeKey", key.to_string()));
+        }
+        self.client.get(&self.cdx_url).query(&query)
+    }
 
-        let subdomains = self.extract_subdomains(domain, &urls)?;
-        self.success(&format!("Found {} unique subdomains", subdomains.len()));
-        
-        Ok(subdomains)
+    fn request_error(&self, e: reqwest::Error) -> WaybackError {
+        if e.is_timeout() {
+            WaybackError::RequestTimeout(self.request_timeout)
+        } else {
+            WaybackError::NetworkError(e.to_string())
+        }
     }
 
-    fn extract_subdomains(&self, base_domain: &str, urls: &[String]) -> Result<Vec<String>, WaybackError> {
+    fn extract_subdomains(&self, base_domain: &str, urls: &[String], subdomains: &mut HashSet<String>) {
         self.info(&format!("Processing {} URLs for subdomain extraction", urls.len()));
-        
-        let subdomain_pattern = format!(
-            r"(?i)https?://([a-zA-Z0-9][-a-zA-Z0-9]*\.)*{}",
-            regex::escape(base_domain)
-        );
-        
-        let re = Regex::new(&subdomain_pattern)
-            .map_err(|e| WaybackError::RegexError(e.to_string()))?;
-        
-        let mut subdomains = HashSet::new();
+
+        let known = subdomains.len();
         let mut invalid_count = 0;
-        // let mut processed = 0;
 
         for url in urls {
-            // processed += 1;
-            // if processed % 1000 == 0 {
-            //     self.info(&format!("Processed {} URLs", processed));
-            // }
-
-            match re.captures(url) {
-                Some(captures) => {
-                    if let Some(subdomain) = captures.get(1) {
-                        let mut full_domain = subdomain.as_str().to_string();
-                        if !full_domain.ends_with(base_domain) {
-                            full_domain.push_str(base_domain);
-                        }
-                        if full_domain.ends_with('.') {
-                            full_domain.pop();
-                        }
-                        subdomains.insert(full_domain);
-                    } else {
-                        invalid_count += 1;
-                    }
-                }
-                None => {
-                    invalid_count += 1;
-                }
+            let hostnames = harvest_hostnames(url, base_domain);
+            if hostnames.is_empty() {
+                invalid_count += 1;
             }
+            subdomains.extend(hostnames);
         }
 
         if invalid_count > 0 {
             self.warn(&format!("Skipped {} invalid URLs", invalid_count));
         }
 
-        let result: Vec<String> = subdomains.into_iter().collect();
-        self.info(&format!("Extracted {} unique subdomains", result.len()));
-        Ok(result)
+        self.info(&format!("Extracted {} new unique subdomains", subdomains.len() - known));
     }
 
     fn info(&self, message: &str) {
-        println!("{} {}", "[*]".blue(), message);
+        say!(quiet = self.quiet; "{} {}", "[*]".blue(), message);
     }
 
     fn success(&self, message: &str) {
-        println!("{} {}", "[+]".green(), message);
+        say!(quiet = self.quiet; "{} {}", "[+]".green(), message);
     }
 
     fn warn(&self, message: &str) {
-        println!("{} {}", "[!]".yellow(), message);
+        say!(quiet = self.quiet; "{} {}", "[!]".yellow(), message);
     }
+}
 
-    fn error(&self, message: &str) {
-        println!("{} {}", "[!]".red(), message);
+/// Parse a CDX JSON body, telling HTML error pages and oddly shaped JSON apart from
+/// genuine results so outages are reported with a readable preview.
+fn parse_cdx(content_type: &str, body: &str) -> Result<Vec<Vec<String>>, WaybackError> {
+    let trimmed = body.trim_start();
+    let unexpected = || WaybackError::UnexpectedFormat {
+        content_type: content_type.to_string(),
+        preview: body_preview(trimmed),
+    };
+
+    if content_type.contains("html") || trimmed.starts_with('<') {
+        return Err(unexpected());
+    }
+    if trimmed.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Valid JSON of the wrong shape (e.g. an error object) is as unexpected as HTML
+    serde_json::from_str(trimmed).map_err(|_| unexpected())
+}
+
+/// Split a CDX page into its URLs and the resume key. With `showResumeKey=true` the
+/// rows are: a header, the results, an empty row, then the key in a row of its own.
+fn parse_page(content_type: &str, body: &str) -> Result<CdxPage, WaybackError> {
+    let mut rows = parse_cdx(content_type, body)?.into_iter();
+    let mut urls = Vec::new();
+    let mut resume_key = None;
+    while let Some(row) = rows.next() {
+        match row.as_slice() {
+            [] => {
+                resume_key = rows.next().and_then(|row| row.into_iter().next());
+                break;
+            }
+            [field] if field == "original" => {} // Header row
+            [url, ..] => urls.push(url.clone()),
+        }
+    }
+    Ok(CdxPage { urls, resume_key })
+}
+
+/// Check a CDX timestamp: `YYYYMMDD` optionally followed by up to `hhmmss`.
+pub fn parse_timestamp(value: &str) -> Result<String, WaybackError> {
+    if (8..=14).contains(&value.len()) && value.chars().all(|c| c.is_ascii_digit()) {
+        Ok(value.to_string())
+    } else {
+        Err(WaybackError::InvalidTimestamp(value.to_string()))
     }
-}
\ No newline at end of file
+}
+
+fn build_client(timeout: Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .unwrap_or_default()
+}
+
+/// The first `PREVIEW_LEN` characters of a body, on a single line.
+fn body_preview(body: &str) -> String {
+    let flat: String = body.split_whitespace().collect::<Vec<_>>().join(" ");
+    match flat.char_indices().nth(PREVIEW_LEN) {
+        Some((end, _)) => format!("{}...", &flat[..end]),
+        None => flat,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cdx() {
+        let rows = parse_cdx("application/json", r#"[["original"],["https://a.example.com/"]]"#).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert!(parse_cdx("text/plain", "").unwrap().is_empty());
+
+        let html = "<html>\n<body>Service Unavailable</body></html>";
+        match parse_cdx("text/html; charset=utf-8", html) {
+            Err(WaybackError::UnexpectedFormat { content_type, preview }) => {
+                assert_eq!(content_type, "text/html; charset=utf-8");
+                assert_eq!(preview, "<html> <body>Service Unavailable</body></html>");
+            }
+            other => panic!("expected UnexpectedFormat, got {:?}", other),
+        }
+
+        assert!(matches!(
+            parse_cdx("application/json", r#"{"error": "rate limited"}"#),
+            Err(WaybackError::UnexpectedFormat { .. })
+        ));
+        assert!(matches!(
+            parse_cdx("application/json", "not json at all"),
+            Err(WaybackError::UnexpectedFormat { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_page() {
+        let body = r#"[["original"],["https://a.example.com/"],["https://b.example.com/x"],[],["com,example,b)/x 20200101"]]"#;
+        let page = parse_page("application/json", body).unwrap();
+        assert_eq!(page.urls, vec!["https://a.example.com/", "https://b.example.com/x"]);
+        assert_eq!(page.resume_key.as_deref(), Some("com,example,b)/x 20200101"));
+
+        // The last page has no resume key
+        let page = parse_page("application/json", r#"[["original"],["https://c.example.com/"]]"#).unwrap();
+        assert_eq!(page, CdxPage { urls: vec!["https://c.example.com/".to_string()], resume_key: None });
+
+        assert_eq!(parse_page("text/plain", "").unwrap(), CdxPage { urls: Vec::new(), resume_key: None });
+    }
+
+    /// Serve two CDX pages: the first links to the second through its resume key.
+    /// Both start with the header row and both list `b.example.com`.
+    async fn cdx_server() -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/cdx", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = vec![0; 2048];
+                let len = stream.read(&mut request).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..len]).to_string();
+                let body = if request.lines().next().unwrap_or_default().contains("resumeKey=") {
+                    r#"[["original"],["https://b.example.com/y"],["https://c.example.com/"]]"#
+                } else {
+                    r#"[["original"],["https://a.example.com/"],["https://b.example.com/x"],[],["com,example,b)/x"]]"#
+                };
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_fetch_pages() {
+        let mut wayback = WaybackMachine::new(None);
+        wayback.cdx_url = cdx_server().await;
+
+        let mut subdomains = wayback.fetch_subdomains("example.com").await.unwrap();
+        subdomains.sort();
+        assert_eq!(subdomains, vec!["a.example.com", "b.example.com", "c.example.com"]);
+
+        // The row limit stops paging after the first page
+        let mut limited = WaybackMachine::new(None).with_row_limit(Some(2));
+        limited.cdx_url = wayback.cdx_url.clone();
+        let mut subdomains = limited.fetch_subdomains("example.com").await.unwrap();
+        subdomains.sort();
+        assert_eq!(subdomains, vec!["a.example.com", "b.example.com"]);
+    }
+
+    #[test]
+    fn test_time_range_query() {
+        let url = |wayback: &WaybackMachine| {
+            wayback.page_request("example.com", PAGE_SIZE, None).build().unwrap().url().to_string()
+        };
+        let unbounded = "http://web.archive.org/cdx/search/cdx?url=*.example.com&output=json&fl=original&collapse=urlkey&showResumeKey=true&limit=5000";
+
+        assert_eq!(url(&WaybackMachine::new(None)), unbounded);
+        assert_eq!(url(&WaybackMachine::new(None).with_time_range(None, None).unwrap()), unbounded);
+
+        let ranged = WaybackMachine::new(None).with_time_range(Some("20200101"), Some("20231231235959")).unwrap();
+        assert_eq!(url(&ranged), format!("{}&from=20200101&to=20231231235959", unbounded));
+
+        for bad in ["2020", "2020-01-01", "202001011200000", "yesterday"] {
+            assert!(matches!(
+                WaybackMachine::new(None).with_time_range(Some(bad), None),
+                Err(WaybackError::InvalidTimestamp(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_body_preview_truncates() {
+        let long = "x".repeat(500);
+        let preview = body_preview(&long);
+        assert_eq!(preview.len(), PREVIEW_LEN + 3);
+        assert!(preview.ends_with("..."));
+    }
+}
@@ -0,0 +1,767 @@
+use crate::permute::label_variants;
+use crate::utils::{to_ascii_domain, to_unicode_domain};
+use clap::ValueEnum;
+use colored::*;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use flate2::read::GzDecoder;
+use std::io::{BufRead, BufReader, Lines};
+use std::path::{Path, PathBuf};
+
+/// Characters that mark a wordlist line as junk (URL fragments, templates) by default.
+pub const DEFAULT_SKIP_CHARS: &str = "=&?[]{}";
+
+/// Labels checked alongside the apex by `--apex-only`.
+pub const CRITICAL_LABELS: &[&str] = &["www", "mail", "api"];
+
+/// Common labels seeded by `WordlistManager::load_defaults` when no wordlist is supplied.
+pub const DEFAULT_PREFIXES: &[&str] = &[
+    "www", "mail", "webmail", "smtp", "pop", "imap", "mx", "ns1", "ns2", "dns", "ftp", "sftp", "vpn",
+    "remote", "api", "app", "m", "mobile", "dev", "test", "staging", "stage", "qa", "uat", "beta",
+    "demo", "sandbox", "prod", "admin", "portal", "dashboard", "login", "sso", "auth", "secure",
+    "intranet", "internal", "blog", "shop", "store", "docs", "help", "support", "status", "cdn",
+    "static", "assets", "img", "media", "files", "download", "git", "gitlab", "jenkins", "ci", "jira",
+    "wiki", "grafana", "monitor", "db", "mysql", "backup", "old", "new", "autodiscover", "owa",
+    "gateway", "proxy", "cloud", "search", "forum", "news", "crm", "billing", "pay",
+];
+
+/// Affixes combined with every word by `--permutations` unless others are given.
+pub const DEFAULT_PERMUTATION_AFFIXES: &str = "dev,stg,staging,test,uat,qa,int,prod,internal";
+
+/// Upper bound on generated permutations unless `--max-permutations` says otherwise.
+pub const DEFAULT_MAX_PERMUTATIONS: usize = 100_000;
+
+/// Characters `CharsetLabels` builds labels from, in generation order.
+pub const BRUTE_CHARSET: &str = "abcdefghijklmnopqrstuvwxyz0123456789-";
+
+/// Longest label `--charset-brute` accepts; 6 characters is already over 2 billion names.
+pub const MAX_BRUTE_LEN: usize = 6;
+
+/// Upper bound on generated multi-label names unless `--max-nested` says otherwise.
+pub const DEFAULT_MAX_NESTED: usize = 100_000;
+
+/// How many words `generate_nested` uses below the leftmost label. The shortest words
+/// are taken, since environment and service names (`dev`, `qa`, `api`) tend to be short.
+pub const NESTED_INNER_WORDS: usize = 100;
+
+/// Words `stream_words` can deduplicate before the false-positive rate rises above
+/// `STREAM_DEDUP_FP_RATE`. Sized for the largest public wordlists; costs about 18 MB.
+pub const STREAM_DEDUP_CAPACITY: usize = 10_000_000;
+
+/// Chance that a streamed word is wrongly taken for a duplicate and dropped.
+const STREAM_DEDUP_FP_RATE: f64 = 0.001;
+
+/// Letter case used for wordlist labels when building candidates.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum LabelCase {
+    Lower,
+    Upper,
+    /// Both lowercase and uppercase variants of each label
+    Both,
+}
+
+#[derive(Debug)]
+pub enum WordlistError {
+    IoError(PathBuf, String),
+    EmptyWordlist(PathBuf),
+    NoWordlists,
+    AllFailed(usize),
+}
+
+impl std::fmt::Display for WordlistError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WordlistError::IoError(path, e) => write!(f, "Failed to read {}: {}", path.display(), e),
+            WordlistError::EmptyWordlist(path) => write!(f, "Wordlist {} contains no usable words", path.display()),
+            WordlistError::NoWordlists => write!(f, "No wordlists were provided"),
+            WordlistError::AllFailed(count) => write!(f, "None of the {} wordlists yielded any words", count),
+        }
+    }
+}
+
+impl Error for WordlistError {}
+
+pub struct WordlistManager {
+    wordlists: Vec<PathBuf>,
+    loaded_words: HashSet<String>,
+    /// Validated words in sorted order, computed once by `load_all` and reused for every domain.
+    labels: Vec<String>,
+    skip_chars: String,
+    strict: bool,
+    failed: Vec<PathBuf>,
+}
+
+impl WordlistManager {
+    /// Lines containing any of `skip_chars` are dropped before validation.
+    /// An empty string disables the blacklist. With `strict` set, the first
+    /// wordlist that fails to load aborts `load_all`; otherwise it is skipped.
+    pub fn new(skip_chars: &str, strict: bool) -> Self {
+        WordlistManager {
+            wordlists: Vec::new(),
+            loaded_words: HashSet::new(),
+            labels: Vec::new(),
+            skip_chars: skip_chars.to_string(),
+            strict,
+            failed: Vec::new(),
+        }
+    }
+
+    pub fn add_wordlist(&mut self, path: &Path) {
+        self.wordlists.push(path.to_path_buf());
+    }
+
+    /// Register every `.txt` and `.gz` file in a directory, in name order.
+    pub fn add_directory(&mut self, dir: &Path) -> Result<usize, WordlistError> {
+        let entries = std::fs::read_dir(dir).map_err(|e| WordlistError::IoError(dir.to_path_buf(), e.to_string()))?;
+
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "txt" || ext == "gz"))
+            .collect();
+        paths.sort();
+
+        let count = paths.len();
+        self.wordlists.extend(paths);
+        Ok(count)
+    }
+
+    /// Load every registered wordlist into a single deduplicated set.
+    pub fn load_all(&mut self) -> Result<&HashSet<String>, WordlistError> {
+        if self.wordlists.is_empty() {
+            return Err(WordlistError::NoWordlists);
+        }
+
+        for path in self.wordlists.clone() {
+            match self.load_wordlist(&path) {
+                Ok(count) => self.info(&format!("Loaded {} words from {}", count, path.display())),
+                Err(e) if self.strict => return Err(e),
+                Err(e) => {
+                    self.warn(&format!("{}, skipping", e));
+                    self.failed.push(path);
+                }
+            }
+        }
+
+        if self.loaded_words.is_empty() {
+            return Err(WordlistError::AllFailed(self.wordlists.len()));
+        }
+
+        self.labels = self.loaded_words.iter().cloned().collect();
+        self.labels.sort();
+        Ok(&self.loaded_words)
+    }
+
+    /// Load `DEFAULT_PREFIXES` through the same normalization and validation as a
+    /// wordlist file, for runs where no wordlist was supplied.
+    pub fn load_defaults(&mut self) -> &HashSet<String> {
+        for prefix in DEFAULT_PREFIXES {
+            if let Some(word) = normalize_line(prefix).filter(|word| accepts_word(word, &self.skip_chars)) {
+                self.loaded_words.insert(word);
+            }
+        }
+        self.labels = self.loaded_words.iter().cloned().collect();
+        self.labels.sort();
+        &self.loaded_words
+    }
+
+    /// Wordlists that were skipped by `load_all` because they could not be loaded.
+    pub fn failed_wordlists(&self) -> &[PathBuf] {
+        &self.failed
+    }
+
+    fn load_wordlist(&mut self, path: &Path) -> Result<usize, WordlistError> {
+        let reader = open_wordlist(path).map_err(|e| WordlistError::IoError(path.to_path_buf(), e.to_string()))?;
+
+        let mut count = 0;
+        let mut skipped = 0;
+        for line in reader.lines() {
+            let line = line.map_err(|e| WordlistError::IoError(path.to_path_buf(), e.to_string()))?;
+            let Some(word) = normalize_line(&line) else {
+                continue;
+            };
+            if !accepts_word(&word, &self.skip_chars) {
+                skipped += 1;
+                continue;
+            }
+            self.loaded_words.insert(word);
+            count += 1;
+        }
+
+        if skipped > 0 {
+            self.warn(&format!("Skipped {} invalid entries in {}", skipped, path.display()));
+        }
+        if count == 0 {
+            return Err(WordlistError::EmptyWordlist(path.to_path_buf()));
+        }
+        Ok(count)
+    }
+
+    /// Check that a word is usable as one or more DNS labels. Unicode words are
+    /// checked in their punycode form, so length limits apply after encoding.
+    pub fn validate_word(word: &str) -> bool {
+        if !word.is_ascii() {
+            return word == word.to_lowercase()
+                && to_ascii_domain(word).is_some_and(|ascii| ascii.is_ascii() && Self::validate_word(&ascii));
+        }
+        word.len() <= 253
+            && word.split('.').all(|label| {
+                !label.is_empty()
+                    && label.len() <= 63
+                    && !label.starts_with('-')
+                    && !label.ends_with('-')
+                    && label
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+            })
+            // Encoding hides a leading hyphen (`-café` -> `xn---caf-epa`)
+            && to_unicode_domain(word).split('.').all(|label| !label.starts_with('-') && !label.ends_with('-'))
+    }
+
+    /// Stream the validated words of every registered wordlist, in file order, without
+    /// loading them all. Duplicates across files are dropped by a Bloom filter, so memory
+    /// stays bounded; see `STREAM_DEDUP_CAPACITY`. Unreadable files are skipped with a
+    /// warning, or with `strict` set rejected here before any word is yielded.
+    pub fn stream_words(&self) -> Result<WordStream, WordlistError> {
+        if self.wordlists.is_empty() {
+            return Err(WordlistError::NoWordlists);
+        }
+        if self.strict {
+            for path in &self.wordlists {
+                File::open(path).map_err(|e| WordlistError::IoError(path.clone(), e.to_string()))?;
+            }
+        }
+        Ok(WordStream {
+            paths: self.wordlists.clone().into_iter(),
+            current: None,
+            skip_chars: self.skip_chars.clone(),
+            seen: BloomFilter::new(STREAM_DEDUP_CAPACITY, STREAM_DEDUP_FP_RATE),
+        })
+    }
+
+    /// Streaming counterpart of `build_candidates`: candidates are produced as the
+    /// wordlists are read, so scanning can start before the last file is opened.
+    pub fn stream_candidates(
+        &self,
+        domain: &str,
+        case: LabelCase,
+    ) -> Result<impl Iterator<Item = String>, WordlistError> {
+        let domain = domain.to_string();
+        Ok(self.stream_words()?.flat_map(move |word| label_candidates(&word, &domain, case)))
+    }
+
+    /// Derive new labels from the loaded words: `word-affix`, `affix-word`, `word.affix`
+    /// for each affix, and `word1`-style numeric suffixes. Labels that fail `validate_word`
+    /// or are already loaded are dropped. Words and affixes are walked in order and
+    /// generation stops at `max` labels, so memory stays bounded on large wordlists.
+    pub fn generate_permutations(&self, patterns: &[String], max: usize) -> HashSet<String> {
+        let mut permutations = HashSet::new();
+        for word in &self.labels {
+            let mut variants = label_variants(word, patterns);
+            variants.extend(patterns.iter().map(|affix| format!("{}.{}", word, affix)));
+
+            for variant in variants {
+                if permutations.len() >= max {
+                    return permutations;
+                }
+                if Self::validate_word(&variant) && !self.loaded_words.contains(&variant) {
+                    permutations.insert(variant);
+                }
+            }
+        }
+        permutations
+    }
+
+    /// Multi-label names for `--label-depth`: every loaded word followed by up to
+    /// `levels - 1` of the `NESTED_INNER_WORDS` shortest words (`api.dev`, `api.dev.eu`).
+    /// A word is never repeated within a name. Two-label names are all generated before
+    /// three-label ones, inner words are walked shortest first, and generation stops at
+    /// `max` names.
+    pub fn generate_nested(&self, levels: usize, max: usize) -> HashSet<String> {
+        let mut inner: Vec<&String> = self.labels.iter().collect();
+        inner.sort_by_key(|word| word.len());
+        inner.truncate(NESTED_INNER_WORDS);
+
+        let mut nested = HashSet::new();
+        let mut previous = self.labels.clone();
+        for _ in 2..=levels {
+            let mut next = Vec::new();
+            for word in &inner {
+                for name in &previous {
+                    if nested.len() >= max {
+                        return nested;
+                    }
+                    if name.split('.').any(|label| label == word.as_str()) {
+                        continue;
+                    }
+                    let candidate = format!("{}.{}", name, word);
+                    if Self::validate_word(&candidate)
+                        && !self.loaded_words.contains(&candidate)
+                        && nested.insert(candidate.clone())
+                    {
+                        next.push(candidate);
+                    }
+                }
+            }
+            previous = next;
+        }
+        nested
+    }
+
+    /// Add labels (e.g. from `generate_permutations`) to those used by `build_candidates`.
+    pub fn extend_labels(&mut self, labels: HashSet<String>) {
+        self.labels.extend(labels.into_iter().filter(|label| !self.loaded_words.contains(label)));
+        self.labels.sort();
+        self.labels.dedup();
+    }
+
+    /// Join every loaded word with the target domain. Loading and validation happen
+    /// once in `load_all`; this only does the per-domain join. With `LabelCase::Both`
+    /// labels without letters are only emitted once.
+    pub fn build_candidates(&self, domain: &str, case: LabelCase) -> Vec<String> {
+        self.labels.iter().flat_map(|word| label_candidates(word, domain, case)).collect()
+    }
+
+    fn info(&self, message: &str) {
+        say!("{} {}", "[*]".blue(), message);
+    }
+
+    fn warn(&self, message: &str) {
+        say!("{} {}", "[!]".yellow(), message);
+    }
+}
+
+/// Open a wordlist for reading, decompressing it on the fly when it ends in `.gz`.
+fn open_wordlist(path: &Path) -> std::io::Result<Box<dyn BufRead + Send>> {
+    let file = File::open(path)?;
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        Ok(Box::new(BufReader::new(GzDecoder::new(file))))
+    } else {
+        Ok(Box::new(BufReader::new(file)))
+    }
+}
+
+/// Trimmed, lowercased and punycode-encoded; `None` for blank lines and comments. Words
+/// that cannot be encoded are kept as they are, for `accepts_word` to reject.
+fn normalize_line(line: &str) -> Option<String> {
+    let word = line.trim().to_lowercase();
+    if word.is_empty() || word.starts_with('#') {
+        return None;
+    }
+    Some(to_ascii_domain(&word).unwrap_or(word))
+}
+
+fn accepts_word(word: &str, skip_chars: &str) -> bool {
+    !word.chars().any(|c| skip_chars.contains(c)) && WordlistManager::validate_word(word)
+}
+
+/// The candidates for one label under `domain` in the requested letter case.
+fn label_candidates(word: &str, domain: &str, case: LabelCase) -> Vec<String> {
+    let mut candidates = Vec::with_capacity(2);
+    if case != LabelCase::Upper {
+        candidates.push(format!("{}.{}", word, domain));
+    }
+    if case != LabelCase::Lower {
+        let upper = word.to_uppercase();
+        if case == LabelCase::Upper || upper != word {
+            candidates.push(format!("{}.{}", upper, domain));
+        }
+    }
+    candidates
+}
+
+/// Fixed-size Bloom filter for deduplicating streamed words in bounded memory.
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    hashes: u64,
+}
+
+impl BloomFilter {
+    /// Size the filter so `capacity` items stay under `false_positive_rate`.
+    pub fn new(capacity: usize, false_positive_rate: f64) -> Self {
+        let capacity = capacity.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-capacity * false_positive_rate.ln() / (ln2 * ln2)).ceil().max(64.0);
+        let hashes = (bits / capacity * ln2).round().max(1.0);
+        BloomFilter {
+            bits: vec![0; (bits as usize).div_ceil(64)],
+            hashes: hashes as u64,
+        }
+    }
+
+    /// Record `item`, returning `false` if it was (probably) seen before.
+    pub fn insert(&mut self, item: &str) -> bool {
+        let (h1, h2) = Self::hash_pair(item);
+        let len = self.bits.len() as u64 * 64;
+        let mut fresh = false;
+        for i in 0..self.hashes {
+            let bit = h1.wrapping_add(i.wrapping_mul(h2)) % len;
+            let (slot, mask) = ((bit / 64) as usize, 1u64 << (bit % 64));
+            if self.bits[slot] & mask == 0 {
+                self.bits[slot] |= mask;
+                fresh = true;
+            }
+        }
+        fresh
+    }
+
+    /// Two independent hashes, combined per probe as `h1 + i * h2`.
+    fn hash_pair(item: &str) -> (u64, u64) {
+        let mut hasher = DefaultHasher::new();
+        item.hash(&mut hasher);
+        let h1 = hasher.finish();
+        0xb10au16.hash(&mut hasher);
+        (h1, hasher.finish() | 1)
+    }
+}
+
+/// Iterator over validated, deduplicated words, returned by `WordlistManager::stream_words`.
+pub struct WordStream {
+    paths: std::vec::IntoIter<PathBuf>,
+    current: Option<(PathBuf, Lines<Box<dyn BufRead + Send>>)>,
+    skip_chars: String,
+    seen: BloomFilter,
+}
+
+impl Iterator for WordStream {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        loop {
+            let Some((path, lines)) = self.current.as_mut() else {
+                let path = self.paths.next()?;
+                match open_wordlist(&path) {
+                    Ok(reader) => self.current = Some((path, reader.lines())),
+                    Err(e) => say!("{} {}, skipping", "[!]".yellow(), WordlistError::IoError(path, e.to_string())),
+                }
+                continue;
+            };
+            match lines.next() {
+                Some(Ok(line)) => {
+                    if let Some(word) = normalize_line(&line) {
+                        if accepts_word(&word, &self.skip_chars) && self.seen.insert(&word) {
+                            return Some(word);
+                        }
+                    }
+                }
+                Some(Err(e)) => {
+                    say!(
+                        "{} {}, skipping the rest",
+                        "[!]".yellow(),
+                        WordlistError::IoError(path.clone(), e.to_string())
+                    );
+                    self.current = None;
+                }
+                None => self.current = None,
+            }
+        }
+    }
+}
+
+/// Every label of 1 to `max_len` characters from `BRUTE_CHARSET` that does not start
+/// or end with a hyphen, shortest first and in charset order. Labels are produced one
+/// at a time, so the set is never held in memory.
+pub struct CharsetLabels {
+    charset: Vec<char>,
+    /// Charset positions of the next label's characters
+    indices: Vec<usize>,
+    max_len: usize,
+}
+
+impl CharsetLabels {
+    pub fn new(max_len: usize) -> Self {
+        CharsetLabels { charset: BRUTE_CHARSET.chars().collect(), indices: vec![0], max_len }
+    }
+
+    /// How many labels `CharsetLabels::new(max_len)` yields.
+    pub fn count_labels(max_len: usize) -> usize {
+        let all = BRUTE_CHARSET.len();
+        let ends = all - 1;
+        (1..=max_len)
+            .map(|len| if len == 1 { ends } else { ends * ends * all.pow(len as u32 - 2) })
+            .sum()
+    }
+}
+
+impl Iterator for CharsetLabels {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        loop {
+            if self.indices.len() > self.max_len {
+                return None;
+            }
+            let label: String = self.indices.iter().map(|&i| self.charset[i]).collect();
+
+            // Advance like an odometer, growing by one character after the last label
+            let mut position = self.indices.len();
+            loop {
+                if position == 0 {
+                    self.indices = vec![0; self.indices.len() + 1];
+                    break;
+                }
+                position -= 1;
+                self.indices[position] += 1;
+                if self.indices[position] < self.charset.len() {
+                    break;
+                }
+                self.indices[position] = 0;
+            }
+
+            if !label.starts_with('-') && !label.ends_with('-') {
+                return Some(label);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn wordlist(lines: &[&str]) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "{}", lines.join("\n")).unwrap();
+        file
+    }
+
+    fn load(file: &NamedTempFile, skip_chars: &str) -> Vec<String> {
+        let mut manager = WordlistManager::new(skip_chars, true);
+        manager.add_wordlist(file.path());
+        let mut words: Vec<String> = manager.load_all().unwrap().iter().cloned().collect();
+        words.sort();
+        words
+    }
+
+    #[test]
+    fn test_default_skip_chars() {
+        let file = wordlist(&["www", "# comment", "api?x=1", "{{template}}", "_dmarc", "dev-api", ""]);
+        assert_eq!(load(&file, DEFAULT_SKIP_CHARS), vec!["_dmarc", "dev-api", "www"]);
+    }
+
+    #[test]
+    fn test_custom_skip_chars() {
+        let file = wordlist(&["www", "_dmarc", "dev-api", "mail"]);
+        assert_eq!(load(&file, "_-"), vec!["mail", "www"]);
+    }
+
+    #[test]
+    fn test_empty_skip_chars() {
+        // Without a blacklist only validate_word filters lines
+        let file = wordlist(&["www", "_dmarc", "dev-api", "api?x=1"]);
+        assert_eq!(load(&file, ""), vec!["_dmarc", "dev-api", "www"]);
+    }
+
+    #[test]
+    fn test_build_candidates() {
+        let file = wordlist(&["www", "mail"]);
+        let mut manager = WordlistManager::new(DEFAULT_SKIP_CHARS, true);
+        manager.add_wordlist(file.path());
+        manager.load_all().unwrap();
+        assert_eq!(manager.build_candidates("example.com", LabelCase::Lower), vec!["mail.example.com", "www.example.com"]);
+
+        // The same loaded set is applied to further domains without reloading
+        std::fs::remove_file(file.path()).unwrap();
+        assert_eq!(manager.build_candidates("example.org", LabelCase::Lower), vec!["mail.example.org", "www.example.org"]);
+    }
+
+    #[test]
+    fn test_generate_permutations() {
+        let file = wordlist(&["api", "api-dev"]);
+        let mut manager = WordlistManager::new(DEFAULT_SKIP_CHARS, true);
+        manager.add_wordlist(file.path());
+        manager.load_all().unwrap();
+
+        let patterns = vec!["dev".to_string(), "-bad".to_string()];
+        let permutations = manager.generate_permutations(&patterns, DEFAULT_MAX_PERMUTATIONS);
+        for expected in ["dev-api", "api.dev", "api1", "api01", "api-dev-dev", "api-dev2"] {
+            assert!(permutations.contains(expected), "missing {}", expected);
+        }
+        // Already loaded, or not a valid label
+        assert!(!permutations.contains("api-dev"));
+        assert!(!permutations.contains("-bad-api"));
+        assert!(!permutations.contains("api.-bad"));
+
+        assert_eq!(manager.generate_permutations(&patterns, 4).len(), 4);
+
+        manager.extend_labels(manager.generate_permutations(&["qa".to_string()], 1));
+        assert_eq!(
+            manager.build_candidates("example.com", LabelCase::Lower),
+            vec!["api.example.com", "api-dev.example.com", "api-qa.example.com"]
+        );
+    }
+
+    #[test]
+    fn test_charset_labels() {
+        let one: Vec<String> = CharsetLabels::new(1).collect();
+        assert_eq!(one.len(), 36);
+        assert_eq!((one[0].as_str(), one[35].as_str()), ("a", "9"));
+
+        let labels: Vec<String> = CharsetLabels::new(3).collect();
+        assert_eq!(labels.len(), CharsetLabels::count_labels(3));
+        assert_eq!(labels.len(), 36 + 36 * 36 + 36 * 37 * 36);
+        assert_eq!(&labels[36..38], ["aa", "ab"]);
+        assert_eq!(labels.last().unwrap(), "9-9");
+        assert!(labels.contains(&"a-b".to_string()));
+        assert!(labels.iter().all(|label| WordlistManager::validate_word(label)));
+        assert_eq!(labels.iter().collect::<HashSet<_>>().len(), labels.len());
+        assert_eq!(CharsetLabels::new(0).next(), None);
+    }
+
+    #[test]
+    fn test_generate_nested() {
+        let file = wordlist(&["api", "dev", "staging"]);
+        let mut manager = WordlistManager::new(DEFAULT_SKIP_CHARS, true);
+        manager.add_wordlist(file.path());
+        manager.load_all().unwrap();
+
+        let mut two: Vec<String> = manager.generate_nested(2, DEFAULT_MAX_NESTED).into_iter().collect();
+        two.sort();
+        assert_eq!(two, ["api.dev", "api.staging", "dev.api", "dev.staging", "staging.api", "staging.dev"]);
+
+        let three = manager.generate_nested(3, DEFAULT_MAX_NESTED);
+        assert_eq!(three.len(), 12);
+        assert!(three.contains("staging.api.dev"));
+        assert!(!three.contains("api.dev.api"));
+
+        // Shortest inner words come first, so a tight cap keeps `.api` and `.dev` names
+        let capped = manager.generate_nested(3, 4);
+        assert_eq!(capped.len(), 4);
+        assert!(capped.iter().all(|name| !name.ends_with(".staging")));
+        assert!(manager.generate_nested(1, DEFAULT_MAX_NESTED).is_empty());
+    }
+
+    #[test]
+    fn test_stream_candidates() {
+        let first = wordlist(&["www", "mail", "api?x=1", "# comment"]);
+        let second = wordlist(&["MAIL", "dev", "www"]);
+        let missing = Path::new("/nonexistent/wordlist.txt");
+
+        let mut manager = WordlistManager::new(DEFAULT_SKIP_CHARS, false);
+        manager.add_wordlist(first.path());
+        manager.add_wordlist(missing);
+        manager.add_wordlist(second.path());
+        // File order is kept and duplicates across files are dropped
+        assert_eq!(
+            manager.stream_candidates("example.com", LabelCase::Lower).unwrap().collect::<Vec<_>>(),
+            vec!["www.example.com", "mail.example.com", "dev.example.com"]
+        );
+
+        manager.strict = true;
+        assert!(matches!(manager.stream_words(), Err(WordlistError::IoError(..))));
+    }
+
+    #[test]
+    fn test_bloom_filter() {
+        let mut filter = BloomFilter::new(1_000, 0.001);
+        assert!(filter.insert("www"));
+        assert!(!filter.insert("www"));
+        let fresh = (0..1_000).filter(|i| filter.insert(&format!("word{}", i))).count();
+        assert!(fresh >= 995, "{} of 1000 new words were kept", fresh);
+    }
+
+    #[test]
+    fn test_continue_on_wordlist_error() {
+        let good = wordlist(&["www", "mail"]);
+        let empty = wordlist(&["# nothing here"]);
+        let missing = Path::new("/nonexistent/wordlist.txt");
+
+        let mut manager = WordlistManager::new(DEFAULT_SKIP_CHARS, false);
+        manager.add_wordlist(missing);
+        manager.add_wordlist(empty.path());
+        manager.add_wordlist(good.path());
+        assert_eq!(manager.load_all().unwrap().len(), 2);
+        assert_eq!(manager.failed_wordlists(), &[missing.to_path_buf(), empty.path().to_path_buf()]);
+
+        let mut strict = WordlistManager::new(DEFAULT_SKIP_CHARS, true);
+        strict.add_wordlist(missing);
+        strict.add_wordlist(good.path());
+        assert!(matches!(strict.load_all(), Err(WordlistError::IoError(..))));
+
+        let mut lenient = WordlistManager::new(DEFAULT_SKIP_CHARS, false);
+        lenient.add_wordlist(missing);
+        assert!(matches!(lenient.load_all(), Err(WordlistError::AllFailed(1))));
+    }
+
+    #[test]
+    fn test_add_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "www\n").unwrap();
+        std::fs::write(dir.path().join("b.txt"), "api\n").unwrap();
+        std::fs::write(dir.path().join("notes.md"), "ignored\n").unwrap();
+
+        let mut manager = WordlistManager::new(DEFAULT_SKIP_CHARS, true);
+        assert_eq!(manager.add_directory(dir.path()).unwrap(), 2);
+        assert_eq!(manager.load_all().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_gzip_wordlist() {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+
+        let lines = ["www", "# comment", "api?x=1", "MAIL", "", "dev-api"];
+        let plain = wordlist(&lines);
+        let dir = tempfile::tempdir().unwrap();
+        let gzipped = dir.path().join("words.txt.gz");
+        let mut encoder = GzEncoder::new(File::create(&gzipped).unwrap(), Compression::default());
+        writeln!(encoder, "{}", lines.join("\n")).unwrap();
+        encoder.finish().unwrap();
+
+        let mut manager = WordlistManager::new(DEFAULT_SKIP_CHARS, true);
+        manager.add_wordlist(&gzipped);
+        let mut words: Vec<String> = manager.load_all().unwrap().iter().cloned().collect();
+        words.sort();
+        assert_eq!(words, load(&plain, DEFAULT_SKIP_CHARS));
+        assert_eq!(manager.stream_words().unwrap().count(), 3);
+
+        // Picked up from a directory alongside plain lists
+        let mut from_dir = WordlistManager::new(DEFAULT_SKIP_CHARS, true);
+        assert_eq!(from_dir.add_directory(dir.path()).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_label_case() {
+        let file = wordlist(&["www", "123"]);
+        let mut manager = WordlistManager::new(DEFAULT_SKIP_CHARS, true);
+        manager.add_wordlist(file.path());
+        manager.load_all().unwrap();
+
+        assert_eq!(manager.build_candidates("example.com", LabelCase::Upper), vec!["123.example.com", "WWW.example.com"]);
+        assert_eq!(
+            manager.build_candidates("example.com", LabelCase::Both),
+            vec!["123.example.com", "www.example.com", "WWW.example.com"]
+        );
+    }
+
+    #[test]
+    fn test_idn_words() {
+        let file = wordlist(&["café", "Bücher", "-café", "www"]);
+        assert_eq!(load(&file, DEFAULT_SKIP_CHARS), vec!["www", "xn--bcher-kva", "xn--caf-dma"]);
+
+        assert!(WordlistManager::validate_word("münchen"));
+        assert!(WordlistManager::validate_word("例え.テスト"));
+        assert!(!WordlistManager::validate_word("-münchen"));
+        assert!(!WordlistManager::validate_word("münchen-"));
+        assert!(!WordlistManager::validate_word("München"));
+        // 60 characters, but longer than 63 once encoded
+        assert!(!WordlistManager::validate_word(&"ü".repeat(60)));
+    }
+
+    #[test]
+    fn test_load_defaults() {
+        let mut manager = WordlistManager::new(DEFAULT_SKIP_CHARS, true);
+        let words = manager.load_defaults();
+        assert_eq!(words.len(), DEFAULT_PREFIXES.len());
+        assert!(words.iter().all(|word| WordlistManager::validate_word(word)));
+        assert!(manager.build_candidates("example.com", LabelCase::Lower).contains(&"www.example.com".to_string()));
+
+        // The skip list applies to the defaults too
+        let mut manager = WordlistManager::new("w", true);
+        assert!(!manager.load_defaults().contains("www"));
+    }
+}
@@ -0,0 +1,132 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::Command;
+use std::sync::mpsc;
+use trust_dns_resolver::proto::op::{Message, MessageType, ResponseCode};
+use trust_dns_resolver::proto::rr::{rdata, RData, Record, RecordType};
+use trust_dns_resolver::proto::serialize::binary::BinEncodable;
+
+/// Names under `example.test` that resolve and their address; everything else is NXDOMAIN.
+/// Only `www` has a public address.
+const RECORDS: &[(&str, [u8; 4])] = &[
+    ("www.example.test.", [93, 184, 216, 34]),
+    ("mail.example.test.", [127, 0, 0, 2]),
+    ("api.example.test.", [127, 0, 0, 2]),
+    ("v2.api.example.test.", [127, 0, 0, 2]),
+];
+
+/// Serve an A record for each of `RECORDS`.
+fn nameserver() -> u16 {
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let port = socket.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        let mut buf = [0u8; 512];
+        while let Ok((len, peer)) = socket.recv_from(&mut buf) {
+            let Ok(query) = Message::from_vec(&buf[..len]) else { continue };
+            let mut response = Message::new();
+            response.set_id(query.id()).set_message_type(MessageType::Response).set_recursion_available(true);
+            response.set_response_code(ResponseCode::NXDomain);
+            for question in query.queries() {
+                response.add_query(question.clone());
+                let name = question.name().to_utf8();
+                if let Some((_, address)) = RECORDS.iter().find(|(record, _)| *record == name) {
+                    response.set_response_code(ResponseCode::NoError);
+                    if question.query_type() == RecordType::A {
+                        let data = RData::A(rdata::A((*address).into()));
+                        response.add_answer(Record::from_rdata(question.name().clone(), 60, data));
+                    }
+                }
+            }
+            let _ = socket.send_to(&response.to_bytes().unwrap(), peer);
+        }
+    });
+    port
+}
+
+/// Accept HTTP requests, answer each with `200 OK` and send its body down the channel.
+fn webhook() -> (u16, mpsc::Receiver<serde_json::Value>) {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut content_length = 0;
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                if let Some((header, value)) = line.split_once(':') {
+                    if header.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+                line.clear();
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+            let _ = sender.send(serde_json::from_slice(&body).unwrap());
+        }
+    });
+    (port, receiver)
+}
+
+/// Run a quiet scan of `example.test` with these wordlist lines and extra arguments,
+/// returning the sorted stdout lines.
+fn quiet_scan(words: &str, extra: &[&str]) -> Vec<String> {
+    let port = nameserver();
+    let dir = tempfile::tempdir().unwrap();
+    let wordlist = dir.path().join("words.txt");
+    std::fs::write(&wordlist, words).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_SubTahu"))
+        .args(["-d", "example.test", "--quiet", "--resolvers", &format!("127.0.0.1:{}", port)])
+        .arg("-w")
+        .arg(&wordlist)
+        .args(extra)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let mut lines: Vec<String> = stdout.lines().map(str::to_string).collect();
+    lines.sort();
+    lines
+}
+
+#[test]
+fn test_quiet_prints_only_results() {
+    assert_eq!(quiet_scan("www\nmail\nmissing\n", &[]), vec!["mail.example.test", "www.example.test"]);
+}
+
+#[test]
+fn test_recursive_scan() {
+    // v2.api is only found by scanning the wordlist again under api
+    assert_eq!(quiet_scan("api\nv2\nwww\n", &[]), vec!["api.example.test", "www.example.test"]);
+    assert_eq!(
+        quiet_scan("api\nv2\nwww\n", &["--recursive", "--depth", "2"]),
+        vec!["api.example.test", "v2.api.example.test", "www.example.test"]
+    );
+}
+
+#[test]
+fn test_webhook_posts_filtered_results() {
+    let (port, bodies) = webhook();
+    let url = format!("http://127.0.0.1:{}/hook", port);
+    assert_eq!(
+        quiet_scan("www\nmail\n", &["--webhook", &url, "--exclude-private"]),
+        vec!["www.example.test"]
+    );
+
+    // A single batch carries the events and the stats; mail is private and never posted
+    let body = bodies.try_recv().unwrap();
+    assert!(bodies.try_recv().is_err());
+    let events = body["events"].as_array().unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0]["event"], "found");
+    assert_eq!(events[0]["subdomain"], "www.example.test");
+    assert_eq!(events[0]["ips"][0], "93.184.216.34");
+    assert_eq!(events[1]["event"], "enriched");
+    assert_eq!(events[1]["subdomain"], "www.example.test");
+    assert_eq!(events[1]["private"], false);
+    assert!(body["stats"].is_object());
+}